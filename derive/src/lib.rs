@@ -0,0 +1,229 @@
+//! The `#[derive(NbtCompound)]` macro of `neonmc`, documented in
+//! `neonmc::serialize::nbt::convert`, and the literals of its `nbt!` macro.
+
+extern crate proc_macro;
+
+mod literal;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, LitStr, Path, Type};
+
+const WIDTHS: [(&str, &str, &str); 9] = [
+    ("byte", "Byte", "i8"),
+    ("short", "Short", "i16"),
+    ("int", "Int", "i32"),
+    ("long", "Long", "i64"),
+    ("float", "Float", "f32"),
+    ("double", "Double", "f64"),
+    ("byte_array", "ByteArray", ""),
+    ("int_array", "IntArray", ""),
+    ("long_array", "LongArray", ""),
+];
+
+#[proc_macro_derive(NbtCompound, attributes(nbt))]
+pub fn derive_nbt_compound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __nbt_literal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let literal = syn::parse_macro_input!(input as literal::Literal);
+    literal.expand().unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __nbt_list(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    literal::list(input.into()).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __nbt_compound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    literal::compound(input.into()).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+enum Fallback {
+    None,
+    Trait,
+    Function(Path),
+}
+
+struct Field {
+    ident: syn::Ident,
+    key: String,
+    width: Option<usize>,
+    default: Fallback,
+    flatten: bool,
+    optional: bool,
+}
+
+impl Field {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let mut this = Field {
+            key: ident.to_string().trim_start_matches("r#").into(),
+            ident,
+            width: None,
+            default: Fallback::None,
+            flatten: false,
+            optional: is_option(&field.ty),
+        };
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("nbt")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    this.key = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    this.default = match meta.value() {
+                        Ok(value) => Fallback::Function(value.parse::<LitStr>()?.parse()?),
+                        Err(_) => Fallback::Trait,
+                    };
+                } else if meta.path.is_ident("flatten") {
+                    this.flatten = true;
+                } else if let Some(i) = WIDTHS.iter().position(|w| meta.path.is_ident(w.0)) {
+                    if this.width.is_some() {
+                        return Err(meta.error("more than one width"));
+                    }
+                    this.width = Some(i);
+                } else {
+                    return Err(meta.error("unknown nbt attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if this.flatten && (this.width.is_some() || this.optional || !matches!(this.default, Fallback::None)) {
+            return Err(syn::Error::new_spanned(field, "a flattened field takes no other nbt attribute"));
+        }
+        if this.optional && !matches!(this.default, Fallback::None) {
+            return Err(syn::Error::new_spanned(field, "an `Option` field takes no default, it is `None` when missing"));
+        }
+
+        Ok(this)
+    }
+
+    // the tag of `value`, a reference to the value of the field
+    fn writer(&self, value: TokenStream) -> TokenStream {
+        match self.width {
+            None => quote!(::neonmc::serialize::nbt::ToTag::to_tag(#value)),
+            Some(i) => {
+                let (_, variant, ty) = WIDTHS[i];
+                let variant = format_ident!("{}", variant);
+                if ty.is_empty() {
+                    quote!(::neonmc::serialize::nbt::Tag::#variant(::std::clone::Clone::clone(#value).into()))
+                } else {
+                    let ty = syn::Ident::new(ty, Span::call_site());
+                    quote!(::neonmc::serialize::nbt::Tag::#variant(*#value as #ty))
+                }
+            },
+        }
+    }
+
+    // the function that converts a tag to the value of the field
+    fn reader(&self) -> TokenStream {
+        match self.width {
+            None => quote!(::neonmc::serialize::nbt::convert::__from_tag),
+            Some(i) => {
+                let name = format_ident!("__{}", WIDTHS[i].0);
+                quote!(::neonmc::serialize::nbt::convert::#name)
+            },
+        }
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty.qself.is_none() && ty.path.segments.last().is_some_and(|s| s.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input, "NbtCompound needs named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input, "NbtCompound can only be derived for structs")),
+    };
+    let fields = fields.iter().map(Field::parse).collect::<syn::Result<Vec<_>>>()?;
+
+    let inserts = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let key = &f.key;
+        if f.flatten {
+            quote!(m.extend(::neonmc::serialize::nbt::NbtCompound::to_compound(&self.#ident));)
+        } else if f.optional {
+            let tag = f.writer(quote!(x));
+            quote! {
+                if let ::std::option::Option::Some(x) = &self.#ident {
+                    m.insert(::std::string::String::from(#key), #tag);
+                }
+            }
+        } else {
+            let tag = f.writer(quote!(&self.#ident));
+            quote!(m.insert(::std::string::String::from(#key), #tag);)
+        }
+    });
+
+    let gets = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let key = &f.key;
+        let from_tag = f.reader();
+        let get = quote!(::neonmc::serialize::nbt::convert::__get(m, #key, #from_tag)?);
+        let value = if f.flatten {
+            quote!(::neonmc::serialize::nbt::NbtCompound::from_compound(m)?)
+        } else if f.optional {
+            // converts the value inside the option
+            quote!(#get.map(::std::option::Option::Some).unwrap_or_default())
+        } else {
+            match &f.default {
+                Fallback::None => quote! {
+                    #get.ok_or_else(|| ::neonmc::serialize::nbt::convert::__missing(#key))?
+                },
+                Fallback::Trait => quote!(#get.unwrap_or_default()),
+                Fallback::Function(path) => quote!(#get.unwrap_or_else(#path)),
+            }
+        };
+        quote!(#ident: #value,)
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::neonmc::serialize::nbt::NbtCompound for #name #ty_generics #where_clause {
+            fn to_compound(&self) -> ::neonmc::serialize::nbt::Compound {
+                let mut m = ::neonmc::serialize::nbt::Compound::new();
+                #(#inserts)*
+                m
+            }
+
+            fn from_compound(
+                m: &::neonmc::serialize::nbt::Compound,
+            ) -> ::std::result::Result<Self, ::neonmc::serialize::nbt::convert::Error> {
+                ::std::result::Result::Ok(Self { #(#gets)* })
+            }
+        }
+
+        impl #impl_generics ::neonmc::serialize::nbt::ToTag for #name #ty_generics #where_clause {
+            fn to_tag(&self) -> ::neonmc::serialize::nbt::Tag {
+                ::neonmc::serialize::nbt::Tag::Compound(::neonmc::serialize::nbt::NbtCompound::to_compound(self))
+            }
+        }
+
+        impl #impl_generics ::neonmc::serialize::nbt::FromTag for #name #ty_generics #where_clause {
+            fn from_tag(
+                tag: &::neonmc::serialize::nbt::Tag,
+            ) -> ::std::result::Result<Self, ::neonmc::serialize::nbt::convert::Error> {
+                ::neonmc::serialize::nbt::NbtCompound::from_compound(
+                    ::neonmc::serialize::nbt::convert::__compound(tag)?,
+                )
+            }
+        }
+    })
+}
@@ -0,0 +1,148 @@
+//! The literals of `nbt!`, checked and typed at compile time, and the lists
+//! and compounds made of them.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Lit, LitStr, Token};
+
+pub(crate) struct Literal {
+    negative: bool,
+    lit: Lit,
+}
+
+impl Parse for Literal {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let negative = input.parse::<Option<Token![-]>>()?.is_some();
+        let lit = input.parse()?;
+        Ok(Literal { negative, lit })
+    }
+}
+
+// the digits of a number, with its sign
+fn signed(negative: bool, digits: &str) -> String {
+    if negative {
+        format!("-{}", digits)
+    } else {
+        digits.into()
+    }
+}
+
+fn number<T: std::str::FromStr>(lit: &Lit, digits: &str, message: &str) -> syn::Result<T> {
+    digits.parse().map_err(|_| syn::Error::new_spanned(lit, message))
+}
+
+fn float<T: std::str::FromStr + Into<f64> + Copy>(lit: &Lit, digits: &str, message: &str) -> syn::Result<T> {
+    number(lit, digits, message)
+        .ok()
+        .filter(|&x: &T| x.into().is_finite())
+        .ok_or_else(|| syn::Error::new_spanned(lit, message))
+}
+
+impl Literal {
+    pub(crate) fn expand(&self) -> syn::Result<TokenStream> {
+        let tag = quote!(::neonmc::serialize::nbt::Tag);
+        let lit = &self.lit;
+
+        // SNBT suffixes, in either case, or those of Rust
+        let (digits, suffix, is_float) = match lit {
+            Lit::Int(x) => (signed(self.negative, x.base10_digits()), x.suffix(), false),
+            Lit::Float(x) => (signed(self.negative, x.base10_digits()), x.suffix(), true),
+            Lit::Str(s) if !self.negative => {
+                let s = s.value();
+                return Ok(quote!(#tag::String(::std::string::String::from(#s))))
+            },
+            Lit::Bool(b) if !self.negative => {
+                let x = b.value as i8;
+                return Ok(quote!(#tag::Byte(#x)))
+            },
+            _ => return Err(syn::Error::new_spanned(lit, "expected a number, a string or a boolean")),
+        };
+
+        Ok(match suffix {
+            "b" | "B" | "i8" if !is_float => {
+                let x: i8 = number(lit, &digits, "byte out of range")?;
+                quote!(#tag::Byte(#x))
+            },
+            "s" | "S" | "i16" if !is_float => {
+                let x: i16 = number(lit, &digits, "short out of range")?;
+                quote!(#tag::Short(#x))
+            },
+            "" | "i32" if !is_float => {
+                let x: i32 = number(lit, &digits, "int out of range")?;
+                quote!(#tag::Int(#x))
+            },
+            "l" | "L" | "i64" if !is_float => {
+                let x: i64 = number(lit, &digits, "long out of range")?;
+                quote!(#tag::Long(#x))
+            },
+            "f" | "F" | "f32" => {
+                let x: f32 = float(lit, &digits, "invalid float")?;
+                quote!(#tag::Float(#x))
+            },
+            "" | "d" | "D" | "f64" => {
+                let x: f64 = float(lit, &digits, "invalid double")?;
+                quote!(#tag::Double(#x))
+            },
+            _ => return Err(syn::Error::new_spanned(lit, "unknown NBT literal suffix")),
+        })
+    }
+}
+
+// the values of a list or compound, split at the commas between them
+fn split(input: TokenStream) -> syn::Result<Vec<TokenStream>> {
+    let mut values = vec![TokenStream::new()];
+    for tt in input {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ',' => {
+                if values.last().is_some_and(TokenStream::is_empty) {
+                    return Err(syn::Error::new_spanned(p, "expected a value"))
+                }
+                values.push(TokenStream::new());
+            },
+            _ => values.last_mut().unwrap().extend(Some(tt)),
+        }
+    }
+
+    // a trailing comma
+    if values.last().is_some_and(TokenStream::is_empty) {
+        values.pop();
+    }
+    Ok(values)
+}
+
+pub(crate) fn list(input: TokenStream) -> syn::Result<TokenStream> {
+    let elems = split(input)?;
+    Ok(quote!(::neonmc::serialize::nbt::Tag::list(::std::vec![#(::neonmc::nbt!(#elems)),*])))
+}
+
+pub(crate) fn compound(input: TokenStream) -> syn::Result<TokenStream> {
+    let mut inserts = Vec::new();
+    for entry in split(input)? {
+        let mut tokens = entry.into_iter();
+
+        // keys are identifiers or string literals
+        let key = match tokens.next() {
+            Some(TokenTree::Ident(key)) => key.to_string(),
+            Some(TokenTree::Literal(key)) => syn::parse2::<LitStr>(TokenTree::Literal(key).into())?.value(),
+            other => return Err(syn::Error::new_spanned(other, "expected a key")),
+        };
+        let colon = match tokens.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ':' => p,
+            other => return Err(syn::Error::new_spanned(other, "expected `:`")),
+        };
+        let value: TokenStream = tokens.collect();
+        if value.is_empty() {
+            return Err(syn::Error::new_spanned(colon, "expected a value"))
+        }
+
+        inserts.push(quote!(m.insert(::std::string::String::from(#key), ::neonmc::nbt!(#value));));
+    }
+
+    Ok(quote! {{
+        #[allow(unused_mut)]
+        let mut m = ::neonmc::serialize::nbt::Compound::new();
+        #(#inserts)*
+        ::neonmc::serialize::nbt::Tag::Compound(m)
+    }})
+}
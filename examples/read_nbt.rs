@@ -1,8 +1,6 @@
 use std::fs::File;
-use flate2::bufread::GzDecoder;
-use std::io::{self, Read, BufRead, BufReader, Stdin};
+use std::io::{self, Read, BufReader, Stdin};
 
-use neonmc::serialize::DataInput;
 use neonmc::serialize::nbt::NBT;
 
 enum In {
@@ -19,23 +17,7 @@ impl Read for In {
     }
 }
 
-impl BufRead for In {
-    fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        match self {
-            In::Stdin(f) => f.fill_buf(),
-            In::File(f) => f.fill_buf(),
-        }
-    }
-
-    fn consume(&mut self, amt: usize) {
-        match self {
-            In::Stdin(f) => f.consume(amt),
-            In::File(f) => f.consume(amt),
-        }
-    }
-}
-
-fn open_nbt_file(path: &str) -> io::Result<GzDecoder<In>> {
+fn open_nbt_file(path: &str) -> io::Result<In> {
     let r = if path == "<stdin>" {
         let stdin = BufReader::new(io::stdin());
         In::Stdin(stdin)
@@ -43,7 +25,7 @@ fn open_nbt_file(path: &str) -> io::Result<GzDecoder<In>> {
         let f = BufReader::new(File::open(path)?);
         In::File(f)
     };
-    Ok(GzDecoder::new(r))
+    Ok(r)
 }
 
 fn main() -> io::Result<()> {
@@ -51,10 +33,9 @@ fn main() -> io::Result<()> {
         .nth(1)
         .unwrap_or(String::from("<stdin>"));
 
-    let gz = open_nbt_file(&path)?;
-    let mut input = DataInput::new(gz);
+    let r = open_nbt_file(&path)?;
 
-    let nbt = NBT::read_from(&mut input)?;
+    let (nbt, _) = NBT::read_compressed(r)?;
 
     println!("{:#?}", nbt);
     Ok(())
@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use neonmc::serialize::{DataInput, DataOutput};
+use neonmc::serialize::nbt::NBT;
+
+fn encode(nbt: &NBT) -> Option<Vec<u8>> {
+    let mut output = DataOutput::new(Vec::new());
+    nbt.write_to(&mut output).ok()?;
+    Some(output.into_inner())
+}
+
+fuzz_target!(|data: &[u8]| {
+    let nbt = match NBT::read_from(&mut DataInput::new(data)) {
+        Ok(nbt) => nbt,
+        Err(_) => return,
+    };
+
+    // strings may grow past 64 KiB once encoded canonically, which fails
+    let buf = match encode(&nbt) {
+        Some(buf) => buf,
+        None => return,
+    };
+
+    // what was decoded encodes to bytes that decode the same
+    let read = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap();
+    assert_eq!(encode(&read).unwrap(), buf);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use neonmc::serialize::{DataInput, DataOutput};
+
+fuzz_target!(|data: &[u8]| {
+    let s = match DataInput::new(data).read_utf() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    let mut output = DataOutput::new(Vec::new());
+    if output.write_utf(&s).is_ok() {
+        let buf = output.into_inner();
+        assert_eq!(DataInput::new(&buf[..]).read_utf().unwrap(), s);
+    }
+});
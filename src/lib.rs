@@ -1,4 +1,8 @@
+// lets `#[derive(NbtCompound)]` name this crate from within it
+extern crate self as neonmc;
+
 pub mod serialize;
+pub mod protocol;
 pub mod stepsound;
 pub mod material;
 pub mod common;
@@ -77,32 +77,28 @@ impl MaterialMeta {
 
     const fn burnable_material() -> Self {
         let material = Self::default_material();
-        let material = flip!(material, can_burn);
-        material
+        flip!(material, can_burn)
     }
 
     const fn logic_material() -> Self {
         let material = Self::default_material();
         let material = flip!(material, func_216_a);
         let material = flip!(material, func_219_b);
-        let material = flip!(material, func_218_c);
-        material
+        flip!(material, func_218_c)
     }
 
     const fn transparent_material() -> Self {
         let material = Self::default_material();
         let material = flip!(material, func_216_a);
         let material = flip!(material, func_219_b);
-        let material = flip!(material, func_218_c);
-        material
+        flip!(material, func_218_c)
     }
 
     const fn liquid_material() -> Self {
         let material = Self::default_material();
         let material = flip!(material, func_217_d);
         let material = flip!(material, func_218_c);
-        let material = flip!(material, func_216_a);
-        material
+        flip!(material, func_216_a)
     }
 }
 
@@ -0,0 +1,190 @@
+//! Split a stream of bytes into packets, and join them back.
+
+use std::io;
+
+use bytes::{Buf, BytesMut};
+
+use crate::protocol::{Incomplete, Packet};
+use crate::serialize::{BufInput, BufOutput, DataInput};
+
+impl Packet {
+    /// Reads a packet from a buffer, consuming its bytes only if it is
+    /// whole, and failing with a [`NeedMoreData`](crate::serialize::NeedMoreData)
+    /// error otherwise.
+    pub fn read_from_buf(input: &mut BufInput<'_>) -> io::Result<Packet> {
+        let available = input.remaining() as u64;
+        input.read_with(|input| Packet::read_within(input, available))
+    }
+}
+
+/// Frames the packets of a connection.
+///
+/// Packets are decoded as their bytes come in, and those of a packet that
+/// has not arrived whole are kept until the rest does. With the `tokio`
+/// feature, this is a `Decoder` and an `Encoder` of `tokio_util`, to be used
+/// with `Framed`.
+#[derive(Clone, Debug, Default)]
+pub struct PacketCodec {
+    // how many bytes the next packet needs at least
+    needed: usize,
+}
+
+impl PacketCodec {
+    /// Creates a new codec.
+    pub fn new() -> Self {
+        PacketCodec::default()
+    }
+
+    /// Removes the next packet from the front of `src`, or returns `None`
+    /// if it has not arrived whole yet, leaving `src` as it is.
+    ///
+    /// Once the length of an array of the packet is known, room is reserved
+    /// in `src` for the rest, and the packet is not decoded again until it
+    /// has come in.
+    pub fn read_packet(&mut self, src: &mut BytesMut) -> io::Result<Option<Packet>> {
+        if src.len() < self.needed {
+            return Ok(None)
+        }
+
+        let mut input = DataInput::new(&src[..]);
+        match Packet::read_within(&mut input, src.len() as u64) {
+            Ok(packet) => {
+                let len = input.position() as usize;
+                src.advance(len);
+                self.needed = 0;
+                Ok(Some(packet))
+            },
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.needed = match Incomplete::needed(&e) {
+                    Some(needed) => needed as usize,
+                    None => src.len() + 1,
+                };
+                src.reserve(self.needed - src.len());
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Appends `packet` to `dst`, which is left as it was if it fails.
+    pub fn write_packet(&mut self, packet: &Packet, dst: &mut BytesMut) -> io::Result<()> {
+        let len = dst.len();
+        let result = BufOutput::new(&mut *dst).write_with(|output| packet.write_to(output));
+        if result.is_err() {
+            dst.truncate(len);
+        }
+        result
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Decoder for PacketCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Packet>> {
+        self.read_packet(src)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Encoder<Packet> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut BytesMut) -> io::Result<()> {
+        self.write_packet(&packet, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use bytes::BytesMut;
+
+    use crate::protocol::{Packet, PacketCodec};
+
+    fn packets() -> Vec<Packet> {
+        vec![
+            Packet::Handshake { name: "Notch".to_string() },
+            Packet::PreChunk { x: 2, z: -3, load: true },
+            Packet::MapChunk {
+                x: 32,
+                y: 0,
+                z: -48,
+                size_x: 15,
+                size_y: 127,
+                size_z: 15,
+                data: (0..1 << 16).map(|x| x as u8).collect(),
+            },
+            Packet::KeepAlive,
+            Packet::Disconnect { reason: "bye".to_string() },
+        ]
+    }
+
+    #[test]
+    fn test_codec_incremental() {
+        let mut codec = PacketCodec::new();
+        let mut bytes = BytesMut::new();
+        for packet in packets() {
+            codec.write_packet(&packet, &mut bytes).unwrap();
+        }
+
+        // the bytes come in one at a time
+        let mut src = BytesMut::new();
+        let mut read = Vec::new();
+        for &b in &bytes[..] {
+            src.extend_from_slice(&[b]);
+            while let Some(packet) = codec.read_packet(&mut src).unwrap() {
+                read.push(packet);
+            }
+        }
+        assert_eq!(read, packets());
+        assert!(src.is_empty());
+
+        // room is made for the data of a chunk once its length is known
+        let mut src = BytesMut::from(&b"\x33\0\0\0\0\0\0\0\0\0\0\x0f\x7f\x0f\0\x01\0\0"[..]);
+        assert!(PacketCodec::new().read_packet(&mut src).unwrap().is_none());
+        assert!(src.capacity() >= 18 + (1 << 16));
+
+        // malformed bytes fail, rather than wait for more
+        let mut src = BytesMut::from(&b"\x33\0\0\0\0\0\0\0\0\0\0\x0f\x7f\x0f\xff\xff\xff\xff"[..]);
+        let e = codec.read_packet(&mut src).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // and so do writes, without writing
+        let mut dst = BytesMut::from(&b"\x00"[..]);
+        let changes = vec![(0, 0, 0); 1 << 15];
+        assert!(codec.write_packet(&Packet::MultiBlockChange { x: 0, z: 0, changes }, &mut dst).is_err());
+        assert_eq!(&dst[..], b"\x00");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_codec_framed() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_util::codec::Framed;
+
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = Framed::new(client, PacketCodec::new());
+        let mut server = Framed::new(server, PacketCodec::new());
+
+        let sent = packets();
+        let send = async {
+            for packet in packets() {
+                client.send(packet).await.unwrap();
+            }
+            drop(client);
+        };
+        let receive = async {
+            let mut read = Vec::new();
+            while let Some(packet) = server.next().await {
+                read.push(packet.unwrap());
+            }
+            read
+        };
+
+        let ((), read) = futures::join!(send, receive);
+        assert_eq!(read, sent);
+    }
+}
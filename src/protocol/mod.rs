@@ -0,0 +1,911 @@
+//! The packets of the alpha 1.2.6 network protocol.
+//!
+//! Every packet is a byte of its id followed by its fields, encoded as
+//! Java's `DataOutputStream` does, strings with `writeUTF`. Packets have no
+//! length of their own, so the fields of a packet must be read to find where
+//! the next one starts.
+//!
+//! Most packets have the same fields either way, though some mean different
+//! things to the client and to the server.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::error;
+use std::io::{self, Read, Write};
+
+use crate::serialize::{DataInput, DataOutput};
+
+mod codec;
+
+pub use codec::*;
+
+/// The version of the protocol that clients send when logging in.
+pub const PROTOCOL_VERSION: i32 = 6;
+
+/// The most bytes of the compressed data of a packet, such as a chunk's,
+/// that are read.
+pub const MAX_DATA: usize = 1 << 20;
+
+/// An item and how many of it there are.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub id: i16,
+    pub count: i8,
+    /// The wear of a tool, or the variant of other items.
+    pub damage: i16,
+}
+
+/// A packet of the alpha protocol.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    /// 0x00, sent to keep the connection open.
+    KeepAlive,
+    /// 0x01, sent by the client after the handshake, and by the server in
+    /// return.
+    Login {
+        /// The protocol version from the client, the player's entity id from
+        /// the server.
+        id: i32,
+        username: String,
+        password: String,
+        seed: i64,
+        dimension: i8,
+    },
+    /// 0x02, the first packet of a connection.
+    Handshake {
+        /// The username from the client, and from the server the hash of the
+        /// connection, `-` when players are not authenticated.
+        name: String,
+    },
+    /// 0x03, a chat message or command.
+    Chat { message: String },
+    /// 0x04, the time of the world, in ticks.
+    TimeUpdate { time: i64 },
+    /// 0x05, the items of a part of the player's inventory.
+    PlayerInventory {
+        /// -1 for the main inventory, -2 for armor and -3 for crafting.
+        kind: i32,
+        items: Vec<Option<ItemStack>>,
+    },
+    /// 0x06, where compasses point.
+    SpawnPosition { x: i32, y: i32, z: i32 },
+    /// 0x07, a player attacking or using an entity.
+    UseEntity { user: i32, target: i32, left_click: bool },
+    /// 0x08, the health of the player, out of 20.
+    UpdateHealth { health: i16 },
+    /// 0x09, a dead player coming back.
+    Respawn,
+    /// 0x0A, whether the player stands on the ground.
+    Player { on_ground: bool },
+    /// 0x0B, where the player is.
+    PlayerPosition {
+        x: f64,
+        y: f64,
+        /// The height of the player's eyes.
+        stance: f64,
+        z: f64,
+        on_ground: bool,
+    },
+    /// 0x0C, where the player looks.
+    PlayerLook { yaw: f32, pitch: f32, on_ground: bool },
+    /// 0x0D, where the player is and looks.
+    ///
+    /// The server sends `stance` where `y` is, and the other way round.
+    PlayerPositionLook {
+        x: f64,
+        y: f64,
+        stance: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool,
+    },
+    /// 0x0E, a player digging a block.
+    PlayerDigging {
+        /// 0 when digging starts, 1 while it goes on, 2 when it stops, 3 when
+        /// the block breaks and 4 when an item is dropped.
+        status: i8,
+        x: i32,
+        y: i8,
+        z: i32,
+        face: i8,
+    },
+    /// 0x0F, a player placing a block or using an item.
+    PlayerBlockPlacement { item: i16, x: i32, y: i8, z: i32, direction: i8 },
+    /// 0x10, the item that a player holds.
+    HoldingChange { entity: i32, item: i16 },
+    /// 0x11, an item given to the player.
+    AddToInventory { item: ItemStack },
+    /// 0x12, an entity swinging its arm.
+    Animation { entity: i32, animation: i8 },
+    /// 0x14, another player coming into view.
+    ///
+    /// Positions of entities are in 32ths of a block, and angles in 256ths of
+    /// a turn.
+    NamedEntitySpawn {
+        entity: i32,
+        name: String,
+        x: i32,
+        y: i32,
+        z: i32,
+        rotation: i8,
+        pitch: i8,
+        item: i16,
+    },
+    /// 0x15, an item lying on the ground.
+    PickupSpawn {
+        entity: i32,
+        item: i16,
+        count: i8,
+        x: i32,
+        y: i32,
+        z: i32,
+        rotation: i8,
+        pitch: i8,
+        roll: i8,
+    },
+    /// 0x16, an item picked up.
+    CollectItem { collected: i32, collector: i32 },
+    /// 0x17, a vehicle or projectile coming into view.
+    AddObject { entity: i32, kind: i8, x: i32, y: i32, z: i32 },
+    /// 0x18, a mob coming into view.
+    MobSpawn { entity: i32, kind: i8, x: i32, y: i32, z: i32, yaw: i8, pitch: i8 },
+    /// 0x1C, the velocity of an entity, in 8000ths of a block per tick.
+    EntityVelocity { entity: i32, x: i16, y: i16, z: i16 },
+    /// 0x1D, an entity going out of view.
+    DestroyEntity { entity: i32 },
+    /// 0x1E, an entity that did not move.
+    Entity { entity: i32 },
+    /// 0x1F, an entity moving by a little.
+    EntityMove { entity: i32, dx: i8, dy: i8, dz: i8 },
+    /// 0x20, an entity turning.
+    EntityLook { entity: i32, yaw: i8, pitch: i8 },
+    /// 0x21, an entity moving by a little and turning.
+    EntityMoveLook { entity: i32, dx: i8, dy: i8, dz: i8, yaw: i8, pitch: i8 },
+    /// 0x22, an entity moving by a lot.
+    EntityTeleport { entity: i32, x: i32, y: i32, z: i32, yaw: i8, pitch: i8 },
+    /// 0x26, an entity hurt or dead.
+    EntityStatus { entity: i32, status: i8 },
+    /// 0x27, an entity getting in or out of a vehicle, which is -1 then.
+    AttachEntity { entity: i32, vehicle: i32 },
+    /// 0x32, a chunk to allocate before its data comes, or to unload.
+    PreChunk { x: i32, z: i32, load: bool },
+    /// 0x33, the blocks of a region of the world.
+    MapChunk {
+        x: i32,
+        y: i16,
+        z: i32,
+        /// The size of the region along each axis, less one.
+        size_x: u8,
+        size_y: u8,
+        size_z: u8,
+        /// The kinds, metadata and light of the blocks, zlib compressed.
+        data: Vec<u8>,
+    },
+    /// 0x34, some blocks of a chunk changed.
+    MultiBlockChange {
+        x: i32,
+        z: i32,
+        /// The coordinates of each block, packed as `x << 12 | z << 8 | y`,
+        /// with its kind and metadata.
+        changes: Vec<(i16, i8, i8)>,
+    },
+    /// 0x35, a block changed.
+    BlockChange { x: i32, y: i8, z: i32, kind: i8, metadata: i8 },
+    /// 0x3B, the data of a sign, chest or other block entity.
+    ComplexEntity {
+        x: i32,
+        y: i16,
+        z: i32,
+        /// An NBT compound, gzip compressed.
+        data: Vec<u8>,
+    },
+    /// 0x3C, an explosion, with the blocks that it destroyed relative to it.
+    Explosion { x: f64, y: f64, z: f64, radius: f32, records: Vec<(i8, i8, i8)> },
+    /// 0xFF, the reason that the connection is closed.
+    Disconnect { reason: String },
+}
+
+/// The error of reading a packet whose id is not known, carried by an
+/// [`io::Error`] of kind [`io::ErrorKind::InvalidData`].
+#[derive(Debug)]
+pub struct UnknownPacket(pub u8);
+
+impl fmt::Display for UnknownPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown packet 0x{:02X}", self.0)
+    }
+}
+
+impl error::Error for UnknownPacket {}
+
+impl Packet {
+    /// Returns the id of this packet.
+    pub fn id(&self) -> u8 {
+        match self {
+            Packet::KeepAlive => 0x00,
+            Packet::Login { .. } => 0x01,
+            Packet::Handshake { .. } => 0x02,
+            Packet::Chat { .. } => 0x03,
+            Packet::TimeUpdate { .. } => 0x04,
+            Packet::PlayerInventory { .. } => 0x05,
+            Packet::SpawnPosition { .. } => 0x06,
+            Packet::UseEntity { .. } => 0x07,
+            Packet::UpdateHealth { .. } => 0x08,
+            Packet::Respawn => 0x09,
+            Packet::Player { .. } => 0x0a,
+            Packet::PlayerPosition { .. } => 0x0b,
+            Packet::PlayerLook { .. } => 0x0c,
+            Packet::PlayerPositionLook { .. } => 0x0d,
+            Packet::PlayerDigging { .. } => 0x0e,
+            Packet::PlayerBlockPlacement { .. } => 0x0f,
+            Packet::HoldingChange { .. } => 0x10,
+            Packet::AddToInventory { .. } => 0x11,
+            Packet::Animation { .. } => 0x12,
+            Packet::NamedEntitySpawn { .. } => 0x14,
+            Packet::PickupSpawn { .. } => 0x15,
+            Packet::CollectItem { .. } => 0x16,
+            Packet::AddObject { .. } => 0x17,
+            Packet::MobSpawn { .. } => 0x18,
+            Packet::EntityVelocity { .. } => 0x1c,
+            Packet::DestroyEntity { .. } => 0x1d,
+            Packet::Entity { .. } => 0x1e,
+            Packet::EntityMove { .. } => 0x1f,
+            Packet::EntityLook { .. } => 0x20,
+            Packet::EntityMoveLook { .. } => 0x21,
+            Packet::EntityTeleport { .. } => 0x22,
+            Packet::EntityStatus { .. } => 0x26,
+            Packet::AttachEntity { .. } => 0x27,
+            Packet::PreChunk { .. } => 0x32,
+            Packet::MapChunk { .. } => 0x33,
+            Packet::MultiBlockChange { .. } => 0x34,
+            Packet::BlockChange { .. } => 0x35,
+            Packet::ComplexEntity { .. } => 0x3b,
+            Packet::Explosion { .. } => 0x3c,
+            Packet::Disconnect { .. } => 0xff,
+        }
+    }
+
+    /// Reads a packet, its id and then its fields.
+    ///
+    /// Unknown ids fail with an [`UnknownPacket`] error, and data longer than
+    /// [`MAX_DATA`] with [`io::ErrorKind::InvalidData`].
+    pub fn read_from<R: Read>(input: &mut DataInput<R>) -> io::Result<Packet> {
+        Packet::read_within(input, u64::MAX)
+    }
+
+    // reads a packet from an input that has `available` bytes left, failing
+    // with an `Incomplete` error, before allocating anything, if an array
+    // does not fit in them
+    pub(crate) fn read_within<R: Read>(input: &mut DataInput<R>, available: u64) -> io::Result<Packet> {
+        let end = input.position().saturating_add(available);
+        let id = input.read_unsigned_byte()?;
+        let packet = match id {
+            0x00 => Packet::KeepAlive,
+            0x01 => Packet::Login {
+                id: input.read_int()?,
+                username: input.read_utf()?,
+                password: input.read_utf()?,
+                seed: input.read_long()?,
+                dimension: input.read_byte()?,
+            },
+            0x02 => Packet::Handshake { name: input.read_utf()? },
+            0x03 => Packet::Chat { message: input.read_utf()? },
+            0x04 => Packet::TimeUpdate { time: input.read_long()? },
+            0x05 => {
+                let kind = input.read_int()?;
+                let count = input.read_short()?.into();
+                let count = read_len(input, count, 2, end)?;
+                let mut items = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let id = input.read_short()?;
+                    items.push(if id < 0 { None } else { Some(read_item(input, id)?) });
+                }
+                Packet::PlayerInventory { kind, items }
+            },
+            0x06 => Packet::SpawnPosition {
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+            },
+            0x07 => Packet::UseEntity {
+                user: input.read_int()?,
+                target: input.read_int()?,
+                left_click: input.read_boolean()?,
+            },
+            0x08 => Packet::UpdateHealth { health: input.read_short()? },
+            0x09 => Packet::Respawn,
+            0x0a => Packet::Player { on_ground: input.read_boolean()? },
+            0x0b => Packet::PlayerPosition {
+                x: input.read_double()?,
+                y: input.read_double()?,
+                stance: input.read_double()?,
+                z: input.read_double()?,
+                on_ground: input.read_boolean()?,
+            },
+            0x0c => Packet::PlayerLook {
+                yaw: input.read_float()?,
+                pitch: input.read_float()?,
+                on_ground: input.read_boolean()?,
+            },
+            0x0d => Packet::PlayerPositionLook {
+                x: input.read_double()?,
+                y: input.read_double()?,
+                stance: input.read_double()?,
+                z: input.read_double()?,
+                yaw: input.read_float()?,
+                pitch: input.read_float()?,
+                on_ground: input.read_boolean()?,
+            },
+            0x0e => Packet::PlayerDigging {
+                status: input.read_byte()?,
+                x: input.read_int()?,
+                y: input.read_byte()?,
+                z: input.read_int()?,
+                face: input.read_byte()?,
+            },
+            0x0f => Packet::PlayerBlockPlacement {
+                item: input.read_short()?,
+                x: input.read_int()?,
+                y: input.read_byte()?,
+                z: input.read_int()?,
+                direction: input.read_byte()?,
+            },
+            0x10 => Packet::HoldingChange {
+                entity: input.read_int()?,
+                item: input.read_short()?,
+            },
+            0x11 => {
+                let id = input.read_short()?;
+                Packet::AddToInventory { item: read_item(input, id)? }
+            },
+            0x12 => Packet::Animation {
+                entity: input.read_int()?,
+                animation: input.read_byte()?,
+            },
+            0x14 => Packet::NamedEntitySpawn {
+                entity: input.read_int()?,
+                name: input.read_utf()?,
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+                rotation: input.read_byte()?,
+                pitch: input.read_byte()?,
+                item: input.read_short()?,
+            },
+            0x15 => Packet::PickupSpawn {
+                entity: input.read_int()?,
+                item: input.read_short()?,
+                count: input.read_byte()?,
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+                rotation: input.read_byte()?,
+                pitch: input.read_byte()?,
+                roll: input.read_byte()?,
+            },
+            0x16 => Packet::CollectItem {
+                collected: input.read_int()?,
+                collector: input.read_int()?,
+            },
+            0x17 => Packet::AddObject {
+                entity: input.read_int()?,
+                kind: input.read_byte()?,
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+            },
+            0x18 => Packet::MobSpawn {
+                entity: input.read_int()?,
+                kind: input.read_byte()?,
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+                yaw: input.read_byte()?,
+                pitch: input.read_byte()?,
+            },
+            0x1c => Packet::EntityVelocity {
+                entity: input.read_int()?,
+                x: input.read_short()?,
+                y: input.read_short()?,
+                z: input.read_short()?,
+            },
+            0x1d => Packet::DestroyEntity { entity: input.read_int()? },
+            0x1e => Packet::Entity { entity: input.read_int()? },
+            0x1f => Packet::EntityMove {
+                entity: input.read_int()?,
+                dx: input.read_byte()?,
+                dy: input.read_byte()?,
+                dz: input.read_byte()?,
+            },
+            0x20 => Packet::EntityLook {
+                entity: input.read_int()?,
+                yaw: input.read_byte()?,
+                pitch: input.read_byte()?,
+            },
+            0x21 => Packet::EntityMoveLook {
+                entity: input.read_int()?,
+                dx: input.read_byte()?,
+                dy: input.read_byte()?,
+                dz: input.read_byte()?,
+                yaw: input.read_byte()?,
+                pitch: input.read_byte()?,
+            },
+            0x22 => Packet::EntityTeleport {
+                entity: input.read_int()?,
+                x: input.read_int()?,
+                y: input.read_int()?,
+                z: input.read_int()?,
+                yaw: input.read_byte()?,
+                pitch: input.read_byte()?,
+            },
+            0x26 => Packet::EntityStatus {
+                entity: input.read_int()?,
+                status: input.read_byte()?,
+            },
+            0x27 => Packet::AttachEntity {
+                entity: input.read_int()?,
+                vehicle: input.read_int()?,
+            },
+            0x32 => Packet::PreChunk {
+                x: input.read_int()?,
+                z: input.read_int()?,
+                load: input.read_boolean()?,
+            },
+            0x33 => {
+                let x = input.read_int()?;
+                let y = input.read_short()?;
+                let z = input.read_int()?;
+                let size_x = input.read_unsigned_byte()?;
+                let size_y = input.read_unsigned_byte()?;
+                let size_z = input.read_unsigned_byte()?;
+                let len = input.read_int()?.into();
+                let len = read_len(input, len, 1, end)?;
+                let data = input.read_unsigned_bytes(len)?;
+                Packet::MapChunk { x, y, z, size_x, size_y, size_z, data }
+            },
+            0x34 => {
+                let x = input.read_int()?;
+                let z = input.read_int()?;
+                let count = input.read_short()?.into();
+                let count = read_len(input, count, 4, end)?;
+                let mut coords = Vec::with_capacity(count);
+                for _ in 0..count {
+                    coords.push(input.read_short()?);
+                }
+                let kinds = input.read_bytes(count)?;
+                let metadata = input.read_bytes(count)?;
+
+                let changes = coords
+                    .into_iter()
+                    .zip(kinds)
+                    .zip(metadata)
+                    .map(|((at, kind), metadata)| (at, kind, metadata))
+                    .collect();
+                Packet::MultiBlockChange { x, z, changes }
+            },
+            0x35 => Packet::BlockChange {
+                x: input.read_int()?,
+                y: input.read_byte()?,
+                z: input.read_int()?,
+                kind: input.read_byte()?,
+                metadata: input.read_byte()?,
+            },
+            0x3b => {
+                let x = input.read_int()?;
+                let y = input.read_short()?;
+                let z = input.read_int()?;
+                let len = input.read_short()?.into();
+                let len = read_len(input, len, 1, end)?;
+                let data = input.read_unsigned_bytes(len)?;
+                Packet::ComplexEntity { x, y, z, data }
+            },
+            0x3c => {
+                let x = input.read_double()?;
+                let y = input.read_double()?;
+                let z = input.read_double()?;
+                let radius = input.read_float()?;
+                let count = input.read_int()?.into();
+                let count = read_len(input, count, 3, end)?;
+
+                let mut records = Vec::with_capacity(count);
+                for _ in 0..count {
+                    records.push((input.read_byte()?, input.read_byte()?, input.read_byte()?));
+                }
+                Packet::Explosion { x, y, z, radius, records }
+            },
+            0xff => Packet::Disconnect { reason: input.read_utf()? },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, UnknownPacket(id))),
+        };
+        Ok(packet)
+    }
+
+    /// Writes this packet, its id and then its fields.
+    ///
+    /// Arrays too long for the count before them, or longer than
+    /// [`MAX_DATA`], fail with [`io::ErrorKind::InvalidInput`].
+    pub fn write_to<W: Write>(&self, output: &mut DataOutput<W>) -> io::Result<()> {
+        output.write_unsigned_byte(self.id())?;
+        match self {
+            Packet::KeepAlive | Packet::Respawn => Ok(()),
+            Packet::Login { id, username, password, seed, dimension } => {
+                output.write_int(*id)?;
+                output.write_utf(username)?;
+                output.write_utf(password)?;
+                output.write_long(*seed)?;
+                output.write_byte(*dimension)
+            },
+            Packet::Handshake { name } => output.write_utf(name),
+            Packet::Chat { message } => output.write_utf(message),
+            Packet::TimeUpdate { time } => output.write_long(*time),
+            Packet::PlayerInventory { kind, items } => {
+                output.write_int(*kind)?;
+                output.write_short(short_len(items.len())?)?;
+                for item in items {
+                    match item {
+                        Some(item) => write_item(output, item)?,
+                        None => output.write_short(-1)?,
+                    }
+                }
+                Ok(())
+            },
+            Packet::SpawnPosition { x, y, z } => {
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)
+            },
+            Packet::UseEntity { user, target, left_click } => {
+                output.write_int(*user)?;
+                output.write_int(*target)?;
+                output.write_boolean(*left_click)
+            },
+            Packet::UpdateHealth { health } => output.write_short(*health),
+            Packet::Player { on_ground } => output.write_boolean(*on_ground),
+            Packet::PlayerPosition { x, y, stance, z, on_ground } => {
+                output.write_double(*x)?;
+                output.write_double(*y)?;
+                output.write_double(*stance)?;
+                output.write_double(*z)?;
+                output.write_boolean(*on_ground)
+            },
+            Packet::PlayerLook { yaw, pitch, on_ground } => {
+                output.write_float(*yaw)?;
+                output.write_float(*pitch)?;
+                output.write_boolean(*on_ground)
+            },
+            Packet::PlayerPositionLook { x, y, stance, z, yaw, pitch, on_ground } => {
+                output.write_double(*x)?;
+                output.write_double(*y)?;
+                output.write_double(*stance)?;
+                output.write_double(*z)?;
+                output.write_float(*yaw)?;
+                output.write_float(*pitch)?;
+                output.write_boolean(*on_ground)
+            },
+            Packet::PlayerDigging { status, x, y, z, face } => {
+                output.write_byte(*status)?;
+                output.write_int(*x)?;
+                output.write_byte(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*face)
+            },
+            Packet::PlayerBlockPlacement { item, x, y, z, direction } => {
+                output.write_short(*item)?;
+                output.write_int(*x)?;
+                output.write_byte(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*direction)
+            },
+            Packet::HoldingChange { entity, item } => {
+                output.write_int(*entity)?;
+                output.write_short(*item)
+            },
+            Packet::AddToInventory { item } => write_item(output, item),
+            Packet::Animation { entity, animation } => {
+                output.write_int(*entity)?;
+                output.write_byte(*animation)
+            },
+            Packet::NamedEntitySpawn { entity, name, x, y, z, rotation, pitch, item } => {
+                output.write_int(*entity)?;
+                output.write_utf(name)?;
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*rotation)?;
+                output.write_byte(*pitch)?;
+                output.write_short(*item)
+            },
+            Packet::PickupSpawn { entity, item, count, x, y, z, rotation, pitch, roll } => {
+                output.write_int(*entity)?;
+                output.write_short(*item)?;
+                output.write_byte(*count)?;
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*rotation)?;
+                output.write_byte(*pitch)?;
+                output.write_byte(*roll)
+            },
+            Packet::CollectItem { collected, collector } => {
+                output.write_int(*collected)?;
+                output.write_int(*collector)
+            },
+            Packet::AddObject { entity, kind, x, y, z } => {
+                output.write_int(*entity)?;
+                output.write_byte(*kind)?;
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)
+            },
+            Packet::MobSpawn { entity, kind, x, y, z, yaw, pitch } => {
+                output.write_int(*entity)?;
+                output.write_byte(*kind)?;
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*yaw)?;
+                output.write_byte(*pitch)
+            },
+            Packet::EntityVelocity { entity, x, y, z } => {
+                output.write_int(*entity)?;
+                output.write_short(*x)?;
+                output.write_short(*y)?;
+                output.write_short(*z)
+            },
+            Packet::DestroyEntity { entity } | Packet::Entity { entity } => output.write_int(*entity),
+            Packet::EntityMove { entity, dx, dy, dz } => {
+                output.write_int(*entity)?;
+                output.write_byte(*dx)?;
+                output.write_byte(*dy)?;
+                output.write_byte(*dz)
+            },
+            Packet::EntityLook { entity, yaw, pitch } => {
+                output.write_int(*entity)?;
+                output.write_byte(*yaw)?;
+                output.write_byte(*pitch)
+            },
+            Packet::EntityMoveLook { entity, dx, dy, dz, yaw, pitch } => {
+                output.write_int(*entity)?;
+                output.write_byte(*dx)?;
+                output.write_byte(*dy)?;
+                output.write_byte(*dz)?;
+                output.write_byte(*yaw)?;
+                output.write_byte(*pitch)
+            },
+            Packet::EntityTeleport { entity, x, y, z, yaw, pitch } => {
+                output.write_int(*entity)?;
+                output.write_int(*x)?;
+                output.write_int(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*yaw)?;
+                output.write_byte(*pitch)
+            },
+            Packet::EntityStatus { entity, status } => {
+                output.write_int(*entity)?;
+                output.write_byte(*status)
+            },
+            Packet::AttachEntity { entity, vehicle } => {
+                output.write_int(*entity)?;
+                output.write_int(*vehicle)
+            },
+            Packet::PreChunk { x, z, load } => {
+                output.write_int(*x)?;
+                output.write_int(*z)?;
+                output.write_boolean(*load)
+            },
+            Packet::MapChunk { x, y, z, size_x, size_y, size_z, data } => {
+                output.write_int(*x)?;
+                output.write_short(*y)?;
+                output.write_int(*z)?;
+                output.write_unsigned_byte(*size_x)?;
+                output.write_unsigned_byte(*size_y)?;
+                output.write_unsigned_byte(*size_z)?;
+                output.write_int(int_len(data.len())?)?;
+                output.write_unsigned_bytes(data)
+            },
+            Packet::MultiBlockChange { x, z, changes } => {
+                output.write_int(*x)?;
+                output.write_int(*z)?;
+                output.write_short(short_len(changes.len())?)?;
+                for &(at, _, _) in changes {
+                    output.write_short(at)?;
+                }
+                for &(_, kind, _) in changes {
+                    output.write_byte(kind)?;
+                }
+                for &(_, _, metadata) in changes {
+                    output.write_byte(metadata)?;
+                }
+                Ok(())
+            },
+            Packet::BlockChange { x, y, z, kind, metadata } => {
+                output.write_int(*x)?;
+                output.write_byte(*y)?;
+                output.write_int(*z)?;
+                output.write_byte(*kind)?;
+                output.write_byte(*metadata)
+            },
+            Packet::ComplexEntity { x, y, z, data } => {
+                output.write_int(*x)?;
+                output.write_short(*y)?;
+                output.write_int(*z)?;
+                output.write_short(short_len(data.len())?)?;
+                output.write_unsigned_bytes(data)
+            },
+            Packet::Explosion { x, y, z, radius, records } => {
+                output.write_double(*x)?;
+                output.write_double(*y)?;
+                output.write_double(*z)?;
+                output.write_float(*radius)?;
+                output.write_int(int_len(records.len())?)?;
+                for &(dx, dy, dz) in records {
+                    output.write_byte(dx)?;
+                    output.write_byte(dy)?;
+                    output.write_byte(dz)?;
+                }
+                Ok(())
+            },
+            Packet::Disconnect { reason } => output.write_utf(reason),
+        }
+    }
+}
+
+// reads the rest of an item whose id is `id`
+fn read_item<R: Read>(input: &mut DataInput<R>, id: i16) -> io::Result<ItemStack> {
+    Ok(ItemStack {
+        id,
+        count: input.read_byte()?,
+        damage: input.read_short()?,
+    })
+}
+
+fn write_item<W: Write>(output: &mut DataOutput<W>, item: &ItemStack) -> io::Result<()> {
+    output.write_short(item.id)?;
+    output.write_byte(item.count)?;
+    output.write_short(item.damage)
+}
+
+// checks the count of an array, of elements of at least `size` bytes,
+// before reading it from an input that ends at `end`
+fn read_len<R>(input: &DataInput<R>, len: i64, size: u64, end: u64) -> io::Result<usize> {
+    if len < 0 || len as usize > MAX_DATA {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid array length"))
+    }
+
+    let needed = input.position() + len as u64 * size;
+    if needed > end {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, Incomplete(needed)))
+    }
+    Ok(len as usize)
+}
+
+// The error of an array that goes past the end of the input, with the
+// position that the packet reaches at least.
+#[derive(Debug)]
+struct Incomplete(u64);
+
+impl fmt::Display for Incomplete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "packet needs at least {} bytes", self.0)
+    }
+}
+
+impl error::Error for Incomplete {}
+
+impl Incomplete {
+    // returns the position that `e` tells a packet reaches
+    fn needed(e: &io::Error) -> Option<u64> {
+        e.get_ref()?.downcast_ref::<Incomplete>().map(|x| x.0)
+    }
+}
+
+fn short_len(len: usize) -> io::Result<i16> {
+    i16::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "array is too long"))
+}
+
+// the count of an array that is read back only up to `MAX_DATA`
+fn int_len(len: usize) -> io::Result<i32> {
+    if len > MAX_DATA {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "array is too long"))
+    }
+    Ok(len as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::protocol::{ItemStack, Packet, UnknownPacket, MAX_DATA, PROTOCOL_VERSION};
+    use crate::serialize::{DataInput, DataOutput};
+
+    fn encode(packet: &Packet) -> Vec<u8> {
+        let mut output = DataOutput::new(Vec::new());
+        packet.write_to(&mut output).unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_protocol_round_trip() {
+        let stone = ItemStack { id: 1, count: 64, damage: 0 };
+        let packets = vec![
+            Packet::KeepAlive,
+            Packet::Login {
+                id: PROTOCOL_VERSION,
+                username: "Notch".to_string(),
+                password: String::new(),
+                seed: 0,
+                dimension: 0,
+            },
+            Packet::Handshake { name: "-".to_string() },
+            Packet::Chat { message: "<Notch> h\u{e9}llo \0".to_string() },
+            Packet::PlayerInventory { kind: -1, items: vec![Some(stone), None, Some(stone)] },
+            Packet::PlayerPositionLook {
+                x: 0.5,
+                y: 64.0,
+                stance: 65.62,
+                z: -0.5,
+                yaw: 90.0,
+                pitch: 0.0,
+                on_ground: true,
+            },
+            Packet::PlayerBlockPlacement { item: 4, x: 1, y: 63, z: -1, direction: 1 },
+            Packet::AddToInventory { item: stone },
+            Packet::NamedEntitySpawn {
+                entity: 7,
+                name: "jeb_".to_string(),
+                x: 16,
+                y: 2048,
+                z: -16,
+                rotation: -128,
+                pitch: 0,
+                item: 0,
+            },
+            Packet::MapChunk {
+                x: 16,
+                y: 0,
+                z: -32,
+                size_x: 15,
+                size_y: 127,
+                size_z: 15,
+                data: vec![0x78, 0x9c, 0x03, 0x00],
+            },
+            Packet::MultiBlockChange { x: 1, z: 2, changes: vec![(0x1f40, 1, 0), (0x0001, 0, 0)] },
+            Packet::ComplexEntity { x: 3, y: 64, z: 4, data: vec![0x1f, 0x8b] },
+            Packet::Explosion { x: 1.0, y: 2.0, z: 3.0, radius: 3.0, records: vec![(0, -1, 0), (1, 0, 1)] },
+            Packet::Disconnect { reason: "Quitting".to_string() },
+        ];
+
+        // back to back, the way they are sent
+        let buf: Vec<u8> = packets.iter().flat_map(encode).collect();
+        let mut input = DataInput::new(&buf[..]);
+        for packet in &packets {
+            assert_eq!(&Packet::read_from(&mut input).unwrap(), packet);
+        }
+        assert_eq!(input.position(), buf.len() as u64);
+    }
+
+    #[test]
+    fn test_protocol_bytes() {
+        assert_eq!(encode(&Packet::Chat { message: "hi".to_string() }), b"\x03\x00\x02hi");
+        assert_eq!(
+            encode(&Packet::BlockChange { x: 1, y: 64, z: -1, kind: 20, metadata: 0 }),
+            b"\x35\x00\x00\x00\x01\x40\xff\xff\xff\xff\x14\x00",
+        );
+
+        let e = Packet::read_from(&mut DataInput::new(&b"\x13"[..])).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), UnknownPacket(0x13).to_string());
+
+        // a negative length
+        let e = Packet::read_from(&mut DataInput::new(&b"\x3b\0\0\0\0\0\0\0\0\0\0\xff\xff"[..])).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // arrays that could not be read back are not written
+        let chunk = Packet::MapChunk { x: 0, y: 0, z: 0, size_x: 0, size_y: 0, size_z: 0, data: vec![0; MAX_DATA + 1] };
+        let e = chunk.write_to(&mut DataOutput::new(Vec::new())).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let explosion = Packet::Explosion { x: 0.0, y: 0.0, z: 0.0, radius: 1.0, records: vec![(0, 0, 0); MAX_DATA + 1] };
+        let e = explosion.write_to(&mut DataOutput::new(Vec::new())).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
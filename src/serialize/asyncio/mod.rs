@@ -0,0 +1,366 @@
+//! Async counterparts of [`DataInput`] and [`DataOutput`], over the
+//! `AsyncRead` and `AsyncWrite` of tokio.
+//!
+//! Only the bytes are moved asynchronously: they are decoded and encoded by
+//! the sync types, so both read and write values the same way.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::serialize::{DataInput, DataOutput};
+
+/// Read Java-like encoded values from an [`AsyncRead`].
+///
+/// Every value is read with a call to the internal reader, which had better
+/// be buffered.
+pub struct AsyncDataInput<R> {
+    pos: u64,
+    r: R,
+}
+
+impl<R> AsyncDataInput<R> {
+    /// Creates a new async reader for Java-like encoded values.
+    pub fn new(r: R) -> Self {
+        Self { pos: 0, r }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the reader associated with this [`AsyncDataInput`].
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncDataInput<R> {
+    /// Read a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
+    pub async fn read_utf(&mut self) -> io::Result<String> {
+        let mut buf = vec![0; 2];
+        self.read_raw(&mut buf).await?;
+
+        let size = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        buf.resize(2 + size, 0);
+        self.read_raw(&mut buf[2..]).await?;
+
+        DataInput::new(&buf[..]).read_utf()
+    }
+
+    /// Read up to `count` bytes from the internal reader.
+    pub async fn read_bytes(&mut self, count: usize) -> io::Result<Vec<i8>> {
+        let buf = self.read_vec(count, 1).await?;
+        DataInput::new(&buf[..]).read_bytes(count)
+    }
+
+    /// Read up to `count` unsigned bytes from the internal reader.
+    pub async fn read_unsigned_bytes(&mut self, count: usize) -> io::Result<Vec<u8>> {
+        self.read_vec(count, 1).await
+    }
+
+    /// Read `count` words from the internal reader.
+    pub async fn read_ints(&mut self, count: usize) -> io::Result<Vec<i32>> {
+        let buf = self.read_vec(count, 4).await?;
+        DataInput::new(&buf[..]).read_ints(count)
+    }
+
+    /// Read `count` long values from the internal reader.
+    pub async fn read_longs(&mut self, count: usize) -> io::Result<Vec<i64>> {
+        let buf = self.read_vec(count, 8).await?;
+        DataInput::new(&buf[..]).read_longs(count)
+    }
+
+    /// Discard exactly `count` bytes from the internal reader.
+    pub async fn skip_bytes(&mut self, count: usize) -> io::Result<()> {
+        let skipped = tokio::io::copy(&mut (&mut self.r).take(count as u64), &mut tokio::io::sink()).await?;
+        self.pos += skipped;
+        if skipped < count as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(())
+    }
+
+    /// Read a single byte from the internal reader.
+    pub async fn read_byte(&mut self) -> io::Result<i8> {
+        let buf = self.read_array::<1>().await?;
+        DataInput::new(&buf[..]).read_byte()
+    }
+
+    /// Read a short value from the internal reader.
+    pub async fn read_short(&mut self) -> io::Result<i16> {
+        let buf = self.read_array::<2>().await?;
+        DataInput::new(&buf[..]).read_short()
+    }
+
+    /// Read a word from the internal reader.
+    pub async fn read_int(&mut self) -> io::Result<i32> {
+        let buf = self.read_array::<4>().await?;
+        DataInput::new(&buf[..]).read_int()
+    }
+
+    /// Read a long value from the internal reader.
+    pub async fn read_long(&mut self) -> io::Result<i64> {
+        let buf = self.read_array::<8>().await?;
+        DataInput::new(&buf[..]).read_long()
+    }
+
+    /// Read an [`f32`] value from the internal reader.
+    pub async fn read_float(&mut self) -> io::Result<f32> {
+        let buf = self.read_array::<4>().await?;
+        DataInput::new(&buf[..]).read_float()
+    }
+
+    /// Read an [`f64`] value from the internal reader.
+    pub async fn read_double(&mut self) -> io::Result<f64> {
+        let buf = self.read_array::<8>().await?;
+        DataInput::new(&buf[..]).read_double()
+    }
+
+    /// Read a single unsigned byte from the internal reader.
+    pub async fn read_unsigned_byte(&mut self) -> io::Result<u8> {
+        let buf = self.read_array::<1>().await?;
+        DataInput::new(&buf[..]).read_unsigned_byte()
+    }
+
+    /// Read a boolean from the internal reader, which is any byte but zero.
+    pub async fn read_boolean(&mut self) -> io::Result<bool> {
+        let buf = self.read_array::<1>().await?;
+        DataInput::new(&buf[..]).read_boolean()
+    }
+
+    /// Read an unsigned short value from the internal reader.
+    pub async fn read_unsigned_short(&mut self) -> io::Result<u16> {
+        let buf = self.read_array::<2>().await?;
+        DataInput::new(&buf[..]).read_unsigned_short()
+    }
+
+    /// Read a UTF-16 code unit from the internal reader, as Java's `char`.
+    pub async fn read_char(&mut self) -> io::Result<u16> {
+        let buf = self.read_array::<2>().await?;
+        DataInput::new(&buf[..]).read_char()
+    }
+
+    /// Read a [VarInt](DataInput::read_varint).
+    pub async fn read_varint(&mut self) -> io::Result<i32> {
+        let buf = self.read_var(5).await?;
+        DataInput::new(&buf[..]).read_varint()
+    }
+
+    /// Read a [VarLong](DataInput::read_varlong).
+    pub async fn read_varlong(&mut self) -> io::Result<i64> {
+        let buf = self.read_var(10).await?;
+        DataInput::new(&buf[..]).read_varlong()
+    }
+
+    /// Read bytes [prefixed by their count](DataInput::read_prefixed_bytes).
+    pub async fn read_prefixed_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = self.read_var(5).await?;
+        let count = DataInput::new(&buf[..]).read_varint()?;
+        if count < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "negative byte count"))
+        }
+
+        // fails as the sync version does if the bytes run out
+        let read = (&mut self.r).take(count as u64).read_to_end(&mut buf).await?;
+        self.pos += read as u64;
+        DataInput::new(&buf[..]).read_prefixed_bytes()
+    }
+
+    // reads the bytes of a varint, up to the last or the `max`th one
+    async fn read_var(&mut self, max: usize) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(max);
+        while buf.len() < max {
+            let [b] = self.read_array::<1>().await?;
+            buf.push(b);
+            if b & 0x80 == 0 {
+                break
+            }
+        }
+        Ok(buf)
+    }
+
+    // fills `buf` with bytes from the internal reader
+    pub(crate) async fn read_raw(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.r.read_exact(buf).await?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+
+    // reads `count` bytes onto the end of `buf`, keeping those that came
+    // before the input ended, if it did
+    pub(crate) async fn read_onto(&mut self, buf: &mut Vec<u8>, count: usize) -> io::Result<()> {
+        let read = (&mut self.r).take(count as u64).read_to_end(buf).await?;
+        self.pos += read as u64;
+        if read < count {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(())
+    }
+
+    async fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_raw(&mut buf).await?;
+        Ok(buf)
+    }
+
+    // reads `count` values of `size` bytes each
+    async fn read_vec(&mut self, count: usize, size: usize) -> io::Result<Vec<u8>> {
+        let len = count
+            .checked_mul(size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "array size overflows usize"))?;
+        let mut buf = vec![0; len];
+        self.read_raw(&mut buf).await?;
+        Ok(buf)
+    }
+}
+
+/// Write Java-like encoded values to an [`AsyncWrite`].
+pub struct AsyncDataOutput<W> {
+    pos: u64,
+    w: W,
+}
+
+impl<W> AsyncDataOutput<W> {
+    /// Creates a new async writer for Java-like encoded values.
+    pub fn new(w: W) -> Self {
+        Self { pos: 0, w }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the writer associated with this [`AsyncDataOutput`].
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+macro_rules! write_with {
+    ($($(#[$doc:meta])* $name:ident($x:ident: $ty:ty);)*) => {
+        $(
+            $(#[$doc])*
+            pub async fn $name(&mut self, $x: $ty) -> io::Result<()> {
+                let mut output = DataOutput::new(Vec::new());
+                output.$name($x)?;
+                self.write_raw(&output.into_inner()).await
+            }
+        )*
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncDataOutput<W> {
+    write_with! {
+        /// Write a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
+        write_utf(s: &str);
+        /// Write all the bytes in `buf` to the internal writer.
+        write_bytes(buf: &[i8]);
+        /// Write all the unsigned bytes in `buf` to the internal writer.
+        write_unsigned_bytes(buf: &[u8]);
+        /// Write all the words in `xs` to the internal writer.
+        write_ints(xs: &[i32]);
+        /// Write all the long values in `xs` to the internal writer.
+        write_longs(xs: &[i64]);
+        /// Write a single byte to the internal writer.
+        write_byte(x: i8);
+        /// Write a short value to the internal writer.
+        write_short(x: i16);
+        /// Write a word to the internal writer.
+        write_int(x: i32);
+        /// Write a long value to the internal writer.
+        write_long(x: i64);
+        /// Write an [`f32`] to the internal writer.
+        write_float(x: f32);
+        /// Write an [`f64`] to the internal writer.
+        write_double(x: f64);
+        /// Write a single unsigned byte to the internal writer.
+        write_unsigned_byte(x: u8);
+        /// Write a boolean to the internal writer, as a byte of one or zero.
+        write_boolean(x: bool);
+        /// Write an unsigned short value to the internal writer.
+        write_unsigned_short(x: u16);
+        /// Write a UTF-16 code unit to the internal writer, as Java's `char`.
+        write_char(x: u16);
+        /// Write a [VarInt](DataOutput::write_varint).
+        write_varint(x: i32);
+        /// Write a [VarLong](DataOutput::write_varlong).
+        write_varlong(x: i64);
+        /// Write bytes [prefixed by their count](DataOutput::write_prefixed_bytes).
+        write_prefixed_bytes(buf: &[u8]);
+    }
+
+    /// Flush the internal writer.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.w.flush().await
+    }
+
+    // writes `buf` to the internal writer
+    pub(crate) async fn write_raw(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.w.write_all(buf).await?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serialize::{AsyncDataInput, AsyncDataOutput, DataInput, DataOutput};
+
+    #[tokio::test]
+    async fn test_async_values() {
+        let mut output = AsyncDataOutput::new(Vec::new());
+        output.write_utf("caf\u{e9}\0\u{1f600}").await.unwrap();
+        output.write_bytes(&[1, -2]).await.unwrap();
+        output.write_ints(&[3, -4]).await.unwrap();
+        output.write_longs(&[i64::MIN]).await.unwrap();
+        output.write_byte(-5).await.unwrap();
+        output.write_short(6).await.unwrap();
+        output.write_int(7).await.unwrap();
+        output.write_long(8).await.unwrap();
+        output.write_float(9.5).await.unwrap();
+        output.write_double(-10.25).await.unwrap();
+        output.write_varint(-300).await.unwrap();
+        output.write_prefixed_bytes(&[0xff]).await.unwrap();
+        let pos = output.position();
+        let buf = output.into_inner();
+        assert_eq!(pos, buf.len() as u64);
+
+        // the same bytes as the sync writer
+        let mut sync = DataOutput::new(Vec::new());
+        sync.write_utf("caf\u{e9}\0\u{1f600}").unwrap();
+        sync.write_bytes([1, -2]).unwrap();
+        sync.write_ints([3, -4]).unwrap();
+        sync.write_longs([i64::MIN]).unwrap();
+        sync.write_byte(-5).unwrap();
+        sync.write_short(6).unwrap();
+        sync.write_int(7).unwrap();
+        sync.write_long(8).unwrap();
+        sync.write_float(9.5).unwrap();
+        sync.write_double(-10.25).unwrap();
+        sync.write_varint(-300).unwrap();
+        sync.write_prefixed_bytes([0xff]).unwrap();
+        assert_eq!(buf, sync.into_inner());
+
+        let mut input = AsyncDataInput::new(&buf[..]);
+        assert_eq!(input.read_utf().await.unwrap(), "caf\u{e9}\0\u{1f600}");
+        assert_eq!(input.read_bytes(2).await.unwrap(), [1, -2]);
+        assert_eq!(input.read_ints(2).await.unwrap(), [3, -4]);
+        assert_eq!(input.read_longs(1).await.unwrap(), [i64::MIN]);
+        assert_eq!(input.read_byte().await.unwrap(), -5);
+        assert_eq!(input.read_short().await.unwrap(), 6);
+        assert_eq!(input.read_int().await.unwrap(), 7);
+        assert_eq!(input.read_long().await.unwrap(), 8);
+        assert_eq!(input.read_float().await.unwrap(), 9.5);
+        assert_eq!(input.read_double().await.unwrap(), -10.25);
+        assert_eq!(input.read_varint().await.unwrap(), -300);
+        assert_eq!(input.read_prefixed_bytes().await.unwrap(), [0xff]);
+        assert_eq!(input.position(), pos);
+        assert!(input.skip_bytes(1).await.is_err());
+
+        let e = AsyncDataInput::new(&[0x00, 0x01, 0x80][..]).read_utf().await.unwrap_err();
+        assert_eq!(e.kind(), DataInput::new(&[0x00, 0x01, 0x80][..]).read_utf().unwrap_err().kind());
+    }
+}
@@ -0,0 +1,428 @@
+//! Read and write Java-like encoded values in memory, such as the frames of
+//! a network stream, which may not have arrived whole yet.
+//!
+//! A read from a [`BufInput`] that runs out of bytes fails with a
+//! [`NeedMoreData`] error, see [`is_incomplete`], and consumes nothing, so
+//! that it can be retried once more bytes are in.
+
+use std::fmt;
+use std::error;
+use std::io::{self, Read};
+
+use bytes::{Buf, BufMut};
+
+use crate::serialize::{DataInput, DataOutput};
+
+/// The error of reads that need more bytes than a [`BufInput`] has left,
+/// carried by an [`io::Error`] of kind [`io::ErrorKind::UnexpectedEof`].
+#[derive(Debug)]
+pub struct NeedMoreData;
+
+impl fmt::Display for NeedMoreData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("need more data")
+    }
+}
+
+impl error::Error for NeedMoreData {}
+
+/// Returns whether `e` is a [`NeedMoreData`] error, rather than one of
+/// malformed bytes.
+pub fn is_incomplete(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<NeedMoreData>())
+}
+
+fn need_more_data() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, NeedMoreData)
+}
+
+/// A position of a [`BufInput`] to rewind to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// Read Java-like encoded values from a slice of bytes.
+///
+/// Byte arrays are borrowed from the slice, rather than copied.
+#[derive(Clone, Debug)]
+pub struct BufInput<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BufInput<'a> {
+    /// Creates a new reader of the bytes of `buf`.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// Returns the bytes left to read.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Returns the current position, to [`rewind`](Self::rewind) to.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.pos)
+    }
+
+    /// Goes back to a position returned by [`checkpoint`](Self::checkpoint),
+    /// to read the bytes since once more.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.0.min(self.buf.len());
+    }
+
+    /// Reads a value with `f`, without consuming it.
+    pub fn peek<T, F>(&self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut BufInput<'a>) -> io::Result<T>,
+    {
+        f(&mut self.clone())
+    }
+
+    /// Reads a value with a [`DataInput`] over the bytes left, consuming
+    /// them only if it succeeds, which lets any decoder written for
+    /// [`DataInput`] tell an incomplete frame apart.
+    pub fn read_with<T, F>(&mut self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut DataInput<&'a [u8]>) -> io::Result<T>,
+    {
+        let mut input = DataInput::new(self.rest());
+        match f(&mut input) {
+            Ok(x) => {
+                self.pos += input.position() as usize;
+                Ok(x)
+            },
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(need_more_data()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
+    pub fn read_utf(&mut self) -> io::Result<String> {
+        self.read_with(DataInput::read_utf)
+    }
+
+    /// Read the next `count` bytes, borrowed.
+    pub fn read_slice(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let buf = self.rest().get(..count).ok_or_else(need_more_data)?;
+        self.pos += count;
+        Ok(buf)
+    }
+
+    /// Read bytes [prefixed by their count](DataInput::read_prefixed_bytes),
+    /// borrowed.
+    pub fn read_prefixed_slice(&mut self) -> io::Result<&'a [u8]> {
+        let checkpoint = self.checkpoint();
+        let count = self.read_varint()?;
+        if count < 0 {
+            self.rewind(checkpoint);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "negative byte count"))
+        }
+
+        self.read_slice(count as usize).inspect_err(|_| self.rewind(checkpoint))
+    }
+
+    /// Read the next `count` bytes as signed bytes, borrowed.
+    pub fn read_signed_slice(&mut self, count: usize) -> io::Result<&'a [i8]> {
+        self.read_slice(count).map(bytemuck::cast_slice)
+    }
+
+    /// Read `count` bytes.
+    pub fn read_bytes(&mut self, count: usize) -> io::Result<Vec<i8>> {
+        self.read_with(|input| input.read_bytes(count))
+    }
+
+    /// Read `count` unsigned bytes.
+    pub fn read_unsigned_bytes(&mut self, count: usize) -> io::Result<Vec<u8>> {
+        self.read_with(|input| input.read_unsigned_bytes(count))
+    }
+
+    /// Read `count` words.
+    pub fn read_ints(&mut self, count: usize) -> io::Result<Vec<i32>> {
+        self.read_with(|input| input.read_ints(count))
+    }
+
+    /// Read `count` long values.
+    pub fn read_longs(&mut self, count: usize) -> io::Result<Vec<i64>> {
+        self.read_with(|input| input.read_longs(count))
+    }
+
+    /// Discard exactly `count` bytes.
+    pub fn skip_bytes(&mut self, count: usize) -> io::Result<()> {
+        self.read_slice(count).map(drop)
+    }
+
+    /// Read a single byte.
+    pub fn read_byte(&mut self) -> io::Result<i8> {
+        self.read_with(DataInput::read_byte)
+    }
+
+    /// Read a single unsigned byte.
+    pub fn read_unsigned_byte(&mut self) -> io::Result<u8> {
+        self.read_with(DataInput::read_unsigned_byte)
+    }
+
+    /// Read a boolean, which is any byte but zero.
+    pub fn read_boolean(&mut self) -> io::Result<bool> {
+        self.read_with(DataInput::read_boolean)
+    }
+
+    /// Read a short value.
+    pub fn read_short(&mut self) -> io::Result<i16> {
+        self.read_with(DataInput::read_short)
+    }
+
+    /// Read an unsigned short value.
+    pub fn read_unsigned_short(&mut self) -> io::Result<u16> {
+        self.read_with(DataInput::read_unsigned_short)
+    }
+
+    /// Read a UTF-16 code unit, as Java's `char`.
+    pub fn read_char(&mut self) -> io::Result<u16> {
+        self.read_with(DataInput::read_char)
+    }
+
+    /// Read a word.
+    pub fn read_int(&mut self) -> io::Result<i32> {
+        self.read_with(DataInput::read_int)
+    }
+
+    /// Read a long value.
+    pub fn read_long(&mut self) -> io::Result<i64> {
+        self.read_with(DataInput::read_long)
+    }
+
+    /// Read an [`f32`] value.
+    pub fn read_float(&mut self) -> io::Result<f32> {
+        self.read_with(DataInput::read_float)
+    }
+
+    /// Read an [`f64`] value.
+    pub fn read_double(&mut self) -> io::Result<f64> {
+        self.read_with(DataInput::read_double)
+    }
+
+    /// Read a [VarInt](DataInput::read_varint).
+    pub fn read_varint(&mut self) -> io::Result<i32> {
+        self.read_with(DataInput::read_varint)
+    }
+
+    /// Read a [VarLong](DataInput::read_varlong).
+    pub fn read_varlong(&mut self) -> io::Result<i64> {
+        self.read_with(DataInput::read_varlong)
+    }
+}
+
+impl Buf for BufInput<'_> {
+    fn remaining(&self) -> usize {
+        BufInput::remaining(self)
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.rest()
+    }
+
+    fn advance(&mut self, count: usize) {
+        assert!(count <= self.remaining(), "advanced past the end of a BufInput");
+        self.pos += count;
+    }
+}
+
+impl Read for BufInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.rest().read(buf)?;
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+/// Write Java-like encoded values to a [`BufMut`], such as a `BytesMut` or a
+/// `Vec<u8>`.
+///
+/// Writes that do not fit in the buffer fail with
+/// [`io::ErrorKind::WriteZero`], having written as much as fits.
+#[derive(Debug)]
+pub struct BufOutput<B> {
+    buf: B,
+    pos: u64,
+}
+
+impl<B> BufOutput<B> {
+    /// Creates a new writer to the end of `buf`.
+    pub fn new(buf: B) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns a reference to the buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns the buffer associated with this [`BufOutput`].
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+
+macro_rules! write_with {
+    ($($(#[$doc:meta])* $name:ident($x:ident: $ty:ty);)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&mut self, $x: $ty) -> io::Result<()> {
+                self.write_with(|output| output.$name($x))
+            }
+        )*
+    }
+}
+
+impl<B: BufMut> BufOutput<B> {
+    /// Returns the number of bytes that can still be written.
+    pub fn remaining(&self) -> usize {
+        self.buf.remaining_mut()
+    }
+
+    /// Writes values with a [`DataOutput`] to the end of the buffer.
+    pub fn write_with<T, F>(&mut self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut DataOutput<bytes::buf::Writer<&mut B>>) -> io::Result<T>,
+    {
+        let mut output = DataOutput::with_position((&mut self.buf).writer(), self.pos);
+        let result = f(&mut output);
+        self.pos = output.position();
+        result
+    }
+
+    write_with! {
+        /// Write a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
+        write_utf(s: &str);
+        /// Write all the bytes in `buf`.
+        write_bytes(buf: &[i8]);
+        /// Write all the unsigned bytes in `buf`.
+        write_unsigned_bytes(buf: &[u8]);
+        /// Write all the words in `xs`.
+        write_ints(xs: &[i32]);
+        /// Write all the long values in `xs`.
+        write_longs(xs: &[i64]);
+        /// Write a single byte.
+        write_byte(x: i8);
+        /// Write a single unsigned byte.
+        write_unsigned_byte(x: u8);
+        /// Write a boolean, as a byte of one or zero.
+        write_boolean(x: bool);
+        /// Write a short value.
+        write_short(x: i16);
+        /// Write an unsigned short value.
+        write_unsigned_short(x: u16);
+        /// Write a UTF-16 code unit, as Java's `char`.
+        write_char(x: u16);
+        /// Write a word.
+        write_int(x: i32);
+        /// Write a long value.
+        write_long(x: i64);
+        /// Write an [`f32`].
+        write_float(x: f32);
+        /// Write an [`f64`].
+        write_double(x: f64);
+        /// Write a [VarInt](DataOutput::write_varint).
+        write_varint(x: i32);
+        /// Write a [VarLong](DataOutput::write_varlong).
+        write_varlong(x: i64);
+        /// Write bytes [prefixed by their count](DataOutput::write_prefixed_bytes).
+        write_prefixed_bytes(buf: &[u8]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use bytes::{Buf, BytesMut};
+
+    use crate::serialize::{is_incomplete, BufInput, BufOutput};
+    use crate::serialize::nbt::NBT;
+
+    #[test]
+    fn test_buffer_partial() {
+        let mut output = BufOutput::new(BytesMut::new());
+        output.write_int(-2).unwrap();
+        output.write_utf("caf\u{e9}").unwrap();
+        output.write_prefixed_bytes(&[1, 2, 3]).unwrap();
+        output.write_varlong(1 << 40).unwrap();
+        assert_eq!(output.position(), 4 + 7 + 4 + 6);
+        let buf = output.into_inner().freeze();
+
+        // every cut short frame is incomplete, and consumes nothing
+        for len in 0..buf.len() {
+            let mut input = BufInput::new(&buf[..len]);
+            let e = (|| {
+                input.read_int()?;
+                input.read_utf()?;
+                input.read_prefixed_slice()?;
+                input.read_varlong()
+            })().unwrap_err();
+            assert!(is_incomplete(&e), "{}", e);
+            assert!(input.position() <= len);
+        }
+
+        let mut input = BufInput::new(&buf[..3]);
+        assert!(is_incomplete(&input.read_int().unwrap_err()));
+        assert_eq!(input.position(), 0);
+
+        let mut input = BufInput::new(&buf);
+        assert_eq!(input.peek(BufInput::read_int).unwrap(), -2);
+        assert_eq!(input.position(), 0);
+        let checkpoint = input.checkpoint();
+        assert_eq!(input.read_int().unwrap(), -2);
+        assert_eq!(input.read_utf().unwrap(), "caf\u{e9}");
+        input.rewind(checkpoint);
+        input.advance(4 + 7);
+        assert_eq!(input.read_prefixed_slice().unwrap(), [1, 2, 3]);
+        assert_eq!(input.read_varlong().unwrap(), 1 << 40);
+        assert_eq!(input.remaining(), 0);
+
+        // malformed bytes are not incomplete
+        let e = BufInput::new(&[0x00, 0x01, 0x80]).read_utf().unwrap_err();
+        assert!(!is_incomplete(&e));
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // a writer that runs out of room
+        let mut room = [0u8; 3];
+        let mut output = BufOutput::new(&mut room[..]);
+        assert_eq!(output.remaining(), 3);
+        assert_eq!(output.write_int(1).unwrap_err().kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_buffer_read_with() {
+        let mut output = BufOutput::new(Vec::new());
+        let nbt: NBT = "{Data:{SpawnX:1,LevelName:\"x\"}}".parse().unwrap();
+        output.write_with(|output| nbt.write_to(output).map_err(io::Error::from)).unwrap();
+        output.write_byte(7).unwrap();
+        let buf = output.into_inner();
+
+        let mut input = BufInput::new(&buf[..buf.len() - 2]);
+        let e = input.read_with(|input| NBT::read_from(input).map_err(io::Error::from)).unwrap_err();
+        assert!(is_incomplete(&e));
+        assert_eq!(input.position(), 0);
+
+        let mut input = BufInput::new(&buf);
+        let read = input.read_with(|input| NBT::read_from(input).map_err(io::Error::from)).unwrap();
+        assert_eq!(read, nbt);
+        assert_eq!(input.rest(), [7]);
+    }
+}
@@ -1,16 +1,29 @@
+use std::fmt;
+use std::error;
 use std::io::{self, Read};
-use std::mem::ManuallyDrop;
 
 /// Read Java-like encoded values.
 pub struct DataInput<R> {
     buf: [u8; 8],
+    pos: u64,
     r: R,
 }
 
 impl<R> DataInput<R> {
     /// Creates a new reader for Java-like encoded values.
     pub fn new(r: R) -> Self {
-        Self { buf: [0; 8], r }
+        Self { buf: [0; 8], pos: 0, r }
+    }
+
+    // creates a reader that counts its position from `pos`
+    #[cfg(feature = "tokio")]
+    pub(crate) fn with_position(r: R, pos: u64) -> Self {
+        Self { buf: [0; 8], pos, r }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> u64 {
+        self.pos
     }
 
     /// Returns the reader associated with this [`DataInput`].
@@ -19,46 +32,221 @@ impl<R> DataInput<R> {
     }
 }
 
+/// Decodes a [modified UTF-8](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8)
+/// string, the way Java's `DataInput::readUTF` does.
+///
+/// Characters outside of the basic multilingual plane are encoded as a
+/// surrogate pair, each half in 3 bytes. Unpaired surrogates and any other
+/// malformed sequence fail with [`io::ErrorKind::InvalidData`].
+pub fn decode_utf(bytes: &[u8]) -> io::Result<String> {
+    let mut s = String::with_capacity(bytes.len());
+    let mut invalid = false;
+    let mut i = 0;
+
+    // the UTF-16 code units encoded by the bytes
+    let units = std::iter::from_fn(|| {
+        let unit = |at: usize| bytes.get(at).map(|&b| b as u16);
+        let more = |at: usize| unit(at).filter(|b| b & 0xc0 == 0x80).map(|b| b & 0x3f);
+
+        let a = unit(i)?;
+        let decoded = match a {
+            0x00..=0x7f => Some((a, 1)),
+            0xc0..=0xdf => more(i + 1).map(|b| ((a & 0x1f) << 6 | b, 2)),
+            0xe0..=0xef => more(i + 1)
+                .zip(more(i + 2))
+                .map(|(b, c)| ((a & 0x0f) << 12 | b << 6 | c, 3)),
+            _ => None,
+        };
+
+        match decoded {
+            Some((unit, len)) => {
+                i += len;
+                Some(unit)
+            },
+            None => {
+                invalid = true;
+                None
+            },
+        }
+    });
+
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => s.push(c),
+            Err(_) => return Err(invalid_utf()),
+        }
+    }
+
+    if invalid {
+        return Err(invalid_utf())
+    }
+
+    Ok(s)
+}
+
+// the size in bytes of `count` values of `size` bytes each
+fn array_size(count: usize, size: usize) -> io::Result<usize> {
+    count
+        .checked_mul(size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "array size overflows usize"))
+}
+
+// The error of strings that are not validly encoded.
+#[derive(Debug)]
+pub(crate) struct InvalidUtf;
+
+impl fmt::Display for InvalidUtf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid UTF string")
+    }
+}
+
+impl error::Error for InvalidUtf {}
+
+fn invalid_utf() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, InvalidUtf)
+}
+
 impl<R: Read> DataInput<R> {
     /// Read a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
     pub fn read_utf(&mut self) -> io::Result<String> {
+        let mut s = String::new();
+        self.read_utf_into(&mut s)?;
+        Ok(s)
+    }
+
+    /// Read a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string
+    /// into `s`, reusing its allocation.
+    pub fn read_utf_into(&mut self, s: &mut String) -> io::Result<()> {
         // read the size
-        let size = self.read_short()? as usize;
+        let size = self.read_short()? as u16 as usize;
 
-        if size == 0 {
-            return Ok(String::new())
-        }
+        let mut buf = std::mem::take(s).into_bytes();
+        buf.clear();
+        buf.resize(size, 0);
+        self.read_raw(&mut buf[..])?;
+
+        *s = match String::from_utf8(buf) {
+            // plain UTF-8 reads the same, unless it has 4 byte sequences
+            Ok(s) if !s.bytes().any(|b| b >= 0xf0) => s,
+            Ok(s) => decode_utf(s.as_bytes())?,
+            Err(e) => decode_utf(e.as_bytes())?,
+        };
+
+        Ok(())
+    }
 
-        // never call drop
-        let mut buf = ManuallyDrop::new(vec![0; size]);
-        self.r.read_exact(&mut buf[..])?;
+    // fills `buf` with bytes from the internal reader
+    pub(crate) fn read_raw(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.r.read_exact(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
 
-        // TODO: confirm UTF string is valid
-        Ok(unsafe { String::from_raw_parts(buf.as_mut_ptr(), size, buf.capacity()) })
+    // fills the first `count` bytes of the internal buffer
+    fn fill(&mut self, count: usize) -> io::Result<()> {
+        self.r.read_exact(&mut self.buf[..count])?;
+        self.pos += count as u64;
+        Ok(())
     }
 
     /// Read up to `count` bytes from the internal reader.
     pub fn read_bytes(&mut self, count: usize) -> io::Result<Vec<i8>> {
+        // reuses the allocation, as bytes and signed bytes share a layout
+        self.read_unsigned_bytes(count).map(bytemuck::allocation::cast_vec)
+    }
+
+    /// Read up to `count` unsigned bytes from the internal reader.
+    pub fn read_unsigned_bytes(&mut self, count: usize) -> io::Result<Vec<u8>> {
         let mut buf = vec![0; count];
-        self.r.read_exact(&mut buf[..])?;
-        Ok(unsafe { std::mem::transmute(buf) })
+        self.read_raw(&mut buf[..])?;
+        Ok(buf)
+    }
+
+    /// Read `count` words from the internal reader.
+    pub fn read_ints(&mut self, count: usize) -> io::Result<Vec<i32>> {
+        let mut xs = Vec::new();
+        self.read_ints_into(count, &mut xs)?;
+        Ok(xs)
+    }
+
+    /// Read `count` words from the internal reader into `xs`, reusing its
+    /// allocation.
+    pub fn read_ints_into(&mut self, count: usize, xs: &mut Vec<i32>) -> io::Result<()> {
+        array_size(count, 4)?;
+        xs.clear();
+        xs.resize(count, 0);
+        // the bytes are read in place, then put in the native byte order
+        self.read_raw(bytemuck::cast_slice_mut(&mut xs[..]))?;
+        xs.iter_mut().for_each(|x| *x = i32::from_be(*x));
+        Ok(())
+    }
+
+    /// Read `count` long values from the internal reader.
+    pub fn read_longs(&mut self, count: usize) -> io::Result<Vec<i64>> {
+        let mut xs = Vec::new();
+        self.read_longs_into(count, &mut xs)?;
+        Ok(xs)
+    }
+
+    /// Read `count` long values from the internal reader into `xs`, reusing
+    /// its allocation.
+    pub fn read_longs_into(&mut self, count: usize, xs: &mut Vec<i64>) -> io::Result<()> {
+        array_size(count, 8)?;
+        xs.clear();
+        xs.resize(count, 0);
+        self.read_raw(bytemuck::cast_slice_mut(&mut xs[..]))?;
+        xs.iter_mut().for_each(|x| *x = i64::from_be(*x));
+        Ok(())
+    }
+
+    /// Discard exactly `count` bytes from the internal reader.
+    pub fn skip_bytes(&mut self, count: usize) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.r).take(count as u64), &mut io::sink())?;
+        self.pos += skipped;
+        if skipped < count as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(())
     }
 
     /// Read a single byte from the internal reader.
     pub fn read_byte(&mut self) -> io::Result<i8> {
-        self.r.read_exact(&mut self.buf[..1])?;
-        Ok(unsafe { std::mem::transmute(self.buf[0]) })
+        self.fill(1)?;
+        Ok(self.buf[0] as i8)
+    }
+
+    /// Read a single unsigned byte from the internal reader.
+    pub fn read_unsigned_byte(&mut self) -> io::Result<u8> {
+        self.fill(1)?;
+        Ok(self.buf[0])
+    }
+
+    /// Read a boolean from the internal reader, which is any byte but zero.
+    pub fn read_boolean(&mut self) -> io::Result<bool> {
+        self.read_unsigned_byte().map(|x| x != 0)
     }
 
     /// Read a short value from the internal reader.
     pub fn read_short(&mut self) -> io::Result<i16> {
-        self.r.read_exact(&mut self.buf[..2])?;
+        self.fill(2)?;
         Ok(i16::from_be_bytes([self.buf[0], self.buf[1]]))
     }
 
+    /// Read an unsigned short value from the internal reader.
+    pub fn read_unsigned_short(&mut self) -> io::Result<u16> {
+        self.fill(2)?;
+        Ok(u16::from_be_bytes([self.buf[0], self.buf[1]]))
+    }
+
+    /// Read a UTF-16 code unit from the internal reader, as Java's `char`.
+    pub fn read_char(&mut self) -> io::Result<u16> {
+        self.read_unsigned_short()
+    }
+
     /// Read a word from the internal reader.
     pub fn read_int(&mut self) -> io::Result<i32> {
-        self.r.read_exact(&mut self.buf[..4])?;
+        self.fill(4)?;
         Ok(i32::from_be_bytes([
             self.buf[0], self.buf[1], self.buf[2], self.buf[3],
         ]))
@@ -66,7 +254,7 @@ impl<R: Read> DataInput<R> {
 
     /// Read a long value from the internal reader.
     pub fn read_long(&mut self) -> io::Result<i64> {
-        self.r.read_exact(&mut self.buf[..8])?;
+        self.fill(8)?;
         Ok(i64::from_be_bytes([
             self.buf[0], self.buf[1], self.buf[2], self.buf[3],
             self.buf[4], self.buf[5], self.buf[6], self.buf[7],
@@ -75,7 +263,7 @@ impl<R: Read> DataInput<R> {
 
     /// Read an [`f32`] value from the internal reader.
     pub fn read_float(&mut self) -> io::Result<f32> {
-        self.r.read_exact(&mut self.buf[..4])?;
+        self.fill(4)?;
         Ok(f32::from_be_bytes([
             self.buf[0], self.buf[1], self.buf[2], self.buf[3],
         ]))
@@ -83,10 +271,199 @@ impl<R: Read> DataInput<R> {
 
     /// Read an [`f64`] value from the internal reader.
     pub fn read_double(&mut self) -> io::Result<f64> {
-        self.r.read_exact(&mut self.buf[..8])?;
+        self.fill(8)?;
         Ok(f64::from_be_bytes([
             self.buf[0], self.buf[1], self.buf[2], self.buf[3],
             self.buf[4], self.buf[5], self.buf[6], self.buf[7],
         ]))
     }
+
+    /// Read a [VarInt](https://wiki.vg/Protocol#VarInt_and_VarLong), 7 bits
+    /// per byte, low bits first, in at most 5 bytes.
+    pub fn read_varint(&mut self) -> io::Result<i32> {
+        self.read_var(5).map(|x| x as i32)
+    }
+
+    /// Read a [VarLong](https://wiki.vg/Protocol#VarInt_and_VarLong), 7 bits
+    /// per byte, low bits first, in at most 10 bytes.
+    pub fn read_varlong(&mut self) -> io::Result<i64> {
+        self.read_var(10).map(|x| x as i64)
+    }
+
+    /// Read bytes prefixed by their count, as a [VarInt](Self::read_varint).
+    ///
+    /// The bytes are allocated as they are read, so that a bogus count does
+    /// not allocate more than the input has.
+    pub fn read_prefixed_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let count = self.read_varint()?;
+        if count < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "negative byte count"))
+        }
+
+        self.read_to_vec(count as usize)
+    }
+
+    // reads `count` bytes from an untrusted length, so that memory is only
+    // allocated as they come in
+    pub(crate) fn read_to_vec(&mut self, count: usize) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let read = (&mut self.r).take(count as u64).read_to_end(&mut buf)?;
+        self.pos += read as u64;
+        if read < count {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(buf)
+    }
+
+    // reads a varint of at most `max` bytes
+    fn read_var(&mut self, max: u32) -> io::Result<u64> {
+        let mut x = 0;
+        for i in 0..max {
+            let b = self.read_unsigned_byte()?;
+            x |= u64::from(b & 0x7f) << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok(x)
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "varint is too long"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use proptest::prelude::*;
+
+    use crate::serialize::{DataInput, DataOutput};
+
+    fn round_trip(s: &str) -> io::Result<String> {
+        let mut output = DataOutput::new(Vec::new());
+        output.write_utf(s)?;
+        let buf = output.into_inner();
+        DataInput::new(&buf[..]).read_utf()
+    }
+
+    #[test]
+    fn test_utf_encoding() {
+        let mut output = DataOutput::new(Vec::new());
+        output.write_utf("a\0\u{e9}\u{20ac}\u{1f600}").unwrap();
+        assert_eq!(output.into_inner(), [
+            0x00, 0x0e,
+            0x61,
+            0xc0, 0x80,
+            0xc3, 0xa9,
+            0xe2, 0x82, 0xac,
+            0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80,
+        ]);
+
+        for s in ["", "plain", "nul\0in the middle", "caf\u{e9} \u{1f600}\u{1f4a9}"] {
+            assert_eq!(round_trip(s).unwrap(), s);
+        }
+
+        let long = "x".repeat(0xffff);
+        assert_eq!(round_trip(&long).unwrap(), long);
+        assert!(round_trip(&(long + "\0")).is_err());
+    }
+
+    #[test]
+    fn test_utf_invalid() {
+        let invalid: &[&[u8]] = &[
+            // 4 byte sequence of plain UTF-8
+            &[0x00, 0x04, 0xf0, 0x9f, 0x98, 0x80],
+            // unpaired surrogates
+            &[0x00, 0x03, 0xed, 0xa0, 0xbd],
+            &[0x00, 0x03, 0xed, 0xb8, 0x80],
+            // missing continuation bytes
+            &[0x00, 0x01, 0xc3],
+            &[0x00, 0x02, 0xe2, 0x41],
+            // stray continuation byte
+            &[0x00, 0x01, 0x80],
+        ];
+
+        for buf in invalid {
+            let e = DataInput::new(*buf).read_utf().unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut output = DataOutput::new(Vec::new());
+        output.write_bytes([-1, 0, 127, -128]).unwrap();
+        output.write_unsigned_bytes([255, 0, 127, 128]).unwrap();
+        let buf = output.into_inner();
+        assert_eq!(buf, [0xff, 0x00, 0x7f, 0x80, 0xff, 0x00, 0x7f, 0x80]);
+
+        let mut input = DataInput::new(&buf[..]);
+        assert_eq!(input.read_unsigned_bytes(4).unwrap(), [255, 0, 127, 128]);
+        assert_eq!(input.read_bytes(4).unwrap(), [-1, 0, 127, -128]);
+        assert!(input.read_bytes(1).is_err());
+
+        let mut input = crate::serialize::BufInput::new(&buf);
+        assert_eq!(input.read_signed_slice(4).unwrap(), [-1, 0, 127, -128]);
+        assert_eq!(input.read_slice(4).unwrap(), [255, 0, 127, 128]);
+    }
+
+    #[test]
+    fn test_extended_values() {
+        use crate::serialize::{prefixed_len, varint_len, varlong_len};
+
+        let varints: &[(i32, &[u8])] = &[
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (300, &[0xac, 0x02]),
+            (i32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+            (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+            (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+        ];
+        for &(x, bytes) in varints {
+            let mut output = DataOutput::new(Vec::new());
+            output.write_varint(x).unwrap();
+            assert_eq!(output.into_inner(), bytes);
+            assert_eq!(varint_len(x), bytes.len());
+            assert_eq!(DataInput::new(bytes).read_varint().unwrap(), x);
+        }
+        assert_eq!(varlong_len(-1), 10);
+        assert_eq!(varlong_len(i64::MAX), 9);
+
+        let mut output = DataOutput::new(Vec::new());
+        output.write_unsigned_byte(200).unwrap();
+        output.write_boolean(true).unwrap();
+        output.write_unsigned_short(60000).unwrap();
+        output.write_char(0xd83d).unwrap();
+        output.write_varlong(i64::MIN).unwrap();
+        output.write_prefixed_bytes([0xfe, 0x00]).unwrap();
+        assert_eq!(Some(output.position() as usize), prefixed_len(2).map(|len| 1 + 1 + 2 + 2 + 10 + len));
+        assert_eq!(prefixed_len(i32::MAX as usize), Some(i32::MAX as usize + 5));
+        assert_eq!(prefixed_len(i32::MAX as usize + 1), None);
+        let buf = output.into_inner();
+
+        let mut input = DataInput::new(&buf[..]);
+        assert_eq!(input.read_unsigned_byte().unwrap(), 200);
+        assert!(input.read_boolean().unwrap());
+        assert_eq!(input.read_unsigned_short().unwrap(), 60000);
+        assert_eq!(input.read_char().unwrap(), 0xd83d);
+        assert_eq!(input.read_varlong().unwrap(), i64::MIN);
+        assert_eq!(input.read_prefixed_bytes().unwrap(), [0xfe, 0x00]);
+        assert_eq!(input.position(), buf.len() as u64);
+
+        // too long, negative and truncated
+        let e = DataInput::new(&[0x80; 6][..]).read_varint().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        let e = DataInput::new(&[0xff, 0xff, 0xff, 0xff, 0x0f][..]).read_prefixed_bytes().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        let e = DataInput::new(&[0xff, 0xff, 0xff, 0xff, 0x07, 0x00][..]).read_prefixed_bytes().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    proptest! {
+        #[test]
+        fn test_utf_arbitrary(s in any::<String>(), buf in proptest::collection::vec(any::<u8>(), 0..64)) {
+            prop_assert_eq!(round_trip(&s).unwrap(), s);
+
+            // any bytes either decode or fail, without panicking
+            let _ = DataInput::new(&buf[..]).read_utf();
+        }
+    }
 }
@@ -1,14 +1,26 @@
 use std::io::{self, Write};
+use std::convert::TryFrom;
 
 /// Write Java-like encoded values.
 pub struct DataOutput<W> {
+    pos: u64,
     w: W,
 }
 
 impl<W> DataOutput<W> {
     /// Creates a new writer for Java-like encoded values.
     pub fn new(w: W) -> Self {
-        Self { w }
+        Self { pos: 0, w }
+    }
+
+    // creates a writer that counts its position from `pos`
+    pub(crate) fn with_position(w: W, pos: u64) -> Self {
+        Self { pos, w }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> u64 {
+        self.pos
     }
 
     /// Returns the writer associated with this [`DataOutput`].
@@ -17,71 +29,218 @@ impl<W> DataOutput<W> {
     }
 }
 
+/// Returns the size of `s` once encoded in
+/// [modified UTF-8](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8).
+pub fn utf_len(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x01..=0x7f => 1,
+            0x00 | 0x80..=0x7ff => 2,
+            0x800..=0xffff => 3,
+            _ => 6,
+        })
+        .sum()
+}
+
+/// Returns the size of `x` once encoded as a
+/// [VarInt](DataOutput::write_varint), from 1 to 5 bytes.
+pub fn varint_len(x: i32) -> usize {
+    var_len(x as u32 as u64)
+}
+
+/// Returns the size of `x` once encoded as a
+/// [VarLong](DataOutput::write_varlong), from 1 to 10 bytes.
+pub fn varlong_len(x: i64) -> usize {
+    var_len(x as u64)
+}
+
+/// Returns the size of `count` bytes once
+/// [prefixed by their count](DataOutput::write_prefixed_bytes), or `None` if
+/// there are more than `i32::MAX` of them, which cannot be written.
+pub fn prefixed_len(count: usize) -> Option<usize> {
+    let prefix = i32::try_from(count).ok().map(varint_len)?;
+    count.checked_add(prefix)
+}
+
+fn var_len(x: u64) -> usize {
+    // 7 bits per byte, and a byte for zero
+    (64 - x.leading_zeros() as usize).max(1).div_ceil(7)
+}
+
+/// Encodes `s` in [modified UTF-8](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8)
+/// at the end of `buf`, the way Java's `DataOutput::writeUTF` does, without
+/// the size prefix.
+///
+/// NUL is encoded as `C0 80`, and characters outside of the basic
+/// multilingual plane as a surrogate pair, each half in 3 bytes.
+pub fn encode_utf(s: &str, buf: &mut Vec<u8>) {
+    let mut units = [0; 2];
+    for c in s.chars() {
+        for &unit in c.encode_utf16(&mut units).iter() {
+            match unit {
+                0x01..=0x7f => buf.push(unit as u8),
+                0x00 | 0x80..=0x7ff => buf.extend_from_slice(&[
+                    0xc0 | (unit >> 6) as u8,
+                    0x80 | (unit & 0x3f) as u8,
+                ]),
+                _ => buf.extend_from_slice(&[
+                    0xe0 | (unit >> 12) as u8,
+                    0x80 | (unit >> 6 & 0x3f) as u8,
+                    0x80 | (unit & 0x3f) as u8,
+                ]),
+            }
+        }
+    }
+}
+
 impl<W: Write> DataOutput<W> {
     /// Write a [UTF encoded](https://en.wikipedia.org/wiki/UTF-8#Modified_UTF-8) string.
     pub fn write_utf<T: AsRef<str>>(&mut self, s: T) -> io::Result<()> {
         // get the str
         let s = s.as_ref();
-        let slice: &[u8] = s.as_ref();
-        let slice: &[u8] = &slice[..slice.len() & 0x7fff];
+        let size = utf_len(s);
 
-        // string size overflows i16
-        if slice.len() < s.len() {
-            return Err(io::Error::new(io::ErrorKind::Other, "string size overflows i16"));
+        // string size overflows u16
+        if size > 0xffff {
+            return Err(io::Error::other("string size overflows u16"));
         }
 
-        let size = slice.len() as i16;
-
         // write the size to the underlying output
-        self.write_short(size)?;
+        self.write_short(size as u16 as i16)?;
 
         if size == 0 {
             return Ok(())
         }
 
+        // plain UTF-8 encodes the same, without NULs or 4 byte sequences
+        if size == s.len() && !s.bytes().any(|b| b == 0) {
+            return self.write_raw(s.as_bytes())
+        }
+
         // write the string bytes
-        self.w.write_all(slice)
+        let mut buf = Vec::with_capacity(size);
+        encode_utf(s, &mut buf);
+        self.write_raw(&buf)
     }
 
     /// Write all the bytes in `buf` to the internal writer.
     pub fn write_bytes<T: AsRef<[i8]>>(&mut self, buf: T) -> io::Result<()> {
-        let buf: &[u8] = unsafe { std::mem::transmute(buf.as_ref()) };
-        self.w.write_all(buf)
+        self.write_raw(bytemuck::cast_slice(buf.as_ref()))
+    }
+
+    /// Write all the unsigned bytes in `buf` to the internal writer.
+    pub fn write_unsigned_bytes<T: AsRef<[u8]>>(&mut self, buf: T) -> io::Result<()> {
+        self.write_raw(buf.as_ref())
+    }
+
+    /// Write all the words in `xs` to the internal writer.
+    pub fn write_ints<T: AsRef<[i32]>>(&mut self, xs: T) -> io::Result<()> {
+        let buf: Vec<u8> = xs.as_ref().iter().flat_map(|x| x.to_be_bytes()).collect();
+        self.write_raw(&buf)
+    }
+
+    /// Write all the long values in `xs` to the internal writer.
+    pub fn write_longs<T: AsRef<[i64]>>(&mut self, xs: T) -> io::Result<()> {
+        let buf: Vec<u8> = xs.as_ref().iter().flat_map(|x| x.to_be_bytes()).collect();
+        self.write_raw(&buf)
     }
 
     /// Write a single byte to the internal writer.
     pub fn write_byte(&mut self, x: i8) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
+    }
+
+    /// Write a single unsigned byte to the internal writer.
+    pub fn write_unsigned_byte(&mut self, x: u8) -> io::Result<()> {
+        self.write_raw(&[x])
+    }
+
+    /// Write a boolean to the internal writer, as a byte of one or zero.
+    pub fn write_boolean(&mut self, x: bool) -> io::Result<()> {
+        self.write_unsigned_byte(x as u8)
     }
 
     /// Write a short value to the internal writer.
     pub fn write_short(&mut self, x: i16) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
+    }
+
+    /// Write an unsigned short value to the internal writer.
+    pub fn write_unsigned_short(&mut self, x: u16) -> io::Result<()> {
+        let buf = x.to_be_bytes();
+        self.write_raw(&buf[..])
+    }
+
+    /// Write a UTF-16 code unit to the internal writer, as Java's `char`.
+    pub fn write_char(&mut self, x: u16) -> io::Result<()> {
+        self.write_unsigned_short(x)
     }
 
     /// Write a word to the internal writer.
     pub fn write_int(&mut self, x: i32) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
     }
 
     /// Write a long value to the internal writer.
     pub fn write_long(&mut self, x: i64) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
     }
 
     /// Write an [`f32`] to the internal writer.
     pub fn write_float(&mut self, x: f32) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
     }
 
     /// Write an [`f64`] to the internal writer.
     pub fn write_double(&mut self, x: f64) -> io::Result<()> {
         let buf = x.to_be_bytes();
-        self.w.write_all(&buf[..])
+        self.write_raw(&buf[..])
+    }
+
+    /// Write a [VarInt](https://wiki.vg/Protocol#VarInt_and_VarLong), 7 bits
+    /// per byte, low bits first.
+    pub fn write_varint(&mut self, x: i32) -> io::Result<()> {
+        self.write_var(x as u32 as u64)
+    }
+
+    /// Write a [VarLong](https://wiki.vg/Protocol#VarInt_and_VarLong), 7 bits
+    /// per byte, low bits first.
+    pub fn write_varlong(&mut self, x: i64) -> io::Result<()> {
+        self.write_var(x as u64)
+    }
+
+    /// Write the bytes of `buf` prefixed by their count, as a
+    /// [VarInt](Self::write_varint).
+    pub fn write_prefixed_bytes<T: AsRef<[u8]>>(&mut self, buf: T) -> io::Result<()> {
+        let buf = buf.as_ref();
+        if buf.len() > i32::MAX as usize {
+            return Err(io::Error::other("byte count overflows i32"));
+        }
+        self.write_varint(buf.len() as i32)?;
+        self.write_raw(buf)
+    }
+
+    fn write_var(&mut self, mut x: u64) -> io::Result<()> {
+        let mut buf = [0; 10];
+        let mut len = 0;
+        while x >= 0x80 {
+            buf[len] = x as u8 | 0x80;
+            x >>= 7;
+            len += 1;
+        }
+        buf[len] = x as u8;
+        self.write_raw(&buf[..=len])
+    }
+
+    // writes `buf` to the internal writer
+    pub(crate) fn write_raw(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.w.write_all(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
     }
 }
@@ -1,7 +1,12 @@
 //! Serialize content to disk or across the wire.
 
+#![forbid(unsafe_code)]
+
 mod datainput;
 mod dataoutput;
+mod buffer;
+#[cfg(feature = "tokio")]
+mod asyncio;
 
 // Implements the NBT serialization present in the alpha
 // 1.2.6 of Minecraft.
@@ -9,3 +14,6 @@ pub mod nbt;
 
 pub use datainput::*;
 pub use dataoutput::*;
+pub use buffer::*;
+#[cfg(feature = "tokio")]
+pub use asyncio::*;
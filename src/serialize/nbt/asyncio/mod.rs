@@ -0,0 +1,336 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::serialize::{AsyncDataInput, AsyncDataOutput, DataInput, DataOutput};
+use crate::serialize::nbt::{Encoding, Error, Limits, NBT, WriteOptions};
+use crate::serialize::nbt::error::{At, Position};
+
+impl<R> Position for AsyncDataInput<R> {
+    fn position(&self) -> u64 {
+        AsyncDataInput::position(self)
+    }
+}
+
+impl<W> Position for AsyncDataOutput<W> {
+    fn position(&self) -> u64 {
+        AsyncDataOutput::position(self)
+    }
+}
+
+impl NBT {
+    /// Async counterpart of [`NBT::read_from`].
+    pub async fn read_from_async<R: AsyncRead + Unpin>(input: &mut AsyncDataInput<R>) -> Result<NBT, Error> {
+        NBT::read_with_limits_async(input, &Limits::default()).await
+    }
+
+    /// Async counterpart of [`NBT::read_with_limits`].
+    pub async fn read_with_limits_async<R: AsyncRead + Unpin>(input: &mut AsyncDataInput<R>, limits: &Limits) -> Result<NBT, Error> {
+        NBT::read_encoded_async(input, Encoding::BigEndian, limits).await
+    }
+
+    /// Async counterpart of [`NBT::read_encoded`].
+    ///
+    /// The bytes of the value are read first, no more than it has, and then
+    /// decoded by [`NBT::read_encoded`], which fails the same way. At most
+    /// twice `limits.max_bytes` are buffered.
+    pub async fn read_encoded_async<R: AsyncRead + Unpin>(input: &mut AsyncDataInput<R>, encoding: Encoding, limits: &Limits) -> Result<NBT, Error> {
+        let offset = input.position();
+        let mut framer = Framer {
+            input,
+            buf: Vec::new(),
+            encoding,
+            limits,
+        };
+
+        if let Err(Halt::Io(e)) = framer.frame().await {
+            return Err(e).at(framer.input)
+        }
+
+        let mut input = DataInput::with_position(&framer.buf[..], offset);
+        NBT::read_encoded(&mut input, encoding, limits)
+    }
+
+    /// Async counterpart of [`NBT::write_to`].
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(&self, output: &mut AsyncDataOutput<W>) -> Result<(), Error> {
+        self.write_with_options_async(output, &WriteOptions::default()).await
+    }
+
+    /// Async counterpart of [`NBT::write_with_options`], which encodes the
+    /// value before writing it at once.
+    pub async fn write_with_options_async<W: AsyncWrite + Unpin>(&self, output: &mut AsyncDataOutput<W>, options: &WriteOptions) -> Result<(), Error> {
+        let mut buf = DataOutput::with_position(Vec::new(), output.position());
+        self.write_with_options(&mut buf, options)?;
+        output.write_raw(&buf.into_inner()).await.at(output)
+    }
+}
+
+// Why the bytes of a value stopped being read.
+enum Halt {
+    // the bytes so far are malformed or truncated, which decoding reports
+    Decode,
+    Io(io::Error),
+}
+
+// A list or compound being read.
+enum Open {
+    List(i8, usize),
+    Compound,
+}
+
+// Reads the bytes of an NBT value, without decoding them.
+struct Framer<'a, R> {
+    input: &'a mut AsyncDataInput<R>,
+    buf: Vec<u8>,
+    encoding: Encoding,
+    limits: &'a Limits,
+}
+
+impl<R: AsyncRead + Unpin> Framer<'_, R> {
+    async fn frame(&mut self) -> Result<(), Halt> {
+        if self.take_kind().await? != 10 {
+            return Err(Halt::Decode)
+        }
+        self.take_utf().await?;
+
+        // lists and compounds are tracked on the heap, rather than with
+        // recursion, which async functions cannot do without boxing
+        let mut open = Vec::new();
+        let mut next = Some(10);
+
+        loop {
+            if let Some(kind) = next.take() {
+                self.take_value(kind, &mut open).await?;
+            }
+
+            match open.last_mut() {
+                None => return Ok(()),
+                Some(Open::List(_, 0)) => {
+                    open.pop();
+                },
+                Some(Open::List(kind, len)) => {
+                    *len -= 1;
+                    next = Some(*kind);
+                },
+                Some(Open::Compound) => match self.take_kind().await? {
+                    0 => {
+                        open.pop();
+                    },
+                    kind => {
+                        self.take_utf().await?;
+                        next = Some(kind);
+                    },
+                },
+            }
+        }
+    }
+
+    async fn take_value(&mut self, kind: i8, open: &mut Vec<Open>) -> Result<(), Halt> {
+        match kind {
+            0x00 => Ok(()),
+            0x01 => self.take(1).await,
+            0x02 => self.take_number(2).await,
+            0x03 => self.take_number(4).await,
+            0x04 => self.take_number(8).await,
+            0x05 => self.take(4).await,
+            0x06 => self.take(8).await,
+            0x07 => {
+                let len = self.take_len().await?;
+                self.take(len).await
+            },
+            0x0b | 0x0c => {
+                let len = self.take_len().await?;
+                let size = if kind == 0x0b { 4 } else { 8 };
+                match self.encoding {
+                    Encoding::Network => {
+                        for _ in 0..len {
+                            self.take_number(size).await?;
+                        }
+                        Ok(())
+                    },
+                    _ => self.take(len.saturating_mul(size)).await,
+                }
+            },
+            0x08 => self.take_utf().await,
+            _ => {
+                if open.len() >= self.limits.max_depth {
+                    return Err(Halt::Decode)
+                }
+
+                if kind == 0x09 {
+                    let kind = self.take_kind().await?;
+                    let len = self.take_len().await?;
+                    open.push(Open::List(kind, len));
+                } else {
+                    open.push(Open::Compound);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    // the kind of a tag
+    async fn take_kind(&mut self) -> Result<i8, Halt> {
+        self.take(1).await?;
+        match self.buf[self.buf.len() - 1] as i8 {
+            kind @ 0x00..=0x0c => Ok(kind),
+            _ => Err(Halt::Decode),
+        }
+    }
+
+    // the length of a list or array
+    async fn take_len(&mut self) -> Result<usize, Halt> {
+        let start = self.buf.len();
+        self.take_number(4).await?;
+
+        let len = self.encoding.read_int(&mut DataInput::new(&self.buf[start..]));
+        match len {
+            Ok(len) if len >= 0 && len as usize <= self.limits.max_len => Ok(len as usize),
+            _ => Err(Halt::Decode),
+        }
+    }
+
+    // a string and its size
+    async fn take_utf(&mut self) -> Result<(), Halt> {
+        let start = self.buf.len();
+        let size = match self.encoding {
+            Encoding::Network => {
+                self.take_varint(5).await?;
+                DataInput::new(&self.buf[start..]).read_varint().map(|x| x as u32 as u64)
+            },
+            _ => {
+                self.take(2).await?;
+                self.encoding.read_short(&mut DataInput::new(&self.buf[start..])).map(|x| x as u16 as u64)
+            },
+        };
+        self.take(size.map_err(|_| Halt::Decode)? as usize).await
+    }
+
+    // a number of `size` bytes, unless the encoding makes it a varint
+    async fn take_number(&mut self, size: usize) -> Result<(), Halt> {
+        match self.encoding {
+            Encoding::Network if size > 2 => self.take_varint(if size == 4 { 5 } else { 10 }).await,
+            _ => self.take(size).await,
+        }
+    }
+
+    // the bytes of a varint of at most `max` bytes
+    async fn take_varint(&mut self, max: usize) -> Result<(), Halt> {
+        for _ in 0..max {
+            self.take(1).await?;
+            if self.buf[self.buf.len() - 1] & 0x80 == 0 {
+                return Ok(())
+            }
+        }
+        Err(Halt::Decode)
+    }
+
+    async fn take(&mut self, count: usize) -> Result<(), Halt> {
+        let cap = self.limits.max_bytes.saturating_mul(2);
+        let allowed = count.min(cap.saturating_sub(self.buf.len()));
+
+        // the bytes before the end of the input, or the cap, are kept, so
+        // that decoding fails where the sync version does
+        match self.input.read_onto(&mut self.buf, allowed).await {
+            Ok(()) if allowed == count => Ok(()),
+            Ok(()) => Err(Halt::Decode),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(Halt::Decode),
+            Err(e) => Err(Halt::Io(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::fs::File;
+
+    use flate2::read::GzDecoder;
+    use futures::executor::block_on;
+    use proptest::prelude::*;
+
+    use crate::serialize::{AsyncDataInput, AsyncDataOutput, DataInput, DataOutput};
+    use crate::serialize::nbt::{Encoding, Limits, NBT, WriteOptions};
+
+    #[tokio::test]
+    async fn test_async_nbt() {
+        let mut buf = Vec::new();
+        GzDecoder::new(File::open("res/bigtest.dat").unwrap()).read_to_end(&mut buf).unwrap();
+        let nbt = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap();
+
+        for encoding in [Encoding::BigEndian, Encoding::LittleEndian, Encoding::Network] {
+            let options = WriteOptions::encoded(encoding);
+            let limits = Limits::default();
+
+            // two values back to back, with the second read from where the
+            // first ends
+            let mut output = AsyncDataOutput::new(Vec::new());
+            nbt.write_with_options_async(&mut output, &options).await.unwrap();
+            nbt.write_with_options_async(&mut output, &options).await.unwrap();
+            let buf = output.into_inner();
+
+            let mut input = AsyncDataInput::new(&buf[..]);
+            for _ in 0..2 {
+                let read = NBT::read_encoded_async(&mut input, encoding, &limits).await.unwrap();
+                assert_eq!(read.tag().to_string(), nbt.tag().to_string());
+            }
+            assert_eq!(input.position(), buf.len() as u64);
+        }
+
+        // fails as the sync version does
+        let mut output = AsyncDataOutput::new(Vec::new());
+        nbt.write_to_async(&mut output).await.unwrap();
+        let buf = output.into_inner();
+        let limits = Limits { max_depth: 1, ..Limits::default() };
+
+        for (buf, limits) in [(&buf[..buf.len() - 10], Limits::default()), (&buf[..], limits)] {
+            let sync = NBT::read_with_limits(&mut DataInput::new(buf), &limits).unwrap_err();
+            let e = NBT::read_with_limits_async(&mut AsyncDataInput::new(buf), &limits).await.unwrap_err();
+            assert_eq!(e.to_string(), sync.to_string());
+            assert_eq!(e.offset(), sync.offset());
+        }
+    }
+
+    proptest! {
+        // the framer walks the bytes on its own, so it is checked against
+        // the sync decoder on valid, damaged and cut short values alike
+        #[test]
+        fn test_async_matches_sync(
+            nbt in crate::serialize::nbt::strategy::nbt(),
+            flips in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+            cut in 0..8usize,
+            next in proptest::collection::vec(any::<u8>(), 0..8),
+        ) {
+            let limits = Limits { max_bytes: 1 << 16, ..Limits::default() };
+            for encoding in [Encoding::BigEndian, Encoding::LittleEndian, Encoding::Network] {
+                let mut output = DataOutput::new(Vec::new());
+                nbt.write_with_options(&mut output, &WriteOptions::encoded(encoding)).unwrap();
+                let mut buf = output.into_inner();
+                for (i, b) in &flips {
+                    let i = i.index(buf.len());
+                    buf[i] = *b;
+                }
+                buf.truncate(buf.len() - cut.min(buf.len()));
+                // the bytes of whatever comes next are left alone
+                buf.extend_from_slice(&next);
+
+                let mut input = DataInput::new(&buf[..]);
+                let sync = NBT::read_encoded(&mut input, encoding, &limits);
+                let mut async_input = AsyncDataInput::new(&buf[..]);
+                let read = block_on(NBT::read_encoded_async(&mut async_input, encoding, &limits));
+
+                match (sync, read) {
+                    (Ok(sync), Ok(read)) => {
+                        prop_assert_eq!(read, sync);
+                        prop_assert_eq!(async_input.position(), input.position());
+                    },
+                    (Err(sync), Err(e)) => {
+                        prop_assert_eq!(e.to_string(), sync.to_string());
+                        prop_assert_eq!(e.offset(), sync.offset());
+                    },
+                    (sync, read) => prop_assert!(false, "sync {:?}, async {:?}", sync, read),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,646 @@
+//! Borrowed views of NBT values, decoded lazily from a byte slice.
+//!
+//! Parsing an [`NBTRef`] walks the input once to check its structure, but
+//! allocates nothing: strings, byte arrays, lists and compounds are slices
+//! into the input, and the elements of lists and compounds are only decoded
+//! as they are iterated. Convert a view into an owned [`Tag`] with
+//! [`TagRef::to_tag`] when needed.
+
+use std::fmt;
+use std::str;
+use std::marker::PhantomData;
+use std::borrow::Cow;
+
+use crate::serialize::decode_utf;
+use crate::serialize::nbt::{Compound, NBT, Tag, Error, ErrorKind, MAX_DEPTH};
+
+/// Represents a borrowed NBT tag.
+#[derive(Copy, Clone, Debug)]
+pub enum TagRef<'a> {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [u8]),
+    String(StrRef<'a>),
+    List(ListRef<'a>),
+    Compound(CompoundRef<'a>),
+    IntArray(ArrayRef<'a, i32>),
+    LongArray(ArrayRef<'a, i64>),
+}
+
+/// A borrowed, still encoded, NBT string.
+#[derive(Copy, Clone)]
+pub struct StrRef<'a> {
+    bytes: &'a [u8],
+}
+
+/// A borrowed NBT list, whose elements are decoded as they are iterated.
+#[derive(Copy, Clone, Debug)]
+pub struct ListRef<'a> {
+    kind: i8,
+    len: usize,
+    data: &'a [u8],
+}
+
+/// A borrowed, still encoded, NBT array of words or long values.
+#[derive(Copy, Clone, Debug)]
+pub struct ArrayRef<'a, T> {
+    data: &'a [u8],
+    marker: PhantomData<T>,
+}
+
+/// The elements of an [`ArrayRef`].
+pub trait Element: Copy + 'static + private::Sealed {
+    #[doc(hidden)]
+    const SIZE: usize;
+    #[doc(hidden)]
+    fn from_be_slice(bytes: &[u8]) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for i32 {}
+    impl Sealed for i64 {}
+}
+
+impl Element for i32 {
+    const SIZE: usize = 4;
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+impl Element for i64 {
+    const SIZE: usize = 8;
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        i64::from_be_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3],
+            bytes[4], bytes[5], bytes[6], bytes[7],
+        ])
+    }
+}
+
+/// A borrowed NBT compound, whose entries are decoded as they are iterated.
+#[derive(Copy, Clone, Debug)]
+pub struct CompoundRef<'a> {
+    data: &'a [u8],
+}
+
+/// Contains a borrowed NBT compound tag and a key.
+#[derive(Copy, Clone, Debug)]
+pub struct NBTRef<'a> {
+    key: StrRef<'a>,
+    tag: CompoundRef<'a>,
+}
+
+// reads big endian values off a slice
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(kind, Some(self.pos as u64))
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.error(ErrorKind::Truncated))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
+    }
+
+    fn read_byte(&mut self) -> Result<i8, Error> {
+        Ok(i8::from_be_bytes(self.array()?))
+    }
+
+    fn read_short(&mut self) -> Result<i16, Error> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn read_int(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn read_long(&mut self) -> Result<i64, Error> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    fn read_float(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_be_bytes(self.array()?))
+    }
+
+    fn read_double(&mut self) -> Result<f64, Error> {
+        Ok(f64::from_be_bytes(self.array()?))
+    }
+
+    fn read_len(&mut self) -> Result<usize, Error> {
+        let offset = self.pos;
+        match self.read_int()? {
+            size if size < 0 => {
+                Err(Error::new(ErrorKind::NegativeLength(size), Some(offset as u64)))
+            },
+            size => Ok(size as usize),
+        }
+    }
+
+    fn read_str(&mut self) -> Result<StrRef<'a>, Error> {
+        let size = self.read_short()? as u16 as usize;
+        Ok(StrRef { bytes: self.take(size)? })
+    }
+
+    fn read_tag(&mut self, tag_kind: i8, depth: usize) -> Result<TagRef<'a>, Error> {
+        match tag_kind {
+            0x00 => Ok(TagRef::End),
+            0x01 => Ok(TagRef::Byte(self.read_byte()?)),
+            0x02 => Ok(TagRef::Short(self.read_short()?)),
+            0x03 => Ok(TagRef::Int(self.read_int()?)),
+            0x04 => Ok(TagRef::Long(self.read_long()?)),
+            0x05 => Ok(TagRef::Float(self.read_float()?)),
+            0x06 => Ok(TagRef::Double(self.read_double()?)),
+            0x07 => {
+                let size = self.read_len()?;
+                Ok(TagRef::ByteArray(self.take(size)?))
+            },
+            0x08 => Ok(TagRef::String(self.read_str()?)),
+            0x0b => self.read_array().map(TagRef::IntArray),
+            0x0c => self.read_array().map(TagRef::LongArray),
+            0x09 => {
+                let depth = self.enter(depth)?;
+                let kind = self.read_kind()?;
+                let offset = self.pos;
+                let len = self.read_len()?;
+
+                // only an empty list holds no values, and every other
+                // element takes at least a byte, which bounds the length
+                if kind == 0 && len > 0 {
+                    return Err(Error::new(ErrorKind::UnknownTag(kind), Some(offset as u64)))
+                }
+                if len > self.data.len() - self.pos {
+                    return Err(self.error(ErrorKind::Truncated))
+                }
+
+                let start = self.pos;
+                match fixed_size(kind) {
+                    Some(size) => {
+                        let size = size
+                            .checked_mul(len)
+                            .ok_or_else(|| self.error(ErrorKind::Truncated))?;
+                        self.take(size)?;
+                    },
+                    None => for i in 0..len {
+                        self.read_tag(kind, depth).map_err(|e| e.in_index(i))?;
+                    },
+                }
+
+                let data = &self.data[start..self.pos];
+                Ok(TagRef::List(ListRef { kind, len, data }))
+            },
+            0x0a => {
+                let depth = self.enter(depth)?;
+                let start = self.pos;
+                loop {
+                    let kind = self.read_kind()?;
+
+                    if kind == 0 {
+                        break;
+                    }
+
+                    let key = self.read_str()?;
+                    self.read_tag(kind, depth)
+                        .map_err(|e| e.in_key(&String::from_utf8_lossy(key.bytes)))?;
+                }
+
+                // leave the end tag out
+                let data = &self.data[start..self.pos - 1];
+                Ok(TagRef::Compound(CompoundRef { data }))
+            },
+            _ => Err(Error::new(ErrorKind::UnknownTag(tag_kind), None)),
+        }
+    }
+
+    fn read_kind(&mut self) -> Result<i8, Error> {
+        let offset = self.pos;
+        match self.read_byte()? {
+            tag_kind @ 0x00..=0x0c => Ok(tag_kind),
+            tag_kind => Err(Error::new(ErrorKind::UnknownTag(tag_kind), Some(offset as u64))),
+        }
+    }
+
+    fn read_array<T: Element>(&mut self) -> Result<ArrayRef<'a, T>, Error> {
+        let len = self.read_len()?;
+        let size = len
+            .checked_mul(T::SIZE)
+            .ok_or_else(|| self.error(ErrorKind::Truncated))?;
+        Ok(ArrayRef { data: self.take(size)?, marker: PhantomData })
+    }
+
+    // steps into a list or compound
+    fn enter(&self, depth: usize) -> Result<usize, Error> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error(ErrorKind::DepthExceeded(MAX_DEPTH)))
+        }
+        Ok(depth + 1)
+    }
+}
+
+// the encoded size of tags that have one
+fn fixed_size(tag_kind: i8) -> Option<usize> {
+    match tag_kind {
+        0x00 => Some(0),
+        0x01 => Some(1),
+        0x02 => Some(2),
+        0x03 | 0x05 => Some(4),
+        0x04 | 0x06 => Some(8),
+        _ => None,
+    }
+}
+
+impl<'a> NBTRef<'a> {
+    /// Parses the NBT value at the start of `bytes`, which is left borrowed.
+    pub fn from_slice(bytes: &'a [u8]) -> Result<NBTRef<'a>, Error> {
+        let mut cursor = Cursor::new(bytes);
+
+        let kind = cursor.read_byte()?;
+        if kind != 0x0a {
+            let kind = ErrorKind::UnexpectedTag { expected: 0x0a, found: kind };
+            return Err(Error::new(kind, Some(0)))
+        }
+
+        let key = cursor.read_str()?;
+
+        match cursor.read_tag(0x0a, 0)? {
+            TagRef::Compound(tag) => Ok(NBTRef { key, tag }),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns this NBT tag's key.
+    pub fn key(&self) -> StrRef<'a> {
+        self.key
+    }
+
+    /// Returns this NBT tag's compound value.
+    pub fn tag(&self) -> CompoundRef<'a> {
+        self.tag
+    }
+
+    /// Decodes an owned copy of this NBT value.
+    pub fn to_nbt(&self) -> Result<NBT, Error> {
+        let key = self.key.to_str()?.into_owned();
+        let tag = TagRef::Compound(self.tag).to_tag()?;
+        Ok(NBT { key, tag })
+    }
+}
+
+impl<'a> TagRef<'a> {
+    /// Returns a byte representing the kind of this tag.
+    pub fn kind(&self) -> i8 {
+        match self {
+            TagRef::End => 0x00,
+            TagRef::Byte(_) => 0x01,
+            TagRef::Short(_) => 0x02,
+            TagRef::Int(_) => 0x03,
+            TagRef::Long(_) => 0x04,
+            TagRef::Float(_) => 0x05,
+            TagRef::Double(_) => 0x06,
+            TagRef::ByteArray(_) => 0x07,
+            TagRef::String(_) => 0x08,
+            TagRef::List(_) => 0x09,
+            TagRef::Compound(_) => 0x0a,
+            TagRef::IntArray(_) => 0x0b,
+            TagRef::LongArray(_) => 0x0c,
+        }
+    }
+
+    /// Decodes an owned copy of this tag.
+    pub fn to_tag(&self) -> Result<Tag, Error> {
+        Ok(match *self {
+            TagRef::End => Tag::End,
+            TagRef::Byte(x) => Tag::Byte(x),
+            TagRef::Short(x) => Tag::Short(x),
+            TagRef::Int(x) => Tag::Int(x),
+            TagRef::Long(x) => Tag::Long(x),
+            TagRef::Float(x) => Tag::Float(x),
+            TagRef::Double(x) => Tag::Double(x),
+            TagRef::ByteArray(xs) => Tag::ByteArray(xs.iter().map(|&x| x as i8).collect()),
+            TagRef::String(s) => Tag::String(s.to_str()?.into_owned()),
+            TagRef::List(xs) => Tag::List(xs.kind, xs
+                .iter()
+                .enumerate()
+                .map(|(i, tag)| tag.to_tag().map_err(|e| e.in_index(i)))
+                .collect::<Result<_, Error>>()?),
+            TagRef::Compound(m) => {
+                let mut owned = Compound::new();
+                for (key, tag) in m.iter() {
+                    let key = key.to_str()?.into_owned();
+                    let tag = tag.to_tag().map_err(|e| e.in_key(&key))?;
+                    owned.insert(key, tag);
+                }
+                Tag::Compound(owned)
+            },
+            TagRef::IntArray(xs) => Tag::IntArray(xs.iter().collect()),
+            TagRef::LongArray(xs) => Tag::LongArray(xs.iter().collect()),
+        })
+    }
+}
+
+impl<'a> StrRef<'a> {
+    /// Returns the encoded bytes of this string.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes this string, borrowing it whenever possible.
+    pub fn to_str(&self) -> Result<Cow<'a, str>, Error> {
+        match str::from_utf8(self.bytes) {
+            // plain UTF-8 reads the same, unless it has 4 byte sequences
+            Ok(s) if !self.bytes.iter().any(|&b| b >= 0xf0) => Ok(Cow::Borrowed(s)),
+            _ => decode_utf(self.bytes)
+                .map(Cow::Owned)
+                .map_err(|_| Error::new(ErrorKind::InvalidUtf, None)),
+        }
+    }
+}
+
+impl fmt::Debug for StrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_str() {
+            Ok(s) => fmt::Debug::fmt(&s, f),
+            Err(_) => f.debug_tuple("StrRef").field(&self.bytes).finish(),
+        }
+    }
+}
+
+impl PartialEq<str> for StrRef<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.bytes == other.as_bytes() || self.to_str().is_ok_and(|s| s == other)
+    }
+}
+
+impl<'a, T: Element> ArrayRef<'a, T> {
+    /// Returns the number of elements in this array.
+    pub fn len(&self) -> usize {
+        self.data.len() / T::SIZE
+    }
+
+    /// Checks if this array has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the element at `index`.
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.data
+            .chunks_exact(T::SIZE)
+            .nth(index)
+            .map(T::from_be_slice)
+    }
+
+    /// Iterates over the elements of this array.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = T> + 'a {
+        self.data.chunks_exact(T::SIZE).map(T::from_be_slice)
+    }
+}
+
+impl<'a> ListRef<'a> {
+    /// Returns a byte representing the kind of the elements of this list.
+    pub fn kind(&self) -> i8 {
+        self.kind
+    }
+
+    /// Returns the number of elements in this list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if this list has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the element at `index`, decoding the elements before it
+    /// unless they have a fixed size.
+    pub fn get(&self, index: usize) -> Option<TagRef<'a>> {
+        if index >= self.len {
+            return None
+        }
+        match fixed_size(self.kind) {
+            Some(size) => {
+                let mut cursor = Cursor::new(&self.data[index * size..]);
+                cursor.read_tag(self.kind, 0).ok()
+            },
+            None => self.iter().nth(index),
+        }
+    }
+
+    /// Iterates over the elements of this list.
+    pub fn iter(&self) -> ListIter<'a> {
+        ListIter {
+            kind: self.kind,
+            remaining: self.len,
+            cursor: Cursor::new(self.data),
+        }
+    }
+}
+
+impl<'a> IntoIterator for ListRef<'a> {
+    type Item = TagRef<'a>;
+    type IntoIter = ListIter<'a>;
+
+    fn into_iter(self) -> ListIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`ListRef`].
+pub struct ListIter<'a> {
+    kind: i8,
+    remaining: usize,
+    cursor: Cursor<'a>,
+}
+
+impl<'a> Iterator for ListIter<'a> {
+    type Item = TagRef<'a>;
+
+    fn next(&mut self) -> Option<TagRef<'a>> {
+        if self.remaining == 0 {
+            return None
+        }
+        self.remaining -= 1;
+        // the list was checked when it was parsed
+        self.cursor.read_tag(self.kind, 0).ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> CompoundRef<'a> {
+    /// Returns the value of the entry with the given `key`.
+    pub fn get(&self, key: &str) -> Option<TagRef<'a>> {
+        self.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, tag)| tag)
+    }
+
+    /// Returns the number of entries in this compound.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Checks if this compound has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterates over the entries of this compound.
+    pub fn iter(&self) -> CompoundIter<'a> {
+        CompoundIter { cursor: Cursor::new(self.data) }
+    }
+}
+
+impl<'a> IntoIterator for CompoundRef<'a> {
+    type Item = (StrRef<'a>, TagRef<'a>);
+    type IntoIter = CompoundIter<'a>;
+
+    fn into_iter(self) -> CompoundIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a [`CompoundRef`].
+pub struct CompoundIter<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> Iterator for CompoundIter<'a> {
+    type Item = (StrRef<'a>, TagRef<'a>);
+
+    fn next(&mut self) -> Option<(StrRef<'a>, TagRef<'a>)> {
+        if self.cursor.pos >= self.cursor.data.len() {
+            return None
+        }
+        // the compound was checked when it was parsed
+        let kind = self.cursor.read_byte().ok()?;
+        let key = self.cursor.read_str().ok()?;
+        let tag = self.cursor.read_tag(kind, 0).ok()?;
+        Some((key, tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufReader, Read};
+
+    use flate2::bufread::GzDecoder;
+
+    use crate::nbt;
+    use crate::serialize::DataOutput;
+    use crate::serialize::nbt::{ErrorKind, NBT, NBTRef, TagRef, Tag};
+
+    fn read_file(path: &str) -> Vec<u8> {
+        let f = File::open(path).unwrap();
+        let mut buf = Vec::new();
+        GzDecoder::new(BufReader::new(f)).read_to_end(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_borrowed_read() {
+        let buf = read_file("res/bigtest.dat");
+        let nbt = NBTRef::from_slice(&buf).unwrap();
+
+        assert_eq!(nbt.key().to_str().unwrap(), "Level");
+
+        match nbt.tag().get("listTest (long)") {
+            Some(TagRef::List(xs)) => {
+                assert_eq!(xs.len(), 5);
+                assert!(matches!(xs.get(2), Some(TagRef::Long(13))));
+                assert_eq!(xs.iter().count(), 5);
+            },
+            _ => panic!("not a list tag"),
+        }
+
+        match nbt.tag().get("nested compound test") {
+            Some(TagRef::Compound(m)) => match m.get("egg") {
+                Some(TagRef::Compound(egg)) => match egg.get("name") {
+                    Some(TagRef::String(name)) => assert!(name == *"Eggbert"),
+                    _ => panic!("not a string tag"),
+                },
+                _ => panic!("not a compound tag"),
+            },
+            _ => panic!("not a compound tag"),
+        }
+
+        let owned = nbt.to_nbt().unwrap();
+        match owned.tag() {
+            Tag::Compound(m) => assert_eq!(m.len(), nbt.tag().len()),
+            _ => panic!("not a compound tag"),
+        }
+    }
+
+    #[test]
+    fn test_borrowed_truncated() {
+        let buf = read_file("res/player.dat");
+
+        for len in 0..buf.len() {
+            assert!(NBTRef::from_slice(&buf[..len]).is_err());
+        }
+        assert!(NBTRef::from_slice(&buf).is_ok());
+
+        // lists claiming more elements than there are bytes, whatever their kind
+        let e = NBTRef::from_slice(b"\x0a\0\0\x09\0\x01l\0\x7f\xff\xff\xff\0").unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnknownTag(0)));
+        assert_eq!(e.offset(), Some(8));
+        let e = NBTRef::from_slice(b"\x0a\0\0\x09\0\x01l\x0a\x7f\xff\xff\xff\0").unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Truncated));
+
+        // but an empty list of end tags is fine
+        let nbt = NBTRef::from_slice(b"\x0a\0\0\x09\0\x01l\0\0\0\0\0\0").unwrap();
+        assert!(nbt.to_nbt().is_ok());
+    }
+
+    #[test]
+    fn test_borrowed_arrays() {
+        let tag = nbt!({ ints: [I; 1, -2], longs: [L; 3L], both: [[I; 4], [I;]] });
+        let mut output = DataOutput::new(Vec::new());
+        NBT::new("", tag.clone()).unwrap().write_to(&mut output).unwrap();
+        let buf = output.into_inner();
+
+        let nbt = NBTRef::from_slice(&buf).unwrap();
+        match nbt.tag().get("ints") {
+            Some(TagRef::IntArray(xs)) => assert_eq!(xs.iter().collect::<Vec<_>>(), [1, -2]),
+            _ => panic!("not an int array tag"),
+        }
+        match nbt.tag().get("longs") {
+            Some(TagRef::LongArray(xs)) => assert_eq!(xs.get(0), Some(3)),
+            _ => panic!("not a long array tag"),
+        }
+        match nbt.tag().get("both") {
+            Some(TagRef::List(xs)) => assert!(matches!(xs.get(1), Some(TagRef::IntArray(x)) if x.is_empty())),
+            _ => panic!("not a list tag"),
+        }
+        assert_eq!(nbt.to_nbt().unwrap().tag(), &tag);
+    }
+}
@@ -0,0 +1,149 @@
+//! Read and write NBT files, whatever their compression.
+//!
+//! Minecraft stores NBT compressed with gzip, such as `level.dat`, with zlib,
+//! such as the chunks of region files, or not at all. Reading sniffs the
+//! compression from the first bytes, and returns it so that a file can be
+//! written back in the same form:
+//!
+//! ```no_run
+//! # use neonmc::serialize::nbt::{NBT, Error};
+//! # fn main() -> Result<(), Error> {
+//! let (nbt, compression) = NBT::open("level.dat")?;
+//! nbt.save("level.dat", compression)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::path::Path;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use crate::serialize::{DataInput, DataOutput};
+use crate::serialize::nbt::{Error, Limits, NBT};
+
+/// The compression of an NBT file or stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Plain NBT.
+    None,
+    /// Compressed with gzip.
+    Gzip,
+    /// Compressed with zlib.
+    Zlib,
+}
+
+impl Compression {
+    /// Guesses the compression of a stream from its first two bytes.
+    pub fn detect(header: &[u8]) -> Compression {
+        match *header {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            // deflate, with a header checksum
+            [cmf, flg, ..] if cmf & 0x0f == 8 && u16::from_be_bytes([cmf, flg]) % 31 == 0 => {
+                Compression::Zlib
+            },
+            _ => Compression::None,
+        }
+    }
+}
+
+impl NBT {
+    /// This associated method decodes a new NBT from `r`, compressed or not,
+    /// within the default [`Limits`]. Returns the detected compression too.
+    pub fn read_compressed<R: Read>(r: R) -> Result<(NBT, Compression), Error> {
+        NBT::read_compressed_with_limits(r, &Limits::default())
+    }
+
+    /// This associated method decodes a new NBT from `r`, compressed or not,
+    /// failing as soon as the decompressed input goes beyond `limits`.
+    /// Returns the detected compression too.
+    pub fn read_compressed_with_limits<R: Read>(mut r: R, limits: &Limits) -> Result<(NBT, Compression), Error> {
+        // sniff the first bytes, then put them back in front
+        let mut header = Vec::with_capacity(2);
+        (&mut r)
+            .take(2)
+            .read_to_end(&mut header)
+            .map_err(|e| Error::from_io(e, None))?;
+
+        let compression = Compression::detect(&header);
+        let r = io::Cursor::new(header).chain(r);
+
+        let nbt = match compression {
+            Compression::None => NBT::read_with_limits(&mut DataInput::new(r), limits),
+            Compression::Gzip => NBT::read_with_limits(&mut DataInput::new(GzDecoder::new(r)), limits),
+            Compression::Zlib => NBT::read_with_limits(&mut DataInput::new(ZlibDecoder::new(r)), limits),
+        }?;
+
+        Ok((nbt, compression))
+    }
+
+    /// This method encodes an NBT tag into `w`, compressed as requested.
+    pub fn write_compressed<W: Write>(&self, w: W, compression: Compression) -> Result<(), Error> {
+        match compression {
+            Compression::None => {
+                let mut output = DataOutput::new(w);
+                self.write_to(&mut output)?;
+                output.into_inner().flush()
+            },
+            Compression::Gzip => {
+                let mut output = DataOutput::new(GzEncoder::new(w, Default::default()));
+                self.write_to(&mut output)?;
+                output.into_inner().finish().and_then(|mut w| w.flush())
+            },
+            Compression::Zlib => {
+                let mut output = DataOutput::new(ZlibEncoder::new(w, Default::default()));
+                self.write_to(&mut output)?;
+                output.into_inner().finish().and_then(|mut w| w.flush())
+            },
+        }
+        .map_err(|e| Error::from_io(e, None))
+    }
+
+    /// This associated method decodes a new NBT from the file at `path`,
+    /// compressed or not. Returns the detected compression too.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(NBT, Compression), Error> {
+        let f = File::open(path).map_err(|e| Error::from_io(e, None))?;
+        NBT::read_compressed(BufReader::new(f))
+    }
+
+    /// This method encodes an NBT tag into the file at `path`, compressed as
+    /// requested.
+    pub fn save<P: AsRef<Path>>(&self, path: P, compression: Compression) -> Result<(), Error> {
+        let f = File::create(path).map_err(|e| Error::from_io(e, None))?;
+        self.write_compressed(BufWriter::new(f), compression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serialize::nbt::{NBT, Tag, ErrorKind};
+    use crate::serialize::nbt::compression::Compression;
+
+    #[test]
+    fn test_compression_detect() {
+        let (nbt, compression) = NBT::open("res/bigtest.dat").unwrap();
+        assert_eq!(compression, Compression::Gzip);
+        assert_eq!(nbt.key(), "Level");
+
+        for &compression in &[Compression::None, Compression::Gzip, Compression::Zlib] {
+            let mut buf = Vec::new();
+            nbt.write_compressed(&mut buf, compression).unwrap();
+            assert_eq!(Compression::detect(&buf), compression);
+
+            let (decoded, detected) = NBT::read_compressed(&buf[..]).unwrap();
+            assert_eq!(detected, compression);
+            match decoded.tag() {
+                Tag::Compound(m) => assert_eq!(m.len(), 11),
+                _ => panic!("not a compound tag"),
+            }
+        }
+
+        let e = NBT::read_compressed(&[0x1f][..]).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnexpectedTag { expected: 10, found: 0x1f }));
+
+        let e = NBT::read_compressed(&[0x1f, 0x8b, 0][..]).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Truncated | ErrorKind::Io(_)));
+    }
+}
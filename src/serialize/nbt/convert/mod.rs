@@ -0,0 +1,399 @@
+//! Convert Rust values to and from tags.
+//!
+//! [`ToTag`] and [`FromTag`] map numbers, strings, lists and compounds to
+//! the tags of the same kind. Structs derive [`NbtCompound`] to map to a
+//! compound with an entry per field:
+//!
+//! ```
+//! use neonmc::serialize::nbt::{NbtCompound, Tag};
+//!
+//! #[derive(NbtCompound)]
+//! struct Item {
+//!     #[nbt(short)]
+//!     id: u16,
+//!     #[nbt(rename = "Count")]
+//!     count: i8,
+//!     #[nbt(default)]
+//!     damage: i16,
+//! }
+//!
+//! let item = Item { id: 276, count: 1, damage: 0 };
+//! let tag = Tag::Compound(item.to_compound());
+//! assert_eq!(tag.to_string(), "{id:276s,Count:1b,damage:0s}");
+//! ```
+//!
+//! Fields take these attributes:
+//!
+//! - `#[nbt(rename = "Key")]` names the entry, which is the name of the
+//!   field otherwise.
+//! - `#[nbt(byte)]`, `short`, `int`, `long`, `float` and `double` choose
+//!   the width of a number, converted with `as` when written, and checked
+//!   when read. Unsigned numbers are kept by their bits, so that a `u8` of
+//!   200 is written as `-56b` and read back as 200. `byte_array`,
+//!   `int_array` and `long_array` map a `Vec` to an array rather than to a
+//!   list.
+//! - `#[nbt(default)]` or `#[nbt(default = "function")]` fills in a missing
+//!   entry, rather than failing. `Option` fields are missing when `None`,
+//!   and take no default.
+//! - `#[nbt(flatten)]` merges the entries of a field that derives
+//!   [`NbtCompound`] itself into those of the struct.
+
+use std::fmt;
+use std::error;
+use std::convert::TryFrom;
+
+use crate::serialize::nbt::{Compound, NBT, Segment, Tag};
+
+pub use neonmc_derive::NbtCompound;
+
+/// Values that convert to a tag.
+pub trait ToTag {
+    /// Returns this value as a tag.
+    fn to_tag(&self) -> Tag;
+}
+
+/// Values that convert from a tag.
+pub trait FromTag: Sized {
+    /// Returns the value of `tag`, which must be of the right kind.
+    fn from_tag(tag: &Tag) -> Result<Self, Error>;
+}
+
+/// Values that convert to and from a compound.
+pub trait NbtCompound: Sized {
+    /// Returns the entries of this value.
+    fn to_compound(&self) -> Compound;
+
+    /// Returns the value with the entries of `m`.
+    fn from_compound(m: &Compound) -> Result<Self, Error>;
+
+    /// Returns this value as an [`NBT`] with the given key.
+    fn to_nbt(&self, key: &str) -> NBT {
+        NBT { key: key.into(), tag: Tag::Compound(self.to_compound()) }
+    }
+
+    /// Returns the value of the compound of `nbt`.
+    fn from_nbt(nbt: &NBT) -> Result<Self, Error> {
+        Self::from_compound(__compound(nbt.tag())?)
+    }
+}
+
+/// The reason a tag could not be converted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A compound lacks an entry without a default.
+    Missing,
+    /// A tag of another kind was expected.
+    WrongKind { expected: i8, found: i8 },
+    /// A number does not fit in the type of its field.
+    OutOfRange,
+}
+
+/// An error raised while converting a tag, along with the path to the
+/// offending value.
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    // innermost segment first
+    path: Vec<Segment>,
+}
+
+impl Error {
+    fn new(kind: ErrorKind) -> Self {
+        Self { kind, path: Vec::new() }
+    }
+
+    fn wrong_kind(expected: i8, tag: &Tag) -> Self {
+        Self::new(ErrorKind::WrongKind { expected, found: tag.kind() })
+    }
+
+    #[doc(hidden)]
+    pub fn in_key(mut self, key: &str) -> Self {
+        self.path.push(Segment::Key(key.into()));
+        self
+    }
+
+    fn in_index(mut self, index: usize) -> Self {
+        self.path.push(Segment::Index(index));
+        self
+    }
+
+    /// Returns the reason of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns the path to the offending value, from the root.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.path.iter().rev()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Missing => f.write_str("missing entry")?,
+            ErrorKind::WrongKind { expected, found } => {
+                write!(f, "expected tag kind {}, found tag kind {}", expected, found)?
+            },
+            ErrorKind::OutOfRange => f.write_str("number out of range")?,
+        }
+
+        if !self.path.is_empty() {
+            let path: Vec<Segment> = self.path().cloned().collect();
+            f.write_str(" at ")?;
+            super::path::write_segments(f, &path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
+
+macro_rules! convert {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl ToTag for $ty {
+                fn to_tag(&self) -> Tag {
+                    Tag::from(self.clone())
+                }
+            }
+
+            impl FromTag for $ty {
+                fn from_tag(tag: &Tag) -> Result<Self, Error> {
+                    match tag {
+                        Tag::$variant(x) => Ok(x.clone()),
+                        _ => Err(Error::wrong_kind(Tag::$variant(Default::default()).kind(), tag)),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+convert! {
+    i8 => Byte,
+    i16 => Short,
+    i32 => Int,
+    i64 => Long,
+    f32 => Float,
+    f64 => Double,
+    String => String,
+    Compound => Compound,
+}
+
+impl ToTag for bool {
+    fn to_tag(&self) -> Tag {
+        Tag::Byte(*self as i8)
+    }
+}
+
+impl FromTag for bool {
+    fn from_tag(tag: &Tag) -> Result<Self, Error> {
+        i8::from_tag(tag).map(|x| x != 0)
+    }
+}
+
+impl ToTag for Tag {
+    fn to_tag(&self) -> Tag {
+        self.clone()
+    }
+}
+
+impl FromTag for Tag {
+    fn from_tag(tag: &Tag) -> Result<Self, Error> {
+        Ok(tag.clone())
+    }
+}
+
+impl<T: ToTag> ToTag for Vec<T> {
+    fn to_tag(&self) -> Tag {
+        Tag::list(self.iter().map(T::to_tag).collect())
+    }
+}
+
+impl<T: FromTag> FromTag for Vec<T> {
+    fn from_tag(tag: &Tag) -> Result<Self, Error> {
+        tag.as_list()
+            .ok_or_else(|| Error::wrong_kind(0x09, tag))?
+            .iter()
+            .enumerate()
+            .map(|(i, x)| T::from_tag(x).map_err(|e| e.in_index(i)))
+            .collect()
+    }
+}
+
+// helpers for the code generated by `#[derive(NbtCompound)]`
+
+#[doc(hidden)]
+pub fn __missing(key: &str) -> Error {
+    Error::new(ErrorKind::Missing).in_key(key)
+}
+
+#[doc(hidden)]
+pub fn __get<T, F>(m: &Compound, key: &str, convert: F) -> Result<Option<T>, Error>
+where
+    F: FnOnce(&Tag) -> Result<T, Error>,
+{
+    m.get(key)
+        .map(|tag| convert(tag).map_err(|e| e.in_key(key)))
+        .transpose()
+}
+
+#[doc(hidden)]
+pub fn __from_tag<T: FromTag>(tag: &Tag) -> Result<T, Error> {
+    T::from_tag(tag)
+}
+
+#[doc(hidden)]
+pub fn __compound(tag: &Tag) -> Result<&Compound, Error> {
+    tag.as_compound().ok_or_else(|| Error::wrong_kind(0x0a, tag))
+}
+
+macro_rules! width {
+    ($($name:ident: $variant:ident($x:ty)),* $(,)?) => {
+        $(
+            #[doc(hidden)]
+            pub fn $name<T: TryFrom<$x>>(tag: &Tag) -> Result<T, Error> {
+                match tag {
+                    Tag::$variant(x) => T::try_from(*x).map_err(|_| Error::new(ErrorKind::OutOfRange)),
+                    _ => Err(Error::wrong_kind(Tag::$variant(Default::default()).kind(), tag)),
+                }
+            }
+        )*
+    }
+}
+
+// integers also read back as their bits, the way unsigned ones are written
+macro_rules! integer_width {
+    ($($name:ident: $variant:ident($x:ty, $bits:ty)),* $(,)?) => {
+        $(
+            #[doc(hidden)]
+            pub fn $name<T: TryFrom<$x> + TryFrom<$bits>>(tag: &Tag) -> Result<T, Error> {
+                match tag {
+                    Tag::$variant(x) => T::try_from(*x)
+                        .or_else(|_| T::try_from(*x as $bits))
+                        .map_err(|_| Error::new(ErrorKind::OutOfRange)),
+                    _ => Err(Error::wrong_kind(Tag::$variant(Default::default()).kind(), tag)),
+                }
+            }
+        )*
+    }
+}
+
+integer_width! {
+    __byte: Byte(i8, u8),
+    __short: Short(i16, u16),
+    __int: Int(i32, u32),
+    __long: Long(i64, u64),
+}
+
+width! {
+    __float: Float(f32),
+    __double: Double(f64),
+}
+
+macro_rules! array {
+    ($($name:ident: $variant:ident($x:ty)),* $(,)?) => {
+        $(
+            #[doc(hidden)]
+            pub fn $name<T: From<Vec<$x>>>(tag: &Tag) -> Result<T, Error> {
+                match tag {
+                    Tag::$variant(xs) => Ok(T::from(xs.clone())),
+                    _ => Err(Error::wrong_kind(Tag::$variant(Default::default()).kind(), tag)),
+                }
+            }
+        )*
+    }
+}
+
+array! {
+    __byte_array: ByteArray(i8),
+    __int_array: IntArray(i32),
+    __long_array: LongArray(i64),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serialize::nbt::{FromTag, NbtCompound, NBT, Tag};
+    use crate::serialize::nbt::convert::ErrorKind;
+
+    #[derive(Debug, PartialEq, NbtCompound)]
+    struct Item {
+        id: i16,
+        #[nbt(rename = "Count")]
+        count: i8,
+        #[nbt(rename = "Slot", byte)]
+        slot: u8,
+        #[nbt(rename = "Damage", short, default)]
+        damage: u16,
+    }
+
+    #[derive(Debug, PartialEq, NbtCompound)]
+    struct Entity {
+        #[nbt(rename = "Pos")]
+        pos: Vec<f64>,
+        #[nbt(rename = "OnGround")]
+        on_ground: bool,
+    }
+
+    #[derive(Debug, PartialEq, NbtCompound)]
+    struct Player {
+        #[nbt(flatten)]
+        entity: Entity,
+        #[nbt(rename = "Health", short)]
+        health: u8,
+        #[nbt(rename = "Inventory")]
+        inventory: Vec<Item>,
+        #[nbt(rename = "Dimension")]
+        dimension: Option<i32>,
+        #[nbt(rename = "Score", default = "default_score")]
+        score: i32,
+        #[nbt(int_array)]
+        heights: Vec<i32>,
+    }
+
+    fn default_score() -> i32 {
+        -1
+    }
+
+    #[test]
+    fn test_convert_derive() {
+        let player = Player {
+            entity: Entity { pos: vec![1.0, 64.0, 1.0], on_ground: true },
+            health: 20,
+            inventory: vec![Item { id: 1, count: 64, slot: 200, damage: 3 }],
+            dimension: None,
+            score: 10,
+            heights: vec![1, 2],
+        };
+
+        let nbt = player.to_nbt("");
+        assert_eq!(
+            nbt.tag().to_string(),
+            "{Pos:[1.0d,64.0d,1.0d],OnGround:1b,Health:20s,\
+             Inventory:[{id:1s,Count:64b,Slot:-56b,Damage:3s}],Score:10,heights:[I;1,2]}",
+        );
+
+        // unsigned values go through their signed width, and back
+        assert_eq!(Player::from_nbt(&nbt).unwrap(), player);
+
+        let nbt: NBT = "{Pos:[0.0d,0.0d,0.0d],OnGround:0b,Health:3s,Inventory:[{id:1s,Count:1b,Slot:2b}],\
+                        Dimension:-1,heights:[I;]}".parse().unwrap();
+        let player = Player::from_nbt(&nbt).unwrap();
+        assert_eq!(player.inventory, [Item { id: 1, count: 1, slot: 2, damage: 0 }]);
+        assert_eq!(player.dimension, Some(-1));
+        assert_eq!(player.score, -1);
+        assert!(!player.entity.on_ground);
+
+        let e = Player::from_tag(&"{Pos:[],OnGround:0b,Health:3,Inventory:[]}".parse().unwrap()).unwrap_err();
+        assert_eq!(e.to_string(), "expected tag kind 2, found tag kind 3 at Health");
+        let e = Player::from_tag(&"{Pos:[],Health:3s,Inventory:[]}".parse().unwrap()).unwrap_err();
+        assert_eq!(e.to_string(), "missing entry at OnGround");
+        let e = Player::from_tag(&"{Pos:[],OnGround:0b,Health:-1s,Inventory:[]}".parse().unwrap()).unwrap_err();
+        assert_eq!(e.kind(), &ErrorKind::OutOfRange);
+        assert_eq!(e.to_string(), "number out of range at Health");
+        let e = Player::from_tag(&Tag::Int(1)).unwrap_err();
+        assert_eq!(e.kind(), &ErrorKind::WrongKind { expected: 10, found: 3 });
+    }
+}
@@ -0,0 +1,405 @@
+//! Compare, patch and merge NBT trees.
+//!
+//! [`diff`] lists the entries added, removed or changed between two trees,
+//! by their path, which [`apply`] replays onto a tree. [`merge`] combines
+//! the edits made to a common base, such as a player file edited by two
+//! people at once, and reports the values both changed differently.
+//!
+//! Compounds are compared entry by entry, and lists of the same kind of
+//! tags element by element, their extra elements being added or removed at
+//! the end. Anything else, including arrays, changes as a whole. Floats are
+//! compared by their bits, so that a NaN equals itself.
+
+use std::fmt;
+use std::error;
+
+use crate::serialize::nbt::{Compound, Segment, Tag};
+use crate::serialize::nbt::path::write_segments;
+
+/// A difference between two trees.
+#[derive(Clone, Debug)]
+pub enum Change {
+    /// A value that only the new tree has.
+    Added { path: Vec<Segment>, value: Tag },
+    /// A value that only the old tree has.
+    Removed { path: Vec<Segment>, value: Tag },
+    /// A value that differs between the trees.
+    Changed { path: Vec<Segment>, old: Tag, new: Tag },
+}
+
+impl Change {
+    /// Returns the path to the value, from the root.
+    pub fn path(&self) -> &[Segment] {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => {
+                f.write_str("+ ")?;
+                write_segments(f, path)?;
+                write!(f, ": {}", value)
+            },
+            Change::Removed { path, value } => {
+                f.write_str("- ")?;
+                write_segments(f, path)?;
+                write!(f, ": {}", value)
+            },
+            Change::Changed { path, old, new } => {
+                f.write_str("~ ")?;
+                write_segments(f, path)?;
+                write!(f, ": {} -> {}", old, new)
+            },
+        }
+    }
+}
+
+/// Values that both sides of a merge changed, each in their own way.
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    /// The path to the value, from the root.
+    pub path: Vec<Segment>,
+    /// The value in the common base, if any.
+    pub base: Option<Tag>,
+    /// Our value, if any, which the merged tree keeps.
+    pub ours: Option<Tag>,
+    /// Their value, if any.
+    pub theirs: Option<Tag>,
+}
+
+/// The result of a three-way merge.
+#[derive(Clone, Debug)]
+pub struct Merge {
+    /// The merged tree, with our values where they conflict.
+    pub tag: Tag,
+    /// The values that conflict, in the order of the tree.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// The reason a change could not be applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// There is no value at the path of the change, or nowhere to add one.
+    Missing,
+    /// The value at the path of the change is not the one it expects.
+    Stale,
+}
+
+/// An error raised while applying a change, along with its path.
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    path: Vec<Segment>,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, path: &[Segment]) -> Self {
+        Self { kind, path: path.to_vec() }
+    }
+
+    /// Returns the reason of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns the path of the change, from the root.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.path.iter()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Missing => f.write_str("no value at ")?,
+            ErrorKind::Stale => f.write_str("unexpected value at ")?,
+        }
+        write_segments(f, &self.path)
+    }
+}
+
+impl error::Error for Error {}
+
+/// Returns the changes that turn `old` into `new`.
+pub fn diff(old: &Tag, new: &Tag) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_into(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn diff_into(old: &Tag, new: &Tag, path: &mut Vec<Segment>, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Tag::Compound(xs), Tag::Compound(ys)) => {
+            for (key, x) in xs {
+                path.push(Segment::Key(key.clone()));
+                match ys.get(key) {
+                    Some(y) => diff_into(x, y, path, changes),
+                    None => changes.push(Change::Removed { path: path.clone(), value: x.clone() }),
+                }
+                path.pop();
+            }
+            for (key, y) in ys {
+                if !xs.contains_key(key) {
+                    path.push(Segment::Key(key.clone()));
+                    changes.push(Change::Added { path: path.clone(), value: y.clone() });
+                    path.pop();
+                }
+            }
+        },
+        (Tag::List(a, xs), Tag::List(b, ys)) if a == b => {
+            for (i, (x, y)) in xs.iter().zip(ys).enumerate() {
+                path.push(Segment::Index(i));
+                diff_into(x, y, path, changes);
+                path.pop();
+            }
+            for (i, y) in ys.iter().enumerate().skip(xs.len()) {
+                path.push(Segment::Index(i));
+                changes.push(Change::Added { path: path.clone(), value: y.clone() });
+                path.pop();
+            }
+            // from the end, so that the indices hold as they are removed
+            for (i, x) in xs.iter().enumerate().skip(ys.len()).rev() {
+                path.push(Segment::Index(i));
+                changes.push(Change::Removed { path: path.clone(), value: x.clone() });
+                path.pop();
+            }
+        },
+        _ if old == new => (),
+        _ => changes.push(Change::Changed { path: path.clone(), old: old.clone(), new: new.clone() }),
+    }
+}
+
+/// Applies `changes` to `tag` in order.
+///
+/// Every change must find the value it expects: nothing where a value is
+/// added, and the old value where one is removed or changed. Otherwise, the
+/// changes before it stay applied.
+pub fn apply(tag: &mut Tag, changes: &[Change]) -> Result<(), Error> {
+    changes.iter().try_for_each(|change| apply_one(tag, change))
+}
+
+fn apply_one(tag: &mut Tag, change: &Change) -> Result<(), Error> {
+    let path = change.path();
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            return match change {
+                Change::Changed { old, new, .. } if tag == old => {
+                    *tag = new.clone();
+                    Ok(())
+                },
+                Change::Changed { .. } => Err(Error::new(ErrorKind::Stale, path)),
+                _ => Err(Error::new(ErrorKind::Missing, path)),
+            }
+        },
+    };
+
+    let mut parent = tag;
+    for segment in parents {
+        parent = child_mut(parent, segment).ok_or_else(|| Error::new(ErrorKind::Missing, path))?;
+    }
+
+    let missing = || Error::new(ErrorKind::Missing, path);
+    let stale = || Error::new(ErrorKind::Stale, path);
+
+    match (parent, last) {
+        (Tag::Compound(m), Segment::Key(key)) => match (change, m.get_mut(key)) {
+            (Change::Added { value, .. }, None) => {
+                m.insert(key.clone(), value.clone());
+            },
+            (Change::Removed { value, .. }, Some(x)) if x == value => {
+                m.shift_remove(key);
+            },
+            (Change::Changed { old, new, .. }, Some(x)) if x == old => *x = new.clone(),
+            (Change::Added { .. }, Some(_)) | (_, Some(_)) => return Err(stale()),
+            (_, None) => return Err(missing()),
+        },
+        (Tag::List(kind, xs), &Segment::Index(i)) => match (change, xs.len(), xs.get(i)) {
+            (Change::Added { value, .. }, len, None) if i == len => {
+                if xs.is_empty() {
+                    *kind = value.kind();
+                } else if *kind != value.kind() {
+                    return Err(stale())
+                }
+                xs.push(value.clone());
+            },
+            (Change::Removed { value, .. }, len, Some(x)) if x == value && i + 1 == len => {
+                xs.pop();
+            },
+            (Change::Changed { old, new, .. }, _, Some(x)) if x == old && old.kind() == new.kind() => {
+                xs[i] = new.clone()
+            },
+            (_, _, Some(_)) => return Err(stale()),
+            (_, _, None) => return Err(missing()),
+        },
+        _ => return Err(missing()),
+    }
+
+    Ok(())
+}
+
+fn child_mut<'a>(tag: &'a mut Tag, segment: &Segment) -> Option<&'a mut Tag> {
+    match (tag, segment) {
+        (Tag::Compound(m), Segment::Key(key)) => m.get_mut(key),
+        (Tag::List(_, xs), &Segment::Index(i)) => xs.get_mut(i),
+        _ => None,
+    }
+}
+
+/// Merges the changes made from `base` to `ours` with the ones made from
+/// `base` to `theirs`.
+pub fn merge(base: &Tag, ours: &Tag, theirs: &Tag) -> Merge {
+    let mut conflicts = Vec::new();
+    let tag = merge_into(Some(base), Some(ours), Some(theirs), &mut Vec::new(), &mut conflicts)
+        .unwrap_or_else(|| ours.clone());
+    Merge { tag, conflicts }
+}
+
+// merges one value, `None` standing for a missing one
+fn merge_into(
+    base: Option<&Tag>,
+    ours: Option<&Tag>,
+    theirs: Option<&Tag>,
+    path: &mut Vec<Segment>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Tag> {
+    if ours == theirs || base == theirs {
+        return ours.cloned()
+    }
+    if base == ours {
+        return theirs.cloned()
+    }
+
+    match (base, ours, theirs) {
+        (Some(Tag::Compound(b)), Some(Tag::Compound(o)), Some(Tag::Compound(t))) => {
+            let mut m = Compound::new();
+            let keys = o.keys().chain(t.keys().filter(|key| !o.contains_key(*key)));
+            for key in keys {
+                path.push(Segment::Key(key.clone()));
+                if let Some(x) = merge_into(b.get(key), o.get(key), t.get(key), path, conflicts) {
+                    m.insert(key.clone(), x);
+                }
+                path.pop();
+            }
+            Some(Tag::Compound(m))
+        },
+        (Some(Tag::List(a, b)), Some(Tag::List(c, o)), Some(Tag::List(d, t)))
+            if a == c && c == d && b.len() == o.len() && o.len() == t.len() =>
+        {
+            let mut xs = Vec::with_capacity(o.len());
+            for (i, ((b, o), t)) in b.iter().zip(o).zip(t).enumerate() {
+                path.push(Segment::Index(i));
+                xs.extend(merge_into(Some(b), Some(o), Some(t), path, conflicts));
+                path.pop();
+            }
+            Some(Tag::List(*c, xs))
+        },
+        _ => {
+            conflicts.push(Conflict {
+                path: path.clone(),
+                base: base.cloned(),
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            });
+            ours.cloned()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nbt;
+    use crate::serialize::nbt::{Segment, Tag};
+    use crate::serialize::nbt::diff::{self, Change, ErrorKind};
+
+    fn player() -> Tag {
+        nbt!({
+            Health: 20s,
+            Pos: [1.0d, 64.0d, 1.0d],
+            Inventory: [
+                { Slot: 0B, id: 1s, Count: 64b },
+                { Slot: 1B, id: 4s, Count: 12b },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_diff_apply() {
+        let old = player();
+        let new = nbt!({
+            Health: 18s,
+            Pos: [1.0d, 64.0d, 1.0d],
+            Inventory: [{ Slot: 0B, id: 1s, Count: 63b }],
+            OnGround: 1b,
+        });
+
+        let changes = diff::diff(&old, &new);
+        let printed: Vec<String> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(printed, [
+            "~ Health: 20s -> 18s",
+            "~ Inventory[0].Count: 64b -> 63b",
+            "- Inventory[1]: {Slot:1b,id:4s,Count:12b}",
+            "+ OnGround: 1b",
+        ]);
+
+        let mut patched = old.clone();
+        diff::apply(&mut patched, &changes).unwrap();
+        assert!(diff::diff(&patched, &new).is_empty());
+
+        // the same changes no longer apply
+        let e = diff::apply(&mut patched, &changes).unwrap_err();
+        assert_eq!(e.kind(), &ErrorKind::Stale);
+        assert_eq!(e.to_string(), "unexpected value at Health");
+
+        let e = diff::apply(&mut nbt!({}), &changes[1..2]).unwrap_err();
+        assert_eq!(e.kind(), &ErrorKind::Missing);
+
+        // lists of other kinds and arrays change as a whole
+        let changes = diff::diff(&nbt!({ xs: [1, 2], ys: [I; 1] }), &nbt!({ xs: [1b], ys: [I; 2] }));
+        assert!(matches!(&changes[..], [Change::Changed { .. }, Change::Changed { .. }]));
+        assert!(diff::diff(&nbt!([1.0f, (f32::NAN)]), &nbt!([1.0f, (f32::NAN)])).is_empty());
+    }
+
+    #[test]
+    fn test_diff_merge() {
+        let base = player();
+        let ours = nbt!({
+            Health: 10s,
+            Pos: [2.0d, 64.0d, 1.0d],
+            Inventory: [
+                { Slot: 0B, id: 1s, Count: 64b },
+                { Slot: 1B, id: 4s, Count: 11b },
+            ],
+        });
+        let theirs = nbt!({
+            Health: 20s,
+            Pos: [3.0d, 64.0d, 1.0d],
+            Inventory: [
+                { Slot: 0B, id: 1s, Count: 32b },
+                { Slot: 1B, id: 4s, Count: 12b },
+            ],
+            XpLevel: 3,
+        });
+
+        let merge = diff::merge(&base, &ours, &theirs);
+        assert_eq!(
+            merge.tag.to_string(),
+            "{Health:10s,Pos:[2.0d,64.0d,1.0d],Inventory:[{Slot:0b,id:1s,Count:32b},\
+             {Slot:1b,id:4s,Count:11b}],XpLevel:3}",
+        );
+
+        assert_eq!(merge.conflicts.len(), 1);
+        let conflict = &merge.conflicts[0];
+        assert_eq!(conflict.path, [Segment::Key("Pos".into()), Segment::Index(0)]);
+        assert!(matches!(conflict.theirs, Some(Tag::Double(x)) if x == 3.0));
+
+        // a removal on one side and an edit on the other
+        let merge = diff::merge(&base, &nbt!({}), &nbt!({ Health: 1s }));
+        assert_eq!(merge.conflicts.len(), 1);
+        assert!(merge.conflicts[0].ours.is_none());
+    }
+}
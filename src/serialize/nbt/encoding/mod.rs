@@ -0,0 +1,185 @@
+use std::io::{self, Read, Write};
+
+use crate::serialize::{DataInput, DataOutput, InvalidUtf};
+
+/// How the numbers and strings of NBT are laid out in bytes.
+///
+/// All the encodings share the same tags, so a [`Tag`](super::Tag) tree
+/// decoded from one can be encoded to any other.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Big-endian numbers and modified UTF-8 strings, as Java edition does.
+    #[default]
+    BigEndian,
+    /// Little-endian numbers and UTF-8 strings, as Bedrock edition stores
+    /// them on disk.
+    LittleEndian,
+    /// Like [`Encoding::LittleEndian`], except that ints, longs and lengths
+    /// are zigzag varints, as Bedrock edition sends them over the network.
+    Network,
+}
+
+impl Encoding {
+    pub(crate) fn read_short<R: Read>(self, input: &mut DataInput<R>) -> io::Result<i16> {
+        match self {
+            Encoding::BigEndian => input.read_short(),
+            _ => read_le(input).map(i16::from_le_bytes),
+        }
+    }
+
+    pub(crate) fn read_int<R: Read>(self, input: &mut DataInput<R>) -> io::Result<i32> {
+        match self {
+            Encoding::BigEndian => input.read_int(),
+            Encoding::LittleEndian => read_le(input).map(i32::from_le_bytes),
+            Encoding::Network => input.read_varint().map(|x| unzigzag(x as u32 as u64) as i32),
+        }
+    }
+
+    pub(crate) fn read_long<R: Read>(self, input: &mut DataInput<R>) -> io::Result<i64> {
+        match self {
+            Encoding::BigEndian => input.read_long(),
+            Encoding::LittleEndian => read_le(input).map(i64::from_le_bytes),
+            Encoding::Network => input.read_varlong().map(|x| unzigzag(x as u64)),
+        }
+    }
+
+    pub(crate) fn read_float<R: Read>(self, input: &mut DataInput<R>) -> io::Result<f32> {
+        match self {
+            Encoding::BigEndian => input.read_float(),
+            _ => read_le(input).map(f32::from_le_bytes),
+        }
+    }
+
+    pub(crate) fn read_double<R: Read>(self, input: &mut DataInput<R>) -> io::Result<f64> {
+        match self {
+            Encoding::BigEndian => input.read_double(),
+            _ => read_le(input).map(f64::from_le_bytes),
+        }
+    }
+
+    pub(crate) fn read_ints<R: Read>(self, input: &mut DataInput<R>, count: usize) -> io::Result<Vec<i32>> {
+        match self {
+            Encoding::BigEndian => input.read_ints(count),
+            _ => (0..count).map(|_| self.read_int(input)).collect(),
+        }
+    }
+
+    pub(crate) fn read_longs<R: Read>(self, input: &mut DataInput<R>, count: usize) -> io::Result<Vec<i64>> {
+        match self {
+            Encoding::BigEndian => input.read_longs(count),
+            _ => (0..count).map(|_| self.read_long(input)).collect(),
+        }
+    }
+
+    pub(crate) fn read_utf<R: Read>(self, input: &mut DataInput<R>) -> io::Result<String> {
+        let size = match self {
+            Encoding::BigEndian => return input.read_utf(),
+            Encoding::LittleEndian => self.read_short(input)? as u16 as usize,
+            Encoding::Network => input.read_varint()? as u32 as usize,
+        };
+
+        // the size is not checked against any limit, so the string is only
+        // allocated as its bytes come in
+        let buf = input.read_to_vec(size)?;
+        String::from_utf8(buf).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, InvalidUtf))
+    }
+
+    // skips over a string without decoding it
+    pub(crate) fn skip_utf<R: Read>(self, input: &mut DataInput<R>) -> io::Result<()> {
+        let size = match self {
+            Encoding::Network => input.read_varint()? as u32 as usize,
+            _ => self.read_short(input)? as u16 as usize,
+        };
+        input.skip_bytes(size)
+    }
+
+    // skips over `count` ints, or longs if `size` is 8
+    pub(crate) fn skip_numbers<R: Read>(self, input: &mut DataInput<R>, count: usize, size: usize) -> io::Result<()> {
+        match self {
+            Encoding::Network => (0..count).try_for_each(|_| input.read_varlong().map(drop)),
+            _ => input.skip_bytes(count.saturating_mul(size)),
+        }
+    }
+
+    pub(crate) fn write_short<W: Write>(self, output: &mut DataOutput<W>, x: i16) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_short(x),
+            _ => output.write_raw(&x.to_le_bytes()),
+        }
+    }
+
+    pub(crate) fn write_int<W: Write>(self, output: &mut DataOutput<W>, x: i32) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_int(x),
+            Encoding::LittleEndian => output.write_raw(&x.to_le_bytes()),
+            Encoding::Network => output.write_varint(zigzag(x as i64) as i32),
+        }
+    }
+
+    pub(crate) fn write_long<W: Write>(self, output: &mut DataOutput<W>, x: i64) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_long(x),
+            Encoding::LittleEndian => output.write_raw(&x.to_le_bytes()),
+            Encoding::Network => output.write_varlong(zigzag(x) as i64),
+        }
+    }
+
+    pub(crate) fn write_float<W: Write>(self, output: &mut DataOutput<W>, x: f32) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_float(x),
+            _ => output.write_raw(&x.to_le_bytes()),
+        }
+    }
+
+    pub(crate) fn write_double<W: Write>(self, output: &mut DataOutput<W>, x: f64) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_double(x),
+            _ => output.write_raw(&x.to_le_bytes()),
+        }
+    }
+
+    pub(crate) fn write_ints<W: Write>(self, output: &mut DataOutput<W>, xs: &[i32]) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_ints(xs),
+            _ => xs.iter().try_for_each(|&x| self.write_int(output, x)),
+        }
+    }
+
+    pub(crate) fn write_longs<W: Write>(self, output: &mut DataOutput<W>, xs: &[i64]) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => output.write_longs(xs),
+            _ => xs.iter().try_for_each(|&x| self.write_long(output, x)),
+        }
+    }
+
+    pub(crate) fn write_utf<W: Write>(self, output: &mut DataOutput<W>, s: &str) -> io::Result<()> {
+        match self {
+            Encoding::BigEndian => return output.write_utf(s),
+            Encoding::LittleEndian => {
+                // string size overflows u16
+                if s.len() > 0xffff {
+                    return Err(io::Error::other("string size overflows u16"));
+                }
+                self.write_short(output, s.len() as u16 as i16)?;
+            },
+            Encoding::Network => output.write_varint(s.len() as u32 as i32)?,
+        }
+        output.write_raw(s.as_bytes())
+    }
+}
+
+// reads the bytes of a little-endian number
+fn read_le<R: Read, const N: usize>(input: &mut DataInput<R>) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    input.read_raw(&mut buf)?;
+    Ok(buf)
+}
+
+// maps signed values to unsigned ones, small magnitudes first
+fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+fn unzigzag(x: u64) -> i64 {
+    (x >> 1) as i64 ^ -((x & 1) as i64)
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::error;
+use std::io;
+
+use crate::serialize::{DataInput, DataOutput, InvalidUtf};
+use crate::serialize::nbt::Version;
+
+/// The reason NBT could not be decoded or encoded.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// A tag id that does not name any tag.
+    UnknownTag(i8),
+    /// A tag of another kind was expected, such as the root compound.
+    UnexpectedTag { expected: i8, found: i8 },
+    /// The input ended in the middle of a value.
+    Truncated,
+    /// A string is not validly encoded.
+    InvalidUtf,
+    /// The length of a list or array is negative.
+    NegativeLength(i32),
+    /// Lists and compounds are nested deeper than the given depth.
+    DepthExceeded(usize),
+    /// A list or array is longer than allowed.
+    LengthExceeded { len: usize, max: usize },
+    /// Decoding would allocate more than the given number of bytes.
+    SizeExceeded(usize),
+    /// The elements of a list are not all of the same kind.
+    HeterogeneousList { expected: i8, found: i8 },
+    /// A tag that the version being written does not have.
+    UnsupportedTag { kind: i8, version: Version },
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+}
+
+/// A step along the path to a value in an NBT tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// The entry of a compound with the given key.
+    Key(String),
+    /// The element of a list or array at the given index.
+    Index(usize),
+}
+
+/// An error raised while decoding or encoding NBT.
+///
+/// Besides its [`ErrorKind`], an error records the byte offset in the stream
+/// where it was raised, and the path to the offending value, such as
+/// `Data/Player/Inventory[3]`.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    // innermost segment first
+    path: Vec<Segment>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, offset: Option<u64>) -> Self {
+        Self { kind, offset, path: Vec::new() }
+    }
+
+    pub(crate) fn from_io(e: io::Error, offset: Option<u64>) -> Self {
+        let kind = match e.kind() {
+            io::ErrorKind::UnexpectedEof => ErrorKind::Truncated,
+            io::ErrorKind::InvalidData if is_invalid_utf(&e) => ErrorKind::InvalidUtf,
+            _ => ErrorKind::Io(e),
+        };
+        Self::new(kind, offset)
+    }
+
+    // prefixes the path of the error with a key
+    pub(crate) fn in_key(mut self, key: &str) -> Self {
+        self.path.push(Segment::Key(key.into()));
+        self
+    }
+
+    // prefixes the path of the error with an index
+    pub(crate) fn in_index(mut self, index: usize) -> Self {
+        self.path.push(Segment::Index(index));
+        self
+    }
+
+    /// Returns the reason of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns the byte offset in the stream where this error was raised.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// Returns the path to the offending value, from the root compound.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.path.iter().rev()
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownTag(kind) => write!(f, "unknown tag kind {}", kind),
+            ErrorKind::UnexpectedTag { expected, found } => {
+                write!(f, "invalid tag kind {}, expected {}", found, expected)
+            },
+            ErrorKind::Truncated => f.write_str("unexpected end of input"),
+            ErrorKind::InvalidUtf => f.write_str("invalid UTF string"),
+            ErrorKind::NegativeLength(size) => write!(f, "negative length {}", size),
+            ErrorKind::DepthExceeded(depth) => write!(f, "tags nested deeper than {}", depth),
+            ErrorKind::LengthExceeded { len, max } => {
+                write!(f, "length {} is over the limit of {}", len, max)
+            },
+            ErrorKind::SizeExceeded(max) => write!(f, "tags take more than {} bytes", max),
+            ErrorKind::HeterogeneousList { expected, found } => {
+                write!(f, "list of tag kind {} contains tag kind {}", expected, found)
+            },
+            ErrorKind::UnsupportedTag { kind, version } => {
+                write!(f, "tag kind {} is not supported by {:?} NBT", kind, version)
+            },
+            ErrorKind::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => f.write_str(key),
+            Segment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)?;
+
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+
+        if !self.path.is_empty() {
+            f.write_str(" in ")?;
+            for (i, segment) in self.path().enumerate() {
+                if i > 0 && matches!(segment, Segment::Key(_)) {
+                    f.write_str("/")?;
+                }
+                segment.fmt(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e.kind {
+            ErrorKind::Io(e) => e.kind(),
+            ErrorKind::Truncated => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+fn is_invalid_utf(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<InvalidUtf>())
+}
+
+// Something that tracks how many bytes went through it.
+pub(crate) trait Position {
+    fn position(&self) -> u64;
+}
+
+impl<R> Position for DataInput<R> {
+    fn position(&self) -> u64 {
+        DataInput::position(self)
+    }
+}
+
+impl<W> Position for DataOutput<W> {
+    fn position(&self) -> u64 {
+        DataOutput::position(self)
+    }
+}
+
+// Attaches the stream offset to I/O errors.
+pub(crate) trait At<T> {
+    fn at<P: Position>(self, stream: &P) -> Result<T, Error>;
+}
+
+impl<T> At<T> for io::Result<T> {
+    fn at<P: Position>(self, stream: &P) -> Result<T, Error> {
+        self.map_err(|e| Error::from_io(e, Some(stream.position())))
+    }
+}
@@ -0,0 +1,198 @@
+//! Compare, hash and fingerprint tags.
+//!
+//! Two tags are equal when they are of the same kind and hold the same
+//! values. Floats compare by their bits, as they are encoded, so `NaN`
+//! equals itself and `0.0` differs from `-0.0`. Lists compare element by
+//! element, along with their kind, and compounds entry by entry, in any
+//! order.
+//!
+//! [`Hash`] agrees with this equality, hashing the entries of compounds in
+//! the order of their keys, and so does [`Tag::fingerprint`], which stays the
+//! same across builds and platforms.
+
+use std::hash::{Hash, Hasher};
+
+use crate::serialize::nbt::{NBT, Tag};
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Tag) -> bool {
+        match (self, other) {
+            (Tag::End, Tag::End) => true,
+            (Tag::Byte(x), Tag::Byte(y)) => x == y,
+            (Tag::Short(x), Tag::Short(y)) => x == y,
+            (Tag::Int(x), Tag::Int(y)) => x == y,
+            (Tag::Long(x), Tag::Long(y)) => x == y,
+            (Tag::Float(x), Tag::Float(y)) => x.to_bits() == y.to_bits(),
+            (Tag::Double(x), Tag::Double(y)) => x.to_bits() == y.to_bits(),
+            (Tag::ByteArray(x), Tag::ByteArray(y)) => x == y,
+            (Tag::String(x), Tag::String(y)) => x == y,
+            (Tag::List(a, xs), Tag::List(b, ys)) => a == b && xs == ys,
+            // the equality of `IndexMap` ignores the order of entries
+            (Tag::Compound(xs), Tag::Compound(ys)) => xs == ys,
+            (Tag::IntArray(x), Tag::IntArray(y)) => x == y,
+            (Tag::LongArray(x), Tag::LongArray(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Tag {}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.feed(h)
+    }
+}
+
+impl PartialEq for NBT {
+    fn eq(&self, other: &NBT) -> bool {
+        self.key == other.key && self.tag == other.tag
+    }
+}
+
+impl Eq for NBT {}
+
+impl Hash for NBT {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.feed(h)
+    }
+}
+
+impl Tag {
+    /// Returns a 64-bit [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function)
+    /// hash of this tag, which equal tags share, and which does not change
+    /// from one build or platform to another, unlike [`Hash`].
+    pub fn fingerprint(&self) -> u64 {
+        let mut h = Fnv::new();
+        self.feed(&mut h);
+        h.finish()
+    }
+
+    // writes the contents of this tag to `h`, the same way on every
+    // platform, with compounds in the order of their keys
+    fn feed<H: Hasher>(&self, h: &mut H) {
+        h.write(&[self.kind() as u8]);
+        match self {
+            Tag::End => (),
+            Tag::Byte(x) => h.write(&x.to_be_bytes()),
+            Tag::Short(x) => h.write(&x.to_be_bytes()),
+            Tag::Int(x) => h.write(&x.to_be_bytes()),
+            Tag::Long(x) => h.write(&x.to_be_bytes()),
+            Tag::Float(x) => h.write(&x.to_be_bytes()),
+            Tag::Double(x) => h.write(&x.to_be_bytes()),
+            Tag::ByteArray(xs) => {
+                feed_len(h, xs.len());
+                xs.iter().for_each(|x| h.write(&x.to_be_bytes()));
+            },
+            Tag::String(s) => feed_str(h, s),
+            Tag::List(kind, xs) => {
+                h.write(&[*kind as u8]);
+                feed_len(h, xs.len());
+                xs.iter().for_each(|x| x.feed(h));
+            },
+            Tag::Compound(m) => {
+                let mut entries: Vec<_> = m.iter().collect();
+                entries.sort_unstable_by_key(|&(key, _)| key);
+
+                feed_len(h, entries.len());
+                for (key, x) in entries {
+                    feed_str(h, key);
+                    x.feed(h);
+                }
+            },
+            Tag::IntArray(xs) => {
+                feed_len(h, xs.len());
+                xs.iter().for_each(|x| h.write(&x.to_be_bytes()));
+            },
+            Tag::LongArray(xs) => {
+                feed_len(h, xs.len());
+                xs.iter().for_each(|x| h.write(&x.to_be_bytes()));
+            },
+        }
+    }
+}
+
+impl NBT {
+    /// Returns a fingerprint of the key and the compound of this value, as
+    /// [`Tag::fingerprint`] does.
+    pub fn fingerprint(&self) -> u64 {
+        let mut h = Fnv::new();
+        self.feed(&mut h);
+        h.finish()
+    }
+
+    fn feed<H: Hasher>(&self, h: &mut H) {
+        feed_str(h, &self.key);
+        self.tag.feed(h);
+    }
+}
+
+fn feed_len<H: Hasher>(h: &mut H, len: usize) {
+    h.write(&(len as u64).to_be_bytes());
+}
+
+fn feed_str<H: Hasher>(h: &mut H, s: &str) {
+    feed_len(h, s.len());
+    h.write(s.as_bytes());
+}
+
+// The 64-bit FNV-1a hash, which is fixed, unlike the hasher of `std`.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::nbt;
+    use crate::serialize::nbt::{NBT, Tag};
+
+    #[test]
+    fn test_hash_equality() {
+        assert_eq!(Tag::Float(f32::NAN), Tag::Float(f32::NAN));
+        assert_ne!(Tag::Double(0.0), Tag::Double(-0.0));
+        assert_ne!(Tag::Int(1), Tag::Long(1));
+        assert_ne!(Tag::List(0x03, vec![]), Tag::List(0x08, vec![]));
+        assert_ne!(nbt!([1, 2]), nbt!([2, 1]));
+
+        // compounds compare and hash in any order
+        let a = nbt!({ id: 1s, Count: 64b, tag: { Damage: 3 } });
+        let b = nbt!({ tag: { Damage: 3 }, Count: 64b, id: 1s });
+        assert_eq!(a, b);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a, nbt!({ id: 1s, Count: 63b, tag: { Damage: 3 } }));
+
+        let stacks: HashSet<Tag> = vec![a.clone(), b, nbt!({ id: 2s })].into_iter().collect();
+        assert_eq!(stacks.len(), 2);
+        assert!(stacks.contains(&a));
+
+        let x = NBT::new("x", a.clone()).unwrap();
+        assert_ne!(x, NBT::new("y", a).unwrap());
+    }
+
+    #[test]
+    fn test_hash_fingerprint() {
+        // fixed, so that fingerprints can be stored
+        assert_eq!(Tag::End.fingerprint(), 0xaf63_bd4c_8601_b7df);
+        assert_eq!(nbt!({ a: 1, b: "c" }).fingerprint(), nbt!({ b: "c", a: 1 }).fingerprint());
+        assert_ne!(nbt!({ a: 1 }).fingerprint(), nbt!({ a: 1L }).fingerprint());
+        assert_ne!(nbt!(["ab", "c"]).fingerprint(), nbt!(["a", "bc"]).fingerprint());
+    }
+}
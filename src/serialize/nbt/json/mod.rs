@@ -0,0 +1,411 @@
+//! Convert NBT values to and from JSON.
+//!
+//! Two mappings are offered, chosen with a [`Mode`]:
+//!
+//! - [`Mode::Typed`] wraps every tag in an object naming its kind, as in
+//!   `{"type": "short", "value": 3}`, so that a tag converted to JSON
+//!   converts back into the very same tag. Lists name the kind of their
+//!   elements too, as in `{"type": "list", "element": "int", "value": []}`.
+//!   Floats that JSON cannot represent are written as the strings `"NaN"`,
+//!   `"Infinity"` and `"-Infinity"`.
+//! - [`Mode::Plain`] maps tags to the closest JSON value, for human
+//!   consumption. Numbers of every width become JSON numbers, and arrays
+//!   become arrays of numbers. Converting back picks `Int`, `Long`
+//!   or `Double` for numbers, and `Byte` for booleans.
+//!
+//! The entries of compounds keep their order in both mappings. An [`NBT`]
+//! maps to an object with a single entry, keyed by its name.
+
+use std::fmt;
+use std::error;
+use std::convert::TryFrom;
+
+use serde_json::{Map, Number, Value};
+
+use crate::serialize::nbt::{Compound, NBT, Segment, Tag};
+
+/// How tags are mapped to JSON.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Lossless, with the kind of every tag spelled out.
+    Typed,
+    /// Lossy, with tags as the closest JSON values.
+    Plain,
+}
+
+/// An error raised while converting between JSON and NBT, along with the
+/// path to the offending value.
+#[derive(Clone, Debug)]
+pub struct Error {
+    message: &'static str,
+    // innermost segment first
+    path: Vec<Segment>,
+}
+
+impl Error {
+    fn new(message: &'static str) -> Self {
+        Self { message, path: Vec::new() }
+    }
+
+    fn in_key(mut self, key: &str) -> Self {
+        self.path.push(Segment::Key(key.into()));
+        self
+    }
+
+    fn in_index(mut self, index: usize) -> Self {
+        self.path.push(Segment::Index(index));
+        self
+    }
+
+    /// A description of the error.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+
+    /// Returns the path to the offending value, from the root.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.path.iter().rev()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message)?;
+
+        if !self.path.is_empty() {
+            f.write_str(" in ")?;
+            for (i, segment) in self.path().enumerate() {
+                if i > 0 && matches!(segment, Segment::Key(_)) {
+                    f.write_str("/")?;
+                }
+                segment.fmt(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
+
+/// Converts a [`Tag`] into JSON, failing on lists of an unknown kind.
+pub fn to_json(tag: &Tag, mode: Mode) -> Result<Value, Error> {
+    match mode {
+        Mode::Typed => typed(tag),
+        Mode::Plain => Ok(plain(tag)),
+    }
+}
+
+/// Converts JSON into a [`Tag`].
+pub fn from_json(value: &Value, mode: Mode) -> Result<Tag, Error> {
+    match mode {
+        Mode::Typed => from_typed(value),
+        Mode::Plain => from_plain(value),
+    }
+}
+
+/// Converts an [`NBT`] into a JSON object with a single entry.
+pub fn nbt_to_json(nbt: &NBT, mode: Mode) -> Result<Value, Error> {
+    let value = to_json(nbt.tag(), mode).map_err(|e| e.in_key(nbt.key()))?;
+    let mut m = Map::new();
+    m.insert(nbt.key().into(), value);
+    Ok(Value::Object(m))
+}
+
+/// Converts a JSON object with a single entry holding a compound into an
+/// [`NBT`].
+pub fn nbt_from_json(value: &Value, mode: Mode) -> Result<NBT, Error> {
+    let (key, value) = match value {
+        Value::Object(m) if m.len() == 1 => m.iter().next().unwrap(),
+        _ => return Err(Error::new("expected an object with a single entry")),
+    };
+    let tag = from_json(value, mode).map_err(|e| e.in_key(key))?;
+    NBT::new(key, tag).ok_or_else(|| Error::new("root tag must be a compound").in_key(key))
+}
+
+// the names of the kinds of tags, by their id
+const KINDS: [&str; 13] = [
+    "end", "byte", "short", "int", "long", "float", "double",
+    "byte_array", "string", "list", "compound", "int_array", "long_array",
+];
+
+fn float(x: f64) -> Value {
+    match Number::from_f64(x) {
+        Some(x) => Value::Number(x),
+        None if x.is_nan() => Value::String("NaN".into()),
+        None if x > 0.0 => Value::String("Infinity".into()),
+        None => Value::String("-Infinity".into()),
+    }
+}
+
+fn kind_name(kind: i8) -> Result<&'static str, Error> {
+    usize::try_from(kind)
+        .ok()
+        .and_then(|kind| KINDS.get(kind))
+        .copied()
+        .ok_or_else(|| Error::new("unknown tag kind"))
+}
+
+fn typed(tag: &Tag) -> Result<Value, Error> {
+    let value = match tag {
+        Tag::End => Value::Null,
+        Tag::Byte(x) => Value::from(*x),
+        Tag::Short(x) => Value::from(*x),
+        Tag::Int(x) => Value::from(*x),
+        Tag::Long(x) => Value::from(*x),
+        Tag::Float(x) => float(*x as f64),
+        Tag::Double(x) => float(*x),
+        Tag::ByteArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+        Tag::String(s) => Value::String(s.clone()),
+        Tag::List(_, xs) => xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| typed(x).map_err(|e| e.in_index(i)))
+            .collect::<Result<_, _>>()?,
+        Tag::Compound(m) => Value::Object(m
+            .iter()
+            .map(|(k, x)| Ok((k.clone(), typed(x).map_err(|e| e.in_key(k))?)))
+            .collect::<Result<_, _>>()?),
+        Tag::IntArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+        Tag::LongArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+    };
+
+    let mut m = Map::new();
+    m.insert("type".into(), Value::from(kind_name(tag.kind())?));
+    if let Tag::List(kind, _) = tag {
+        m.insert("element".into(), Value::from(kind_name(*kind)?));
+    }
+    m.insert("value".into(), value);
+    Ok(Value::Object(m))
+}
+
+fn plain(tag: &Tag) -> Value {
+    match tag {
+        Tag::End => Value::Null,
+        Tag::Byte(x) => Value::from(*x),
+        Tag::Short(x) => Value::from(*x),
+        Tag::Int(x) => Value::from(*x),
+        Tag::Long(x) => Value::from(*x),
+        Tag::Float(x) => Number::from_f64(*x as f64).map_or(Value::Null, Value::Number),
+        Tag::Double(x) => Number::from_f64(*x).map_or(Value::Null, Value::Number),
+        Tag::ByteArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+        Tag::String(s) => Value::String(s.clone()),
+        Tag::List(_, xs) => xs.iter().map(plain).collect(),
+        Tag::Compound(m) => Value::Object(m
+            .iter()
+            .map(|(k, x)| (k.clone(), plain(x)))
+            .collect()),
+        Tag::IntArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+        Tag::LongArray(xs) => xs.iter().map(|&x| Value::from(x)).collect(),
+    }
+}
+
+fn from_typed(value: &Value) -> Result<Tag, Error> {
+    let m = match value {
+        Value::Object(m) => m,
+        _ => return Err(Error::new("expected an object with a type and a value")),
+    };
+    let kind = m
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::new("missing type"))?;
+    let element = m
+        .get("element")
+        .map(|x| x
+            .as_str()
+            .and_then(|x| KINDS.iter().position(|&kind| kind == x))
+            .ok_or_else(|| Error::new("unknown element type")))
+        .transpose()?;
+    let value = m
+        .get("value")
+        .ok_or_else(|| Error::new("missing value"))?;
+
+    fn int<T: TryFrom<i64>>(value: &Value) -> Result<T, Error> {
+        value
+            .as_i64()
+            .and_then(|x| T::try_from(x).ok())
+            .ok_or_else(|| Error::new("integer out of range"))
+    }
+
+    fn float(value: &Value) -> Result<f64, Error> {
+        match value {
+            Value::Number(x) => x.as_f64().ok_or_else(|| Error::new("invalid float")),
+            Value::String(s) if s == "NaN" => Ok(f64::NAN),
+            Value::String(s) if s == "Infinity" => Ok(f64::INFINITY),
+            Value::String(s) if s == "-Infinity" => Ok(f64::NEG_INFINITY),
+            _ => Err(Error::new("invalid float")),
+        }
+    }
+
+    fn array(value: &Value) -> Result<&Vec<Value>, Error> {
+        value.as_array().ok_or_else(|| Error::new("expected an array"))
+    }
+
+    fn ints<T: TryFrom<i64>>(value: &Value) -> Result<Vec<T>, Error> {
+        array(value)?
+            .iter()
+            .enumerate()
+            .map(|(i, x)| int(x).map_err(|e| e.in_index(i)))
+            .collect()
+    }
+
+    match kind {
+        "end" => Ok(Tag::End),
+        "byte" => int(value).map(Tag::Byte),
+        "short" => int(value).map(Tag::Short),
+        "int" => int(value).map(Tag::Int),
+        "long" => int(value).map(Tag::Long),
+        "float" => float(value).map(|x| Tag::Float(x as f32)),
+        "double" => float(value).map(Tag::Double),
+        "byte_array" => ints(value).map(Tag::ByteArray),
+        "int_array" => ints(value).map(Tag::IntArray),
+        "long_array" => ints(value).map(Tag::LongArray),
+        "string" => value
+            .as_str()
+            .map(|s| Tag::String(s.into()))
+            .ok_or_else(|| Error::new("expected a string")),
+        "list" => {
+            let xs = array(value)?
+                .iter()
+                .enumerate()
+                .map(|(i, x)| from_typed(x).map_err(|e| e.in_index(i)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let kind = match element {
+                Some(kind) => kind as i8,
+                None => xs.first().map_or(0, Tag::kind),
+            };
+            check_list(kind, &xs)?;
+            Ok(Tag::List(kind, xs))
+        },
+        "compound" => match value {
+            Value::Object(m) => compound(m, from_typed),
+            _ => Err(Error::new("expected an object")),
+        },
+        _ => Err(Error::new("unknown type")),
+    }
+}
+
+fn from_plain(value: &Value) -> Result<Tag, Error> {
+    match value {
+        Value::Null => Err(Error::new("null has no tag")),
+        Value::Bool(x) => Ok(Tag::Byte(*x as i8)),
+        Value::Number(x) => match (x.as_i64(), x.as_f64()) {
+            (Some(x), _) if x as i32 as i64 == x => Ok(Tag::Int(x as i32)),
+            (Some(x), _) => Ok(Tag::Long(x)),
+            (None, _) if x.is_u64() => Err(Error::new("integer out of range")),
+            (None, Some(x)) => Ok(Tag::Double(x)),
+            (None, None) => Err(Error::new("invalid number")),
+        },
+        Value::String(s) => Ok(Tag::String(s.clone())),
+        Value::Array(xs) => {
+            let mut xs = xs
+                .iter()
+                .enumerate()
+                .map(|(i, x)| from_plain(x).map_err(|e| e.in_index(i)))
+                .collect::<Result<Vec<_>, _>>()?;
+            widen(&mut xs);
+            let kind = xs.first().map_or(0, Tag::kind);
+            check_list(kind, &xs)?;
+            Ok(Tag::List(kind, xs))
+        },
+        Value::Object(m) => compound(m, from_plain),
+    }
+}
+
+fn compound(m: &Map<String, Value>, f: fn(&Value) -> Result<Tag, Error>) -> Result<Tag, Error> {
+    let mut c = Compound::new();
+    for (key, value) in m {
+        let tag = f(value).map_err(|e| e.in_key(key))?;
+        c.insert(key.clone(), tag);
+    }
+    Ok(Tag::Compound(c))
+}
+
+// converts the numbers of a list to the widest kind among them
+fn widen(xs: &mut [Tag]) {
+    let widest = xs.iter().map(Tag::kind).max().unwrap_or(0);
+    if !xs.iter().all(|x| matches!(x, Tag::Int(_) | Tag::Long(_) | Tag::Double(_))) {
+        return
+    }
+    for x in xs.iter_mut() {
+        match *x {
+            Tag::Int(y) if widest == 0x04 => *x = Tag::Long(y as i64),
+            Tag::Int(y) if widest == 0x06 => *x = Tag::Double(y as f64),
+            Tag::Long(y) if widest == 0x06 => *x = Tag::Double(y as f64),
+            _ => (),
+        }
+    }
+}
+
+fn check_list(kind: i8, xs: &[Tag]) -> Result<(), Error> {
+    match xs.iter().position(|x| x.kind() != kind) {
+        Some(i) => Err(Error::new("list elements must be of the same kind").in_index(i)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::serialize::nbt::{Compound, NBT, Tag};
+    use crate::serialize::nbt::json::{self, Mode};
+
+    #[test]
+    fn test_json_typed() {
+        let (nbt, _) = NBT::open("res/bigtest.dat").unwrap();
+
+        let value = json::nbt_to_json(&nbt, Mode::Typed).unwrap();
+        let text = serde_json::to_string(&value).unwrap();
+        let decoded = json::nbt_from_json(&serde_json::from_str(&text).unwrap(), Mode::Typed).unwrap();
+
+        // the same kinds, values and order
+        assert_eq!(decoded.to_string(), nbt.to_string());
+
+        let tag = Tag::list(vec![Tag::Float(f32::NAN), Tag::Float(-0.0), Tag::Float(f32::INFINITY)]);
+        let value = json::to_json(&tag, Mode::Typed).unwrap();
+        assert_eq!(value["value"][0]["value"], "NaN");
+        assert_eq!(json::from_json(&value, Mode::Typed).unwrap().to_string(), tag.to_string());
+
+        let e = json::from_json(&json!({
+            "type": "compound",
+            "value": {"Inventory": {"type": "list", "value": [
+                {"type": "byte", "value": 1},
+                {"type": "byte", "value": 300},
+            ]}},
+        }), Mode::Typed).unwrap_err();
+        assert_eq!(e.to_string(), "integer out of range in Inventory[1]");
+
+        // lists of kinds that do not exist fail rather than panic
+        for &kind in &[-1, 13] {
+            let mut m = Compound::new();
+            m.insert("xs".into(), Tag::list(vec![Tag::List(kind, vec![])]));
+            let e = json::to_json(&Tag::Compound(m), Mode::Typed).unwrap_err();
+            assert_eq!(e.to_string(), "unknown tag kind in xs[0]");
+        }
+    }
+
+    #[test]
+    fn test_json_plain() {
+        let (nbt, _) = NBT::open("res/player.dat").unwrap();
+
+        let value = json::nbt_to_json(&nbt, Mode::Plain).unwrap();
+        assert_eq!(value[""]["Health"], 10);
+        assert_eq!(value[""]["Pos"].as_array().map(Vec::len), Some(3));
+
+        let value = json!({"name": "Steve", "xs": [1, 5000000000i64], "ys": [1, 2.5], "ok": true});
+        match json::from_json(&value, Mode::Plain).unwrap() {
+            Tag::Compound(m) => {
+                let keys: Vec<_> = m.keys().map(String::as_str).collect();
+                assert_eq!(keys, ["name", "xs", "ys", "ok"]);
+                assert!(matches!(&m["xs"], Tag::List(_, xs) if matches!(xs[0], Tag::Long(1))));
+                assert!(matches!(&m["ys"], Tag::List(_, ys) if matches!(ys[0], Tag::Double(_))));
+                assert!(matches!(m["ok"], Tag::Byte(1)));
+            },
+            _ => panic!("not a compound tag"),
+        }
+
+        assert!(json::from_json(&json!([1, "two"]), Mode::Plain).is_err());
+        assert!(json::from_json(&json!({"x": null}), Mode::Plain).is_err());
+    }
+}
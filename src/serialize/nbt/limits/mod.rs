@@ -0,0 +1,95 @@
+use std::mem;
+
+use crate::serialize::nbt::{Error, ErrorKind, Tag, MAX_DEPTH};
+use crate::serialize::nbt::error::Position;
+
+/// Bounds on the resources spent decoding NBT, for reading untrusted input.
+///
+/// The length prefixes of lists, arrays and strings are checked against these
+/// limits before anything is allocated for them, so that a small malicious
+/// payload cannot exhaust the memory or the stack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// How deep lists and compounds may be nested.
+    pub max_depth: usize,
+    /// How many bytes may be allocated for the decoded tags, in total.
+    pub max_bytes: usize,
+    /// How many elements a single list or array may have.
+    pub max_len: usize,
+}
+
+impl Limits {
+    /// Limits that only bound the nesting depth, for trusted input.
+    pub fn unlimited() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            max_bytes: usize::MAX,
+            max_len: usize::MAX,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            max_bytes: 64 << 20,
+            max_len: 16 << 20,
+        }
+    }
+}
+
+// Keeps track of what has been spent decoding against the limits.
+pub(crate) struct Budget<'a> {
+    limits: &'a Limits,
+    depth: usize,
+    allocated: usize,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(limits: &'a Limits, depth: usize) -> Self {
+        Self { limits, depth, allocated: 0 }
+    }
+
+    // steps into a list or compound
+    pub(crate) fn enter<P: Position>(&mut self, at: &P) -> Result<(), Error> {
+        if self.depth >= self.limits.max_depth {
+            let kind = ErrorKind::DepthExceeded(self.limits.max_depth);
+            return Err(Error::new(kind, Some(at.position())))
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // steps out of a list or compound
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // checks the length of a list or array
+    pub(crate) fn check_len<P: Position>(&self, len: usize, at: &P) -> Result<(), Error> {
+        if len > self.limits.max_len {
+            let kind = ErrorKind::LengthExceeded { len, max: self.limits.max_len };
+            return Err(Error::new(kind, Some(at.position())))
+        }
+        Ok(())
+    }
+
+    // accounts for `bytes` about to be allocated
+    pub(crate) fn charge<P: Position>(&mut self, bytes: usize, at: &P) -> Result<(), Error> {
+        self.allocated = self.allocated
+            .checked_add(bytes)
+            .filter(|&allocated| allocated <= self.limits.max_bytes)
+            .ok_or_else(|| {
+                let kind = ErrorKind::SizeExceeded(self.limits.max_bytes);
+                Error::new(kind, Some(at.position()))
+            })?;
+        Ok(())
+    }
+
+    // accounts for `count` tags about to be allocated
+    pub(crate) fn charge_tags<P: Position>(&mut self, count: usize, at: &P) -> Result<(), Error> {
+        let bytes = count.saturating_mul(mem::size_of::<Tag>());
+        self.charge(bytes, at)
+    }
+}
@@ -0,0 +1,225 @@
+use crate::serialize::nbt::Tag;
+
+/// Builds a [`Tag`](crate::serialize::nbt::Tag) from SNBT-like syntax.
+///
+/// Literals take the suffixes of [SNBT](crate::serialize::nbt::snbt), so
+/// `1b`, `2s`, `3`, `4L`, `1.5f` and `2.5d` are a byte, a short, an int, a
+/// long, a float and a double. Lists are written `[...]`, arrays `[B; ...]`,
+/// `[I; ...]` and `[L; ...]`, and compounds `{...}` with the keys as
+/// identifiers or string literals. The outermost braces of a compound may be
+/// left out. Any other Rust expression goes in parentheses, and is
+/// converted with [`From`]:
+///
+/// ```
+/// # use neonmc::nbt;
+/// let health = 20;
+/// let player = nbt! {
+///     "Pos": [1.0d, 64.0d, -1.0d],
+///     "Health": (health as i16),
+///     OnGround: true,
+///     Inventory: [{ id: 1s, Count: 64b }],
+/// };
+/// assert_eq!(player.get("Health").and_then(|x| x.as_i16()), Some(20));
+/// ```
+///
+/// Literals are checked as they are compiled, so `300b` is an error:
+///
+/// ```compile_fail
+/// # use neonmc::nbt;
+/// let tag = nbt!([B; 300b]);
+/// ```
+///
+/// The suffixes may be of either case, and those of Rust, such as `5i8` or
+/// `2.5f32`, name the same kinds. A byte of zero is written `0B` or `0i8`,
+/// as Rust reads `0b` as the start of a binary number. String literals are
+/// those of Rust, escapes included.
+///
+/// # Panics
+///
+/// Panics if an element of an array is not of its kind.
+#[macro_export]
+macro_rules! nbt {
+    ({ $($body:tt)* }) => {
+        $crate::serialize::nbt::__compound!($($body)*)
+    };
+    ([B; $($body:tt)*]) => {
+        $crate::serialize::nbt::__array(0x07, $crate::nbt!([$($body)*]))
+    };
+    ([I; $($body:tt)*]) => {
+        $crate::serialize::nbt::__array(0x0b, $crate::nbt!([$($body)*]))
+    };
+    ([L; $($body:tt)*]) => {
+        $crate::serialize::nbt::__array(0x0c, $crate::nbt!([$($body)*]))
+    };
+    ([ $($body:tt)* ]) => {
+        $crate::serialize::nbt::__list!($($body)*)
+    };
+    (($e:expr)) => {
+        $crate::serialize::nbt::Tag::from($e)
+    };
+    (- $x:literal) => {
+        $crate::serialize::nbt::__literal!(- $x)
+    };
+    ($x:literal) => {
+        $crate::serialize::nbt::__literal!($x)
+    };
+    ($($body:tt)*) => {
+        $crate::nbt!({ $($body)* })
+    };
+}
+
+// types a literal of the `nbt!` macro
+#[doc(hidden)]
+pub use neonmc_derive::__nbt_literal as __literal;
+
+// splits the lists and compounds of the `nbt!` macro at their commas, as
+// macro rules would recurse once per token
+#[doc(hidden)]
+pub use neonmc_derive::{__nbt_compound as __compound, __nbt_list as __list};
+
+// turns the list of the `nbt!` macro into an array of the given kind
+#[doc(hidden)]
+pub fn __array(kind: i8, list: Tag) -> Tag {
+    let xs = match list {
+        Tag::List(_, xs) => xs,
+        _ => unreachable!(),
+    };
+
+    match kind {
+        0x07 => Tag::ByteArray(xs.iter().map(|x| x.as_i8().unwrap_or_else(|| mismatch(x, kind))).collect()),
+        0x0b => Tag::IntArray(xs.iter().map(|x| x.as_i32().unwrap_or_else(|| mismatch(x, kind))).collect()),
+        _ => Tag::LongArray(xs.iter().map(|x| x.as_i64().unwrap_or_else(|| mismatch(x, kind))).collect()),
+    }
+}
+
+fn mismatch(x: &Tag, kind: i8) -> ! {
+    panic!("tag kind {} in an array of tag kind {}", x.kind(), kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serialize::nbt::{Compound, Tag};
+
+    #[test]
+    fn test_macro_build() {
+        let name = String::from("Steve");
+        let tag = nbt! {
+            "Pos": [1.0d, 64.0d, -1.5d],
+            Health: 20s,
+            Air: -1s,
+            Name: (name),
+            Empty: [],
+            Bytes: [B; 1b, -2b],
+            Ints: [I; 3, (4 * 2)],
+            Longs: [L; -5L],
+            Inventory: [
+                { id: 1s, Count: 64b, },
+                { id: 276s, Count: 1b },
+            ],
+            Nested: { "with space": "q\"uoted", flag: false },
+        };
+
+        assert_eq!(tag.get("Health").and_then(Tag::as_i16), Some(20));
+        assert_eq!(tag.get("Air").and_then(Tag::as_i16), Some(-1));
+        assert_eq!(tag.get("Name").and_then(Tag::as_str), Some("Steve"));
+        assert!(matches!(tag.get("Empty"), Some(Tag::List(0, xs)) if xs.is_empty()));
+        assert_eq!(tag.get("Bytes").and_then(Tag::as_byte_array), Some(&[1, -2][..]));
+        assert_eq!(tag.get("Ints").and_then(Tag::as_int_array), Some(&[3, 8][..]));
+        assert_eq!(tag.get("Longs").and_then(Tag::as_long_array), Some(&[-5][..]));
+
+        let pos: Vec<f64> = tag.get("Pos")
+            .and_then(Tag::as_list)
+            .map(|xs| xs.iter().filter_map(Tag::as_f64).collect())
+            .unwrap();
+        assert_eq!(pos, [1.0, 64.0, -1.5]);
+
+        let inventory = tag.get("Inventory").and_then(Tag::as_list).unwrap();
+        assert_eq!(inventory[1].get("id").and_then(Tag::as_i16), Some(276));
+
+        let nested = tag.get("Nested").unwrap();
+        assert_eq!(nested.get("with space").and_then(Tag::as_str), Some("q\"uoted"));
+        assert_eq!(nested.get("flag").and_then(Tag::as_bool), Some(false));
+
+        // the same tree, printed and parsed back
+        let parsed: Tag = tag.to_string().parse().unwrap();
+        assert_eq!(parsed.to_string(), tag.to_string());
+    }
+
+    #[test]
+    fn test_macro_access() {
+        let mut tag = nbt!({});
+        assert_eq!(tag.as_compound().map(Compound::len), Some(0));
+        assert!(tag.as_i32().is_none());
+        assert!(nbt!(5).get("x").is_none());
+
+        tag.as_compound_mut().unwrap().insert("x".into(), Tag::from(1));
+        *tag.get_mut("x").unwrap() = Tag::from(vec![Tag::from("a")]);
+        tag.get_mut("x").and_then(Tag::as_list_mut).unwrap().push("b".into());
+        assert_eq!(tag.to_string(), r#"{x:["a","b"]}"#);
+    }
+
+    #[test]
+    fn test_macro_literals() {
+        let tag = nbt! {
+            zero: 0B,
+            min: -128b,
+            rust: [0i8, 2i16, -3i32, 4i64, 0.5f32, 1.5f64],
+            upper: [1S, 2L, 3F, 4D],
+            plain: [1, 2.5, 1e3],
+            hex: 0x7f_i8,
+            escaped: "tab\there\n",
+            raw: r"C:\dir",
+            negative: [-0.0f, -1.5d],
+        };
+        assert_eq!(
+            tag.to_string(),
+            "{zero:0b,min:-128b,rust:[0b,2s,-3,4L,0.5f,1.5d],upper:[1s,2L,3.0f,4.0d],\
+             plain:[1,2.5d,1000.0d],hex:127b,escaped:\"tab\there\n\",raw:\"C:\\\\dir\",\
+             negative:[-0.0f,-1.5d]}",
+        );
+    }
+
+    #[test]
+    fn test_macro_large() {
+        // many more entries and elements than the recursion limit
+        let tag = nbt! {
+            k0: 0s, k1: 1s, k2: 2s, k3: 3s, k4: 4s, k5: 5s, k6: 6s, k7: 7s, k8: 8s, k9: 9s,
+            k10: 10s, k11: 11s, k12: 12s, k13: 13s, k14: 14s, k15: 15s, k16: 16s, k17: 17s, k18: 18s, k19: 19s,
+            k20: 20s, k21: 21s, k22: 22s, k23: 23s, k24: 24s, k25: 25s, k26: 26s, k27: 27s, k28: 28s, k29: 29s,
+            k30: 30s, k31: 31s, k32: 32s, k33: 33s, k34: 34s, k35: 35s, k36: 36s, k37: 37s, k38: 38s, k39: 39s,
+            k40: 40s, k41: 41s, k42: 42s, k43: 43s, k44: 44s, k45: 45s, k46: 46s, k47: 47s, k48: 48s, k49: 49s,
+            k50: 50s, k51: 51s, k52: 52s, k53: 53s, k54: 54s, k55: 55s, k56: 56s, k57: 57s, k58: 58s, k59: 59s,
+            k60: 60s, k61: 61s, k62: 62s, k63: 63s, k64: 64s, k65: 65s, k66: 66s, k67: 67s, k68: 68s, k69: 69s,
+            k70: 70s, k71: 71s, k72: 72s, k73: 73s, k74: 74s, k75: 75s, k76: 76s, k77: 77s, k78: 78s, k79: 79s,
+            k80: 80s, k81: 81s, k82: 82s, k83: 83s, k84: 84s, k85: 85s, k86: 86s, k87: 87s, k88: 88s, k89: 89s,
+            k90: 90s, k91: 91s, k92: 92s, k93: 93s, k94: 94s, k95: 95s, k96: 96s, k97: 97s, k98: 98s, k99: 99s,
+            k100: 100s, k101: 101s, k102: 102s, k103: 103s, k104: 104s, k105: 105s, k106: 106s, k107: 107s, k108: 108s, k109: 109s,
+            k110: 110s, k111: 111s, k112: 112s, k113: 113s, k114: 114s, k115: 115s, k116: 116s, k117: 117s, k118: 118s, k119: 119s,
+            list: [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+                20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39,
+                40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+                60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79,
+                80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99,
+                100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119,
+                120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 139,
+                140, 141, 142, 143, 144, 145, 146, 147, 148, 149, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159,
+                160, 161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 176, 177, 178, 179,
+                180, 181, 182, 183, 184, 185, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197, 198, 199,
+                200, 201, 202, 203, 204, 205, 206, 207, 208, 209, 210, 211, 212, 213, 214, 215, 216, 217, 218, 219,
+                220, 221, 222, 223, 224, 225, 226, 227, 228, 229, 230, 231, 232, 233, 234, 235, 236, 237, 238, 239,
+                240, 241, 242, 243, 244, 245, 246, 247, 248, 249, 250, 251, 252, 253, 254, 255, 256, 257, 258, 259,
+                260, 261, 262, 263, 264, 265, 266, 267, 268, 269, 270, 271, 272, 273, 274, 275, 276, 277, 278, 279,
+                280, 281, 282, 283, 284, 285, 286, 287, 288, 289, 290, 291, 292, 293, 294, 295, 296, 297, 298, 299,
+                300, 301, 302, 303, 304, 305, 306, 307, 308, 309, 310, 311, 312, 313, 314, 315, 316, 317, 318, 319,
+            ],
+        };
+        let m = tag.as_compound().unwrap();
+        assert_eq!(m.len(), 121);
+        assert_eq!(tag.get("k119").and_then(Tag::as_i16), Some(119));
+
+        let list = tag.get("list").and_then(Tag::as_list).unwrap();
+        assert_eq!(list.len(), 320);
+        assert!(list.iter().enumerate().all(|(i, x)| x.as_i32() == Some(i as i32)));
+    }
+}
@@ -1,10 +1,52 @@
 //! Encode or decode [NBT](https://minecraft.fandom.com/wiki/NBT_format) values.
 
-use std::collections::HashMap;
-use std::io::{self, Read, Write};
+use std::io::{Read, Write};
+
+use indexmap::IndexMap;
 
 use crate::serialize::{DataInput, DataOutput};
 
+mod error;
+mod limits;
+mod options;
+mod encoding;
+mod macros;
+#[cfg(feature = "tokio")]
+mod asyncio;
+#[cfg(test)]
+pub(crate) mod strategy;
+
+pub mod snbt;
+pub mod serde;
+pub mod borrowed;
+pub mod stream;
+pub mod compression;
+pub mod json;
+pub mod path;
+pub mod diff;
+pub mod schema;
+pub mod convert;
+pub mod hash;
+
+pub use error::{Error, ErrorKind, Segment};
+pub use limits::Limits;
+pub use options::{Version, WriteOptions};
+pub use encoding::Encoding;
+#[doc(hidden)]
+pub use macros::{__array, __compound, __list, __literal};
+pub use borrowed::{NBTRef, TagRef};
+pub use compression::Compression;
+pub use convert::{FromTag, NbtCompound, ToTag};
+
+use error::At;
+use limits::Budget;
+
+/// How deep lists and compounds may be nested by default.
+pub const MAX_DEPTH: usize = 256;
+
+/// The entries of a compound tag, in the order they were inserted or read.
+pub type Compound = IndexMap<String, Tag>;
+
 /// Represents an NBT tag.
 #[derive(Clone, Debug)]
 pub enum Tag {
@@ -17,8 +59,12 @@ pub enum Tag {
     Double(f64),
     ByteArray(Vec<i8>),
     String(String),
-    List(Vec<Tag>),
-    Compound(HashMap<String, Tag>),
+    /// A list of tags of the given kind, which is kept even if the list
+    /// is empty.
+    List(i8, Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
 }
 
 /// Contains an NBT compound tag and a key.
@@ -47,10 +93,192 @@ impl Tag {
             Tag::Double(_) => 0x06,
             Tag::ByteArray(_) => 0x07,
             Tag::String(_) => 0x08,
-            Tag::List(_) => 0x09,
+            Tag::List(..) => 0x09,
             Tag::Compound(_) => 0x0a,
+            Tag::IntArray(_) => 0x0b,
+            Tag::LongArray(_) => 0x0c,
+        }
+    }
+
+    /// Returns a new list tag, of the kind of its first element, or of
+    /// [`Tag::End`] if there is none.
+    pub fn list(xs: Vec<Tag>) -> Tag {
+        let kind = xs.first().map_or(0, Tag::kind);
+        Tag::List(kind, xs)
+    }
+
+    /// Returns the value of this tag if it is a byte.
+    pub fn as_i8(&self) -> Option<i8> {
+        match self {
+            Tag::Byte(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is a short.
+    pub fn as_i16(&self) -> Option<i16> {
+        match self {
+            Tag::Short(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is an int.
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            Tag::Int(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is a long.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Long(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is a float.
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Tag::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is a double.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Tag::Double(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this tag if it is a byte, as Minecraft stores
+    /// booleans.
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_i8().map(|x| x != 0)
+    }
+
+    /// Returns the value of this tag if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of this tag if it is a byte array.
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(xs) => Some(xs),
+            _ => None,
+        }
+    }
+
+    /// Returns the ints of this tag if it is an int array.
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            Tag::IntArray(xs) => Some(xs),
+            _ => None,
+        }
+    }
+
+    /// Returns the longs of this tag if it is a long array.
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(xs) => Some(xs),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of this tag if it is a list.
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(_, xs) => Some(xs),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of this tag if it is a list, to be modified.
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Tag>> {
+        match self {
+            Tag::List(_, xs) => Some(xs),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of this tag if it is a compound.
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(m) => Some(m),
+            _ => None,
         }
     }
+
+    /// Returns the entries of this tag if it is a compound, to be modified.
+    pub fn as_compound_mut(&mut self) -> Option<&mut Compound> {
+        match self {
+            Tag::Compound(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns the entry with the given key if this tag is a compound.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        self.as_compound()?.get(key)
+    }
+
+    /// Returns the entry with the given key if this tag is a compound, to
+    /// be modified.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Tag> {
+        self.as_compound_mut()?.get_mut(key)
+    }
+}
+
+macro_rules! tag_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Tag {
+                fn from(x: $ty) -> Tag {
+                    Tag::$variant(x)
+                }
+            }
+        )*
+    }
+}
+
+tag_from! {
+    i8 => Byte,
+    i16 => Short,
+    i32 => Int,
+    i64 => Long,
+    f32 => Float,
+    f64 => Double,
+    String => String,
+    Vec<i8> => ByteArray,
+    Vec<i32> => IntArray,
+    Vec<i64> => LongArray,
+    Compound => Compound,
+}
+
+impl From<bool> for Tag {
+    fn from(x: bool) -> Tag {
+        Tag::Byte(x as i8)
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(s: &str) -> Tag {
+        Tag::String(s.into())
+    }
+}
+
+impl From<Vec<Tag>> for Tag {
+    fn from(xs: Vec<Tag>) -> Tag {
+        Tag::list(xs)
+    }
 }
 
 impl NBT {
@@ -74,143 +302,328 @@ impl NBT {
     pub fn tag(&self) -> &Tag {
         &self.tag
     }
+
+    /// Returns this NBT tag's compound value, dropping its key.
+    pub fn into_tag(self) -> Tag {
+        self.tag
+    }
 }
 
 // reading NBT
 impl Tag {
-    fn read_from<R: Read>(input: &mut DataInput<R>, tag_kind: i8) -> io::Result<Tag> {
+    fn read_from<R: Read>(input: &mut DataInput<R>, tag_kind: i8, encoding: Encoding, budget: &mut Budget) -> Result<Tag, Error> {
         match tag_kind {
             0x00 => Ok(Tag::End),
-            0x01 => Ok(Tag::Byte(input.read_byte()?)),
-            0x02 => Ok(Tag::Short(input.read_short()?)),
-            0x03 => Ok(Tag::Int(input.read_int()?)),
-            0x04 => Ok(Tag::Long(input.read_long()?)),
-            0x05 => Ok(Tag::Float(input.read_float()?)),
-            0x06 => Ok(Tag::Double(input.read_double()?)),
+            0x01 => Ok(Tag::Byte(input.read_byte().at(input)?)),
+            0x02 => Ok(Tag::Short(encoding.read_short(input).at(input)?)),
+            0x03 => Ok(Tag::Int(encoding.read_int(input).at(input)?)),
+            0x04 => Ok(Tag::Long(encoding.read_long(input).at(input)?)),
+            0x05 => Ok(Tag::Float(encoding.read_float(input).at(input)?)),
+            0x06 => Ok(Tag::Double(encoding.read_double(input).at(input)?)),
+            0x07 | 0x0b | 0x0c => Tag::read_array(input, tag_kind, encoding, budget),
+            0x08 => {
+                let s = encoding.read_utf(input).at(input)?;
+                budget.charge(s.len(), input)?;
+                Ok(Tag::String(s))
+            },
+            0x09 => Tag::read_list(input, encoding, budget),
+            0x0a => Tag::read_compound(input, encoding, budget).map(Tag::Compound),
+            _ => Err(Error::new(ErrorKind::UnknownTag(tag_kind), None)),
+        }
+    }
+
+    fn read_array<R: Read>(input: &mut DataInput<R>, tag_kind: i8, encoding: Encoding, budget: &mut Budget) -> Result<Tag, Error> {
+        let size = read_len(input, encoding)?;
+        budget.check_len(size, input)?;
+
+        match tag_kind {
+            0x07 => {
+                budget.charge(size, input)?;
+                Ok(Tag::ByteArray(input.read_bytes(size).at(input)?))
+            },
+            0x0b => {
+                budget.charge(size.saturating_mul(4), input)?;
+                Ok(Tag::IntArray(encoding.read_ints(input, size).at(input)?))
+            },
+            _ => {
+                budget.charge(size.saturating_mul(8), input)?;
+                Ok(Tag::LongArray(encoding.read_longs(input, size).at(input)?))
+            },
+        }
+    }
+
+    fn read_list<R: Read>(input: &mut DataInput<R>, encoding: Encoding, budget: &mut Budget) -> Result<Tag, Error> {
+        budget.enter(input)?;
+
+        // kind and number of tags
+        let (tag_kind, size) = read_list_head(input, encoding, budget)?;
+
+        // read list of tags
+        budget.charge_tags(size, input)?;
+        let mut list = Vec::with_capacity(size);
+
+        for i in 0..size {
+            let tag = Tag::read_from(input, tag_kind, encoding, budget)
+                .map_err(|e| e.in_index(i))?;
+            list.push(tag);
+        }
+
+        budget.leave();
+        Ok(Tag::List(tag_kind, list))
+    }
+
+    fn read_compound<R: Read>(input: &mut DataInput<R>, encoding: Encoding, budget: &mut Budget) -> Result<Compound, Error> {
+        budget.enter(input)?;
+
+        let mut m = Compound::new();
+        loop {
+            let tag_kind = read_kind(input)?;
+
+            if tag_kind == 0 {
+                budget.leave();
+                break Ok(m);
+            }
+
+            // read tag key
+            let key = encoding.read_utf(input).at(input)?;
+            budget.charge(key.len(), input)?;
+            budget.charge_tags(1, input)?;
+
+            // decode tag
+            let tag = Tag::read_from(input, tag_kind, encoding, budget)
+                .map_err(|e| e.in_key(&key))?;
+
+            m.insert(key, tag);
+        }
+    }
+
+    // skips over a tag without decoding it, which allocates nothing
+    pub(crate) fn skip_from<R: Read>(input: &mut DataInput<R>, tag_kind: i8, encoding: Encoding, budget: &mut Budget) -> Result<(), Error> {
+        match tag_kind {
+            0x00 => Ok(()),
+            0x01 => input.skip_bytes(1).at(input),
+            0x02 => encoding.read_short(input).map(drop).at(input),
+            0x03 => encoding.read_int(input).map(drop).at(input),
+            0x04 => encoding.read_long(input).map(drop).at(input),
+            0x05 => input.skip_bytes(4).at(input),
+            0x06 => input.skip_bytes(8).at(input),
             0x07 => {
-                let size = input.read_int()?;
-                Ok(Tag::ByteArray(input.read_bytes(size as usize)?))
+                let size = read_len(input, encoding)?;
+                input.skip_bytes(size).at(input)
             },
-            0x08 => Ok(Tag::String(input.read_utf()?)),
+            0x0b => {
+                let size = read_len(input, encoding)?;
+                encoding.skip_numbers(input, size, 4).at(input)
+            },
+            0x0c => {
+                let size = read_len(input, encoding)?;
+                encoding.skip_numbers(input, size, 8).at(input)
+            },
+            0x08 => encoding.skip_utf(input).at(input),
             0x09 => {
-                // kind of tag
-                let tag_kind = input.read_byte()?;
+                budget.enter(input)?;
 
-                // read list of tags
-                let size = input.read_int()? as usize;
-                let mut list = Vec::with_capacity(size);
+                let (tag_kind, size) = read_list_head(input, encoding, budget)?;
 
-                for _ in 0..size {
-                    list.push(Tag::read_from(input, tag_kind)?);
+                for i in 0..size {
+                    Tag::skip_from(input, tag_kind, encoding, budget)
+                        .map_err(|e| e.in_index(i))?;
                 }
 
-                Ok(Tag::List(list))
+                budget.leave();
+                Ok(())
             },
             0x0a => {
-                let mut m = HashMap::new();
+                budget.enter(input)?;
+
                 loop {
-                    let tag_kind = input.read_byte()?;
+                    let tag_kind = read_kind(input)?;
 
                     if tag_kind == 0 {
-                        break Ok(Tag::Compound(m));
+                        budget.leave();
+                        break Ok(());
                     }
 
-                    // read tag key
-                    let key = input.read_utf()?;
-
-                    // decode tag
-                    let tag = Tag::read_from(input, tag_kind)?;
+                    let key = encoding.read_utf(input).at(input)?;
 
-                    m.insert(key, tag);
+                    Tag::skip_from(input, tag_kind, encoding, budget)
+                        .map_err(|e| e.in_key(&key))?;
                 }
             },
-            _ => Err(io::Error::new(io::ErrorKind::Other, "invalid tag kind")),
+            _ => Err(Error::new(ErrorKind::UnknownTag(tag_kind), None)),
         }
     }
 }
 
+// reads the length of a list or array
+pub(crate) fn read_len<R: Read>(input: &mut DataInput<R>, encoding: Encoding) -> Result<usize, Error> {
+    let offset = input.position();
+    match encoding.read_int(input).at(input)? {
+        size if size < 0 => Err(Error::new(ErrorKind::NegativeLength(size), Some(offset))),
+        size => Ok(size as usize),
+    }
+}
+
+// reads the kind and length of the elements of a list
+pub(crate) fn read_list_head<R: Read>(input: &mut DataInput<R>, encoding: Encoding, budget: &Budget) -> Result<(i8, usize), Error> {
+    let tag_kind = read_kind(input)?;
+    let offset = input.position();
+    let size = read_len(input, encoding)?;
+    budget.check_len(size, input)?;
+
+    // end tags are no values, and take no bytes, so only an empty list may
+    // be of them
+    if tag_kind == 0 && size > 0 {
+        return Err(Error::new(ErrorKind::UnknownTag(tag_kind), Some(offset)))
+    }
+    Ok((tag_kind, size))
+}
+
+// reads the kind of a tag
+pub(crate) fn read_kind<R: Read>(input: &mut DataInput<R>) -> Result<i8, Error> {
+    let offset = input.position();
+    match input.read_byte().at(input)? {
+        tag_kind @ 0x00..=0x0c => Ok(tag_kind),
+        tag_kind => Err(Error::new(ErrorKind::UnknownTag(tag_kind), Some(offset))),
+    }
+}
+
 impl NBT {
-    /// This associated method decodes a new NBT from a [`DataInput`].
-    pub fn read_from<R: Read>(input: &mut DataInput<R>) -> io::Result<NBT> {
-        let tag_kind = input.read_byte()?;
+    /// This associated method decodes a new NBT from a [`DataInput`], within
+    /// the default [`Limits`].
+    pub fn read_from<R: Read>(input: &mut DataInput<R>) -> Result<NBT, Error> {
+        NBT::read_with_limits(input, &Limits::default())
+    }
+
+    /// This associated method decodes a new NBT from a [`DataInput`],
+    /// failing as soon as the input goes beyond `limits`.
+    pub fn read_with_limits<R: Read>(input: &mut DataInput<R>, limits: &Limits) -> Result<NBT, Error> {
+        NBT::read_encoded(input, Encoding::BigEndian, limits)
+    }
+
+    /// This associated method decodes a new NBT laid out as set by
+    /// `encoding` from a [`DataInput`], failing as soon as the input goes
+    /// beyond `limits`.
+    pub fn read_encoded<R: Read>(input: &mut DataInput<R>, encoding: Encoding, limits: &Limits) -> Result<NBT, Error> {
+        let offset = input.position();
+        let tag_kind = input.read_byte().at(input)?;
 
         if tag_kind != 10 {
-            return Err(io::Error::new(io::ErrorKind::Other, "invalid tag kind, expected compound"))
+            let kind = ErrorKind::UnexpectedTag { expected: 10, found: tag_kind };
+            return Err(Error::new(kind, Some(offset)))
         }
 
         // read tag key
-        let key = input.read_utf()?;
+        let key = encoding.read_utf(input).at(input)?;
 
         // decode tag
-        let tag = Tag::read_from(input, 10)?;
+        let tag = Tag::read_from(input, 10, encoding, &mut Budget::new(limits, 0))?;
 
         Ok(NBT { key, tag })
     }
 }
 
 impl Tag {
-    fn write_to<W: Write>(&self, output: &mut DataOutput<W>) -> io::Result<()> {
+    fn write_to<W: Write>(&self, output: &mut DataOutput<W>, options: &WriteOptions) -> Result<(), Error> {
+        let encoding = options.encoding;
         match self {
             Tag::End => Ok(()),
-            Tag::Byte(x) => output.write_byte(*x),
-            Tag::Short(x) => output.write_short(*x),
-            Tag::Int(x) => output.write_int(*x),
-            Tag::Long(x) => output.write_long(*x),
-            Tag::Float(x) => output.write_float(*x),
-            Tag::Double(x) => output.write_double(*x),
+            Tag::Byte(x) => output.write_byte(*x).at(output),
+            Tag::Short(x) => encoding.write_short(output, *x).at(output),
+            Tag::Int(x) => encoding.write_int(output, *x).at(output),
+            Tag::Long(x) => encoding.write_long(output, *x).at(output),
+            Tag::Float(x) => encoding.write_float(output, *x).at(output),
+            Tag::Double(x) => encoding.write_double(output, *x).at(output),
             Tag::ByteArray(xs) => {
-                output.write_int((xs.len()&0x7fff_ffff) as i32)?;
-                output.write_bytes(xs)
+                write_len(output, xs.len(), encoding)?;
+                output.write_bytes(xs).at(output)
             },
-            Tag::String(s) => output.write_utf(s),
-            Tag::List(xs) => {
-                let tag_kind = xs
-                    .get(0)
-                    .map(Tag::kind)
-                    .unwrap_or(1);
+            Tag::String(s) => encoding.write_utf(output, s).at(output),
+            Tag::List(tag_kind, xs) => {
+                let tag_kind = *tag_kind;
+                check_kind(output, tag_kind, options)?;
 
                 // write tag kind and length of the list
-                output.write_byte(tag_kind)?;
-                output.write_int((xs.len()&0x7fff_ffff) as i32)?;
+                output.write_byte(tag_kind).at(output)?;
+                write_len(output, xs.len(), encoding)?;
 
                 // write tag contents
-                for tag in xs.iter() {
-                    tag.write_to(output)?;
+                for (i, tag) in xs.iter().enumerate() {
+                    if tag.kind() != tag_kind {
+                        let kind = ErrorKind::HeterogeneousList { expected: tag_kind, found: tag.kind() };
+                        return Err(Error::new(kind, Some(output.position())).in_index(i))
+                    }
+                    tag.write_to(output, options).map_err(|e| e.in_index(i))?;
                 }
 
                 Ok(())
             },
             Tag::Compound(m) => {
-                for (key, tag) in m.iter() {
+                let mut entries: Vec<_> = m.iter().collect();
+                if options.sort_keys {
+                    entries.sort_unstable_by_key(|&(key, _)| key);
+                }
+
+                for (key, tag) in entries {
                     let tag_kind = tag.kind();
+                    check_kind(output, tag_kind, options).map_err(|e| e.in_key(key))?;
 
                     // write the tag byte
-                    output.write_byte(tag_kind)?;
+                    output.write_byte(tag_kind).at(output)?;
 
                     // write the key
-                    output.write_utf(key)?;
+                    encoding.write_utf(output, key).at(output)?;
 
                     // write the tag itself
-                    tag.write_to(output)?;
+                    tag.write_to(output, options).map_err(|e| e.in_key(key))?;
                 }
                 // end
-                output.write_byte(0)
+                output.write_byte(0).at(output)
+            },
+            Tag::IntArray(xs) => {
+                write_len(output, xs.len(), encoding)?;
+                encoding.write_ints(output, xs).at(output)
+            },
+            Tag::LongArray(xs) => {
+                write_len(output, xs.len(), encoding)?;
+                encoding.write_longs(output, xs).at(output)
             },
         }
     }
 }
 
+// writes the length of a list or array
+fn write_len<W: Write>(output: &mut DataOutput<W>, len: usize, encoding: Encoding) -> Result<(), Error> {
+    encoding.write_int(output, (len&0x7fff_ffff) as i32).at(output)
+}
+
+// checks that a kind of tag exists in the version being written
+fn check_kind<W>(output: &DataOutput<W>, tag_kind: i8, options: &WriteOptions) -> Result<(), Error> {
+    if tag_kind > options.version.max_kind() {
+        let kind = ErrorKind::UnsupportedTag { kind: tag_kind, version: options.version };
+        return Err(Error::new(kind, Some(output.position())))
+    }
+    Ok(())
+}
+
 impl NBT {
-    /// This method encodes an NBT tag into a [`DataOutput`].
-    pub fn write_to<W: Write>(&self, output: &mut DataOutput<W>) -> io::Result<()> {
+    /// This method encodes an NBT tag into a [`DataOutput`], keeping the
+    /// entries of compounds in their insertion order.
+    pub fn write_to<W: Write>(&self, output: &mut DataOutput<W>) -> Result<(), Error> {
+        self.write_with_options(output, &WriteOptions::default())
+    }
+
+    /// This method encodes an NBT tag into a [`DataOutput`], as set by
+    /// `options`.
+    pub fn write_with_options<W: Write>(&self, output: &mut DataOutput<W>, options: &WriteOptions) -> Result<(), Error> {
         // write the tag byte
-        output.write_byte(10)?;
+        output.write_byte(10).at(output)?;
 
         // write the key
-        output.write_utf(&self.key)?;
+        options.encoding.write_utf(output, &self.key).at(output)?;
 
         // write the tag itself
-        self.tag.write_to(output)
+        self.tag.write_to(output, options)
     }
 }
 
@@ -218,13 +631,13 @@ impl NBT {
 mod tests {
     use std::fs::File;
     use std::default::Default;
-    use std::collections::HashMap;
-    use std::io::{self, BufReader, BufWriter};
+    use std::io::{self, BufReader, BufWriter, Read};
 
     use flate2::bufread::GzDecoder;
     use flate2::write::GzEncoder;
 
-    use crate::serialize::nbt::{NBT, Tag};
+    use crate::serialize::nbt::{NBT, Tag, Compound, ErrorKind, Limits, WriteOptions, Version, Encoding, MAX_DEPTH};
+    use crate::serialize::nbt::limits::Budget;
     use crate::serialize::{DataInput, DataOutput};
 
     fn open_nbt_file(path: &str) -> io::Result<GzDecoder<BufReader<File>>> {
@@ -259,7 +672,7 @@ mod tests {
             let gz = create_nbt_file("res/test.dat").unwrap();
             let mut output = DataOutput::new(gz);
 
-            let ints: HashMap<String, Tag> = (0..=100)
+            let ints: Compound = (0..=100)
                 .map(|x| {
                     let s = format!("{}", x);
                     let x = Tag::Int(x);
@@ -284,4 +697,227 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_nbt_errors() {
+        let mut output = DataOutput::new(Vec::new());
+        output.write_byte(0x0a).unwrap();
+        output.write_utf("").unwrap();
+        for key in ["Data", "Player"] {
+            output.write_byte(0x0a).unwrap();
+            output.write_utf(key).unwrap();
+        }
+        output.write_byte(0x09).unwrap();
+        output.write_utf("Inventory").unwrap();
+        output.write_byte(0x0a).unwrap();
+        output.write_int(5).unwrap();
+        for _ in 0..3 {
+            output.write_byte(0).unwrap();
+        }
+        let offset = output.position();
+        output.write_byte(0x20).unwrap();
+        output.write_utf("id").unwrap();
+
+        let buf = output.into_inner();
+        let e = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnknownTag(0x20)));
+        assert_eq!(e.offset(), Some(offset));
+        assert_eq!(
+            e.to_string(),
+            format!("unknown tag kind 32 at byte {} in Data/Player/Inventory[3]", offset),
+        );
+
+        // cut in the middle of the list
+        let e = NBT::read_from(&mut DataInput::new(&buf[..offset as usize - 1])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Truncated));
+        assert_eq!(e.to_string().rsplit(' ').next(), Some("Data/Player/Inventory[2]"));
+
+        // negative length
+        let mut output = DataOutput::new(Vec::new());
+        output.write_byte(0x0a).unwrap();
+        output.write_utf("").unwrap();
+        output.write_byte(0x07).unwrap();
+        output.write_utf("bytes").unwrap();
+        output.write_int(-1).unwrap();
+
+        let buf = output.into_inner();
+        let e = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::NegativeLength(-1)));
+        assert_eq!(e.offset(), Some(11));
+
+        // lists nested too deep
+        let mut output = DataOutput::new(Vec::new());
+        output.write_byte(0x0a).unwrap();
+        output.write_utf("").unwrap();
+        output.write_byte(0x09).unwrap();
+        output.write_utf("list").unwrap();
+        for _ in 0..MAX_DEPTH {
+            output.write_byte(0x09).unwrap();
+            output.write_int(1).unwrap();
+        }
+
+        let buf = output.into_inner();
+        let e = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::DepthExceeded(MAX_DEPTH)));
+
+        // lists mixing kinds of tags
+        let mut m = Compound::new();
+        m.insert("list".into(), Tag::List(3, vec![Tag::Int(1), Tag::Short(2)]));
+        let nbt = NBT::new("", Tag::Compound(m)).unwrap();
+
+        let e = nbt.write_to(&mut DataOutput::new(Vec::new())).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::HeterogeneousList { expected: 3, found: 2 }));
+        assert_eq!(e.to_string().rsplit(' ').next(), Some("list[1]"));
+    }
+
+    #[test]
+    fn test_nbt_limits() {
+        // a list claiming a billion ints
+        let mut output = DataOutput::new(Vec::new());
+        output.write_byte(0x0a).unwrap();
+        output.write_utf("").unwrap();
+        output.write_byte(0x09).unwrap();
+        output.write_utf("list").unwrap();
+        output.write_byte(0x03).unwrap();
+        output.write_int(1 << 30).unwrap();
+
+        let buf = output.into_inner();
+        let e = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::LengthExceeded { len: 0x4000_0000, .. }));
+
+        let limits = Limits { max_len: usize::MAX, ..Limits::default() };
+        let e = NBT::read_with_limits(&mut DataInput::new(&buf[..]), &limits).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::SizeExceeded(_)));
+
+        // the whole file fits in the default limits, but not in tighter ones
+        let mut input = DataInput::new(open_nbt_file("res/bigtest.dat").unwrap());
+        assert!(NBT::read_from(&mut input).is_ok());
+
+        let limits = Limits { max_depth: 2, ..Limits::default() };
+        let mut input = DataInput::new(open_nbt_file("res/bigtest.dat").unwrap());
+        let e = NBT::read_with_limits(&mut input, &limits).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::DepthExceeded(2)));
+
+        let limits = Limits { max_bytes: 1024, ..Limits::default() };
+        let mut input = DataInput::new(open_nbt_file("res/bigtest.dat").unwrap());
+        let e = NBT::read_with_limits(&mut input, &limits).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::SizeExceeded(1024)));
+
+        // a list of end tags, which take no bytes, read or skipped
+        let buf = b"\x0a\0\0\x09\0\x01l\0\x7f\xff\xff\xff\0";
+        let e = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::LengthExceeded { .. }));
+        let limits = Limits::unlimited();
+        let e = NBT::read_with_limits(&mut DataInput::new(&buf[..]), &limits).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnknownTag(0)));
+        assert_eq!(e.offset(), Some(8));
+        let e = Tag::skip_from(&mut DataInput::new(&buf[3..]), 0x0a, Encoding::BigEndian, &mut Budget::new(&limits, 0)).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnknownTag(0)));
+    }
+
+    #[test]
+    fn test_nbt_order() {
+        for path in &["res/player.dat", "res/bigtest.dat"] {
+            let mut buf = Vec::new();
+            open_nbt_file(path).unwrap().read_to_end(&mut buf).unwrap();
+
+            // re-encoding a file that was only read gives back the same bytes
+            let nbt = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap();
+            let mut output = DataOutput::new(Vec::new());
+            nbt.write_to(&mut output).unwrap();
+            assert_eq!(output.into_inner(), buf);
+        }
+
+        let mut m = Compound::new();
+        for key in ["b", "c", "a"] {
+            m.insert(key.into(), Tag::Byte(0));
+        }
+        let nbt = NBT::new("", Tag::Compound(m)).unwrap();
+
+        let keys = |options: &WriteOptions| {
+            let mut output = DataOutput::new(Vec::new());
+            nbt.write_with_options(&mut output, options).unwrap();
+            // a 3 bytes header, then entries of 5 bytes with a 1 byte key
+            output.into_inner()[3..]
+                .chunks(5)
+                .filter_map(|entry| entry.get(3).map(|&key| key as char))
+                .collect::<String>()
+        };
+        assert_eq!(keys(&WriteOptions::default()), "bca");
+        assert_eq!(keys(&WriteOptions::canonical()), "abc");
+    }
+
+    #[test]
+    fn test_nbt_arrays() {
+        let mut m = Compound::new();
+        m.insert("ints".into(), Tag::IntArray(vec![1, -2, i32::MAX]));
+        m.insert("longs".into(), Tag::LongArray(vec![i64::MIN, 0]));
+        m.insert("empty".into(), Tag::List(0x0a, vec![]));
+        let nbt = NBT::new("", Tag::Compound(m)).unwrap();
+
+        let mut output = DataOutput::new(Vec::new());
+        nbt.write_to(&mut output).unwrap();
+        let buf = output.into_inner();
+
+        let decoded = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap();
+        match decoded.tag() {
+            Tag::Compound(m) => {
+                assert!(matches!(&m["ints"], Tag::IntArray(xs) if xs == &[1, -2, i32::MAX]));
+                assert!(matches!(&m["longs"], Tag::LongArray(xs) if xs == &[i64::MIN, 0]));
+                // an empty list keeps the kind of its elements
+                assert!(matches!(&m["empty"], Tag::List(0x0a, xs) if xs.is_empty()));
+            },
+            _ => panic!("not a compound tag"),
+        }
+
+        // alpha NBT has no arrays of ints or longs
+        let e = nbt.write_with_options(&mut DataOutput::new(Vec::new()), &WriteOptions::alpha()).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnsupportedTag { kind: 0x0b, version: Version::Alpha }));
+        assert_eq!(e.to_string().rsplit(' ').next(), Some("ints"));
+    }
+
+    #[test]
+    fn test_nbt_encodings() {
+        let mut m = Compound::new();
+        m.insert("a".into(), Tag::Int(300));
+        let nbt = NBT::new("", Tag::Compound(m)).unwrap();
+
+        let encode = |nbt: &NBT, encoding| {
+            let mut output = DataOutput::new(Vec::new());
+            nbt.write_with_options(&mut output, &WriteOptions::encoded(encoding)).unwrap();
+            output.into_inner()
+        };
+        assert_eq!(encode(&nbt, Encoding::BigEndian), [0x0a, 0, 0, 0x03, 0, 1, b'a', 0, 0, 0x01, 0x2c, 0]);
+        assert_eq!(encode(&nbt, Encoding::LittleEndian), [0x0a, 0, 0, 0x03, 1, 0, b'a', 0x2c, 0x01, 0, 0, 0]);
+        assert_eq!(encode(&nbt, Encoding::Network), [0x0a, 0, 0x03, 1, b'a', 0xd8, 0x04, 0]);
+
+        // the same tree goes through every encoding
+        let mut m = Compound::new();
+        m.insert("ints".into(), Tag::IntArray(vec![0, -1, i32::MIN, i32::MAX]));
+        m.insert("longs".into(), Tag::LongArray(vec![i64::MIN, i64::MAX]));
+        m.insert("list".into(), Tag::list(vec![Tag::Short(-2), Tag::Short(3)]));
+        m.insert("float".into(), Tag::Float(1.5));
+        m.insert("string".into(), Tag::String("caf\u{e9}\0".into()));
+        let nbt = NBT::new("root", Tag::Compound(m)).unwrap();
+
+        for encoding in [Encoding::BigEndian, Encoding::LittleEndian, Encoding::Network] {
+            let buf = encode(&nbt, encoding);
+            let limits = Limits::default();
+            let decoded = NBT::read_encoded(&mut DataInput::new(&buf[..]), encoding, &limits).unwrap();
+            assert_eq!(decoded.key(), "root");
+            assert_eq!(encode(&decoded, encoding), buf);
+            assert_eq!(encode(&decoded, Encoding::BigEndian), encode(&nbt, Encoding::BigEndian));
+        }
+
+        // a varint that never ends
+        let buf = [0x0a, 0, 0x03, 1, b'a', 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let e = NBT::read_encoded(&mut DataInput::new(&buf[..]), Encoding::Network, &Limits::default()).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Io(_)));
+        assert_eq!(e.to_string().rsplit(' ').next(), Some("a"));
+
+        // a key of 4 GiB, which is not allocated before it comes
+        let buf = [0x0a, 0xff, 0xff, 0xff, 0xff, 0x0f, b'a'];
+        let e = NBT::read_encoded(&mut DataInput::new(&buf[..]), Encoding::Network, &Limits::default()).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Truncated));
+    }
 }
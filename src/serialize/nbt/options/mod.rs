@@ -0,0 +1,52 @@
+use crate::serialize::nbt::Encoding;
+
+/// Settings for encoding NBT.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Writes the entries of compounds sorted by key, rather than in their
+    /// insertion order, so that equal values always encode to the same bytes.
+    pub sort_keys: bool,
+    /// The version of the format to write, which refuses the tags it lacks.
+    pub version: Version,
+    /// How numbers and strings are laid out in bytes.
+    pub encoding: Encoding,
+}
+
+impl WriteOptions {
+    /// Options for a canonical encoding, with the keys of compounds sorted.
+    pub fn canonical() -> Self {
+        Self { sort_keys: true, ..Self::default() }
+    }
+
+    /// Options for data that alpha 1.2.6 can read.
+    pub fn alpha() -> Self {
+        Self { version: Version::Alpha, ..Self::default() }
+    }
+
+    /// Options for data laid out as set by `encoding`, such as the
+    /// little-endian NBT of Bedrock edition.
+    pub fn encoded(encoding: Encoding) -> Self {
+        Self { encoding, ..Self::default() }
+    }
+}
+
+/// A version of the NBT format, by the kinds of tags it has.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Version {
+    /// The tags of alpha 1.2.6, up to [`Tag::Compound`](super::Tag::Compound).
+    Alpha,
+    /// Every tag, including [`Tag::IntArray`](super::Tag::IntArray) and
+    /// [`Tag::LongArray`](super::Tag::LongArray).
+    #[default]
+    Modern,
+}
+
+impl Version {
+    // the greatest kind of tag of this version
+    pub(crate) fn max_kind(self) -> i8 {
+        match self {
+            Version::Alpha => 0x0a,
+            Version::Modern => 0x0c,
+        }
+    }
+}
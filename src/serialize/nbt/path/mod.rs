@@ -0,0 +1,742 @@
+//! Look up and edit values deep in NBT trees with path expressions.
+//!
+//! A path is a sequence of steps, in the spirit of the paths of the `/data`
+//! command:
+//!
+//! - `Data` or `"with space"` steps into the entry of a compound with that
+//!   key, and `Data{Version:1}` only if the entry matches the compound in
+//!   braces.
+//! - `Spawn*` steps into every entry whose key matches the pattern, where
+//!   `*` stands for any characters and `?` for any single one.
+//! - `[0]` steps into an element of a list, counting from the end when
+//!   negative, `[]` into every element, and `[{id:1s}]` into every element
+//!   that matches the compound in braces.
+//!
+//! Steps into entries are separated by dots, as in
+//! `Data.Player.Inventory[{Slot:0b}].Count`. A path may also start with a
+//! compound in braces, which the root must match.
+//!
+//! ```
+//! # use neonmc::nbt;
+//! # use neonmc::serialize::nbt::path::Path;
+//! let mut level = nbt!({ Data: { SpawnX: 0, SpawnY: 64, SpawnZ: 0 } });
+//!
+//! let path: Path = "Data.SpawnX".parse().unwrap();
+//! path.set(&mut level, 16.into()).unwrap();
+//! assert_eq!(path.get(&level).unwrap().and_then(|x| x.as_i32()), Some(16));
+//!
+//! let path: Path = "Data.Spawn*".parse().unwrap();
+//! assert_eq!(path.get_all(&level).unwrap().len(), 3);
+//! ```
+
+use std::fmt;
+use std::error;
+use std::str::FromStr;
+
+use crate::serialize::nbt::{snbt, Compound, Segment, Tag};
+
+/// A parsed path expression.
+#[derive(Clone, Debug)]
+pub struct Path {
+    root: Option<Compound>,
+    steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+    Key(String, Option<Compound>),
+    Glob(String, Option<Compound>),
+    Index(i32),
+    All,
+    Filter(Compound),
+}
+
+/// The reason a path could not be parsed or followed.
+#[derive(Clone, Debug)]
+pub enum ErrorKind {
+    /// The path expression is malformed at the given byte offset.
+    Syntax { offset: usize, message: &'static str },
+    /// A value along the path is not of the kind of tag the path steps
+    /// into, a compound or a list.
+    Mismatch { expected: i8, found: i8 },
+    /// An index is out of the bounds of the list it steps into.
+    OutOfBounds { index: i32, len: usize },
+}
+
+/// An error raised while parsing or following a path, along with the path
+/// to the offending value.
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    path: Vec<Segment>,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, path: &[Segment]) -> Self {
+        Self { kind, path: path.to_vec() }
+    }
+
+    fn syntax(offset: usize, message: &'static str) -> Self {
+        Self::new(ErrorKind::Syntax { offset, message }, &[])
+    }
+
+    /// Returns the reason of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns the path to the offending value, from the root.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.path.iter()
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Syntax { offset, message } => write!(f, "{} at byte {}", message, offset),
+            ErrorKind::Mismatch { expected, found } => {
+                write!(f, "expected tag kind {}, found tag kind {}", expected, found)
+            },
+            ErrorKind::OutOfBounds { index, len } => {
+                write!(f, "index {} out of bounds of a list of {}", index, len)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)?;
+
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            write_segments(f, &self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Path, Error> {
+        let mut parser = Parser { src: s, pos: 0 };
+
+        let root = match parser.peek() {
+            Some('{') => Some(parser.compound()?),
+            _ => None,
+        };
+
+        let mut steps = Vec::new();
+        loop {
+            match parser.peek() {
+                None if steps.is_empty() => return Err(Error::syntax(parser.pos, "expected key")),
+                None => break,
+                Some('[') => steps.push(parser.bracket()?),
+                Some('.') if !steps.is_empty() => {
+                    parser.pos += 1;
+                    steps.push(parser.key()?);
+                },
+                Some(_) if steps.is_empty() => steps.push(parser.key()?),
+                Some(_) => return Err(Error::syntax(parser.pos, "expected '.' or '['")),
+            }
+        }
+
+        Ok(Path { root, steps })
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    // a compound in SNBT
+    fn compound(&mut self) -> Result<Compound, Error> {
+        let start = self.pos;
+        match snbt::parse_prefix(&self.src[self.pos..]) {
+            Ok((Tag::Compound(m), len)) => {
+                self.pos += len;
+                Ok(m)
+            },
+            Ok(_) => Err(Error::syntax(start, "expected compound")),
+            Err((offset, message)) => Err(Error::syntax(start + offset, message)),
+        }
+    }
+
+    // a key, quoted or not, and its optional filter
+    fn key(&mut self) -> Result<Step, Error> {
+        let start = self.pos;
+        let (key, glob) = match self.peek() {
+            Some('"') | Some('\'') => match snbt::parse_prefix(&self.src[self.pos..]) {
+                Ok((Tag::String(key), len)) => {
+                    self.pos += len;
+                    (key, false)
+                },
+                Ok(_) => unreachable!(),
+                Err((offset, message)) => return Err(Error::syntax(start + offset, message)),
+            },
+            _ => {
+                let len = self.src[self.pos..]
+                    .find(|c: char| c.is_whitespace() || "\"'.[]{}".contains(c))
+                    .unwrap_or(self.src.len() - self.pos);
+                if len == 0 {
+                    return Err(Error::syntax(start, "expected key"))
+                }
+                let key = &self.src[self.pos..self.pos + len];
+                self.pos += len;
+                (key.to_string(), key.contains(['*', '?']))
+            },
+        };
+
+        let filter = match self.peek() {
+            Some('{') => Some(self.compound()?),
+            _ => None,
+        };
+
+        Ok(match glob {
+            true => Step::Glob(key, filter),
+            false => Step::Key(key, filter),
+        })
+    }
+
+    // an index, `[]` or a filter
+    fn bracket(&mut self) -> Result<Step, Error> {
+        self.pos += 1;
+
+        let step = match self.peek() {
+            Some(']') => Step::All,
+            Some('{') => Step::Filter(self.compound()?),
+            _ => {
+                let start = self.pos;
+                let len = self.src[self.pos..].find(']').unwrap_or(self.src.len() - self.pos);
+                let index = self.src[start..start + len]
+                    .parse()
+                    .map_err(|_| Error::syntax(start, "expected index"))?;
+                self.pos += len;
+                Step::Index(index)
+            },
+        };
+
+        match self.peek() {
+            Some(']') => {
+                self.pos += 1;
+                Ok(step)
+            },
+            _ => Err(Error::syntax(self.pos, "expected ']'")),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(root) = &self.root {
+            write!(f, "{}", Tag::Compound(root.clone()))?;
+        }
+
+        for (i, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Key(key, filter) | Step::Glob(key, filter) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    match step {
+                        Step::Key(..) => write_key(f, key)?,
+                        _ => f.write_str(key)?,
+                    }
+                    if let Some(filter) = filter {
+                        write!(f, "{}", Tag::Compound(filter.clone()))?;
+                    }
+                },
+                Step::Index(index) => write!(f, "[{}]", index)?,
+                Step::All => f.write_str("[]")?,
+                Step::Filter(filter) => write!(f, "[{}]", Tag::Compound(filter.clone()))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// writes segments as a path, outermost first
+pub(crate) fn write_segments(f: &mut fmt::Formatter<'_>, path: &[Segment]) -> fmt::Result {
+    for (i, segment) in path.iter().enumerate() {
+        match segment {
+            Segment::Key(key) => {
+                if i > 0 {
+                    f.write_str(".")?;
+                }
+                write_key(f, key)?;
+            },
+            Segment::Index(index) => write!(f, "[{}]", index)?,
+        }
+    }
+    Ok(())
+}
+
+// writes a key, quoted unless it reads back the same
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str) -> fmt::Result {
+    let plain = !key.is_empty() && !key.contains(|c: char| c.is_whitespace() || "\"'.[]{}*?".contains(c));
+    match plain {
+        true => f.write_str(key),
+        false => write!(f, "{}", Tag::String(key.into())),
+    }
+}
+
+impl Path {
+    /// Returns the first value at this path in `tag`, if any.
+    pub fn get<'a>(&self, tag: &'a Tag) -> Result<Option<&'a Tag>, Error> {
+        Ok(self.get_all(tag)?.into_iter().next())
+    }
+
+    /// Returns every value at this path in `tag`, in order.
+    pub fn get_all<'a>(&self, tag: &'a Tag) -> Result<Vec<&'a Tag>, Error> {
+        let mut found = Vec::new();
+        if self.root.iter().all(|root| matches_compound(tag, root)) {
+            select(tag, &self.steps, &mut Vec::new(), &mut found)?;
+        }
+        Ok(found)
+    }
+
+    /// Sets every value at this path in `tag` to `value`, returning how many
+    /// were set.
+    ///
+    /// Missing entries are created along the way, as empty compounds for
+    /// all but the last step, unless their step has a filter.
+    pub fn set(&self, tag: &mut Tag, value: Tag) -> Result<usize, Error> {
+        let mut count = 0;
+        self.parents(tag, true, &mut |parent, step, path| {
+            count += set_child(parent, step, &value, path)?;
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
+    /// Removes every value at this path from `tag`, returning how many were
+    /// removed.
+    pub fn remove(&self, tag: &mut Tag) -> Result<usize, Error> {
+        let mut count = 0;
+        self.parents(tag, false, &mut |parent, step, path| {
+            count += remove_child(parent, step, path)?;
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
+    // calls `f` with every parent of the values at this path, and the last
+    // step from them
+    fn parents(&self, tag: &mut Tag, create: bool, f: &mut Visit<'_>) -> Result<(), Error> {
+        if !self.root.iter().all(|root| matches_compound(tag, root)) {
+            return Ok(())
+        }
+        let (last, steps) = self.steps.split_last().unwrap();
+        select_mut(tag, steps, last, create, &mut Vec::new(), f)
+    }
+}
+
+type Visit<'a> = dyn FnMut(&mut Tag, &Step, &[Segment]) -> Result<(), Error> + 'a;
+
+fn select<'a>(tag: &'a Tag, steps: &[Step], path: &mut Vec<Segment>, found: &mut Vec<&'a Tag>) -> Result<(), Error> {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => {
+            found.push(tag);
+            return Ok(())
+        },
+    };
+
+    match step {
+        Step::Key(key, filter) => {
+            let m = compound(tag, path)?;
+            if let Some(x) = m.get(key).filter(|x| matches_filter(x, filter)) {
+                path.push(Segment::Key(key.clone()));
+                select(x, rest, path, found)?;
+                path.pop();
+            }
+        },
+        Step::Glob(pattern, filter) => {
+            for (key, x) in compound(tag, path)? {
+                if glob(pattern, key) && matches_filter(x, filter) {
+                    path.push(Segment::Key(key.clone()));
+                    select(x, rest, path, found)?;
+                    path.pop();
+                }
+            }
+        },
+        Step::Index(index) => {
+            let xs = list(tag, path)?;
+            if let Some(i) = resolve(*index, xs.len()) {
+                path.push(Segment::Index(i));
+                select(&xs[i], rest, path, found)?;
+                path.pop();
+            }
+        },
+        Step::All | Step::Filter(_) => {
+            for (i, x) in list(tag, path)?.iter().enumerate() {
+                if matches_step(x, step) {
+                    path.push(Segment::Index(i));
+                    select(x, rest, path, found)?;
+                    path.pop();
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn select_mut(tag: &mut Tag, steps: &[Step], last: &Step, create: bool, path: &mut Vec<Segment>, f: &mut Visit<'_>) -> Result<(), Error> {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return f(tag, last, path),
+    };
+
+    match step {
+        Step::Key(key, filter) => {
+            let m = compound_mut(tag, path)?;
+            if create && filter.is_none() && !m.contains_key(key) {
+                m.insert(key.clone(), Tag::Compound(Compound::new()));
+            }
+            if let Some(x) = m.get_mut(key).filter(|x| matches_filter(x, filter)) {
+                path.push(Segment::Key(key.clone()));
+                select_mut(x, rest, last, create, path, f)?;
+                path.pop();
+            }
+        },
+        Step::Glob(pattern, filter) => {
+            for (key, x) in compound_mut(tag, path)? {
+                if glob(pattern, key) && matches_filter(x, filter) {
+                    path.push(Segment::Key(key.clone()));
+                    select_mut(x, rest, last, create, path, f)?;
+                    path.pop();
+                }
+            }
+        },
+        Step::Index(index) => {
+            let xs = list_mut(tag, path)?;
+            let len = xs.len();
+            match resolve(*index, len) {
+                Some(i) => {
+                    path.push(Segment::Index(i));
+                    select_mut(&mut xs[i], rest, last, create, path, f)?;
+                    path.pop();
+                },
+                None if create => {
+                    return Err(Error::new(ErrorKind::OutOfBounds { index: *index, len }, path))
+                },
+                None => (),
+            }
+        },
+        Step::All | Step::Filter(_) => {
+            for (i, x) in list_mut(tag, path)?.iter_mut().enumerate() {
+                if matches_step(x, step) {
+                    path.push(Segment::Index(i));
+                    select_mut(x, rest, last, create, path, f)?;
+                    path.pop();
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn set_child(parent: &mut Tag, step: &Step, value: &Tag, path: &[Segment]) -> Result<usize, Error> {
+    match step {
+        Step::Key(key, filter) => {
+            let m = compound_mut(parent, path)?;
+            match m.get_mut(key) {
+                Some(x) if matches_filter(x, filter) => *x = value.clone(),
+                Some(_) => return Ok(0),
+                None if filter.is_none() => {
+                    m.insert(key.clone(), value.clone());
+                },
+                None => return Ok(0),
+            }
+            Ok(1)
+        },
+        Step::Glob(pattern, filter) => {
+            let mut count = 0;
+            for (key, x) in compound_mut(parent, path)? {
+                if glob(pattern, key) && matches_filter(x, filter) {
+                    *x = value.clone();
+                    count += 1;
+                }
+            }
+            Ok(count)
+        },
+        _ => {
+            let (kind, xs) = match parent {
+                Tag::List(kind, xs) => (*kind, xs),
+                _ => return Err(mismatch(0x09, parent, path)),
+            };
+            if !xs.is_empty() && kind != value.kind() {
+                let mut path = path.to_vec();
+                path.push(Segment::Index(0));
+                return Err(Error::new(ErrorKind::Mismatch { expected: kind, found: value.kind() }, &path))
+            }
+
+            let mut count = 0;
+            match step {
+                Step::Index(index) => match resolve(*index, xs.len()) {
+                    Some(i) => {
+                        xs[i] = value.clone();
+                        count += 1;
+                    },
+                    None => {
+                        let kind = ErrorKind::OutOfBounds { index: *index, len: xs.len() };
+                        return Err(Error::new(kind, path))
+                    },
+                },
+                _ => for x in xs.iter_mut() {
+                    if matches_step(x, step) {
+                        *x = value.clone();
+                        count += 1;
+                    }
+                },
+            }
+            Ok(count)
+        },
+    }
+}
+
+fn remove_child(parent: &mut Tag, step: &Step, path: &[Segment]) -> Result<usize, Error> {
+    let before = match parent {
+        Tag::Compound(m) => m.len(),
+        Tag::List(_, xs) => xs.len(),
+        _ => 0,
+    };
+
+    match step {
+        Step::Key(key, filter) => {
+            let m = compound_mut(parent, path)?;
+            if m.get(key).is_some_and(|x| matches_filter(x, filter)) {
+                m.shift_remove(key);
+            }
+        },
+        Step::Glob(pattern, filter) => {
+            compound_mut(parent, path)?.retain(|key, x| !(glob(pattern, key) && matches_filter(x, filter)));
+        },
+        Step::Index(index) => {
+            let xs = list_mut(parent, path)?;
+            if let Some(i) = resolve(*index, xs.len()) {
+                xs.remove(i);
+            }
+        },
+        _ => list_mut(parent, path)?.retain(|x| !matches_step(x, step)),
+    }
+
+    let after = match parent {
+        Tag::Compound(m) => m.len(),
+        Tag::List(_, xs) => xs.len(),
+        _ => 0,
+    };
+    Ok(before - after)
+}
+
+fn mismatch(expected: i8, found: &Tag, path: &[Segment]) -> Error {
+    Error::new(ErrorKind::Mismatch { expected, found: found.kind() }, path)
+}
+
+fn compound<'a>(tag: &'a Tag, path: &[Segment]) -> Result<&'a Compound, Error> {
+    tag.as_compound().ok_or_else(|| mismatch(0x0a, tag, path))
+}
+
+fn compound_mut<'a>(tag: &'a mut Tag, path: &[Segment]) -> Result<&'a mut Compound, Error> {
+    match tag {
+        Tag::Compound(m) => Ok(m),
+        _ => Err(mismatch(0x0a, tag, path)),
+    }
+}
+
+fn list<'a>(tag: &'a Tag, path: &[Segment]) -> Result<&'a [Tag], Error> {
+    tag.as_list().ok_or_else(|| mismatch(0x09, tag, path))
+}
+
+fn list_mut<'a>(tag: &'a mut Tag, path: &[Segment]) -> Result<&'a mut Vec<Tag>, Error> {
+    match tag {
+        Tag::List(_, xs) => Ok(xs),
+        _ => Err(mismatch(0x09, tag, path)),
+    }
+}
+
+// the index into a list of `len` elements, counting from the end when negative
+fn resolve(index: i32, len: usize) -> Option<usize> {
+    let i = match index {
+        i if i < 0 => len.checked_sub(i.unsigned_abs() as usize)?,
+        i => i as usize,
+    };
+    Some(i).filter(|&i| i < len)
+}
+
+fn matches_step(x: &Tag, step: &Step) -> bool {
+    match step {
+        Step::Filter(filter) => matches_compound(x, filter),
+        _ => true,
+    }
+}
+
+fn matches_filter(x: &Tag, filter: &Option<Compound>) -> bool {
+    filter.iter().all(|filter| matches_compound(x, filter))
+}
+
+fn matches_compound(x: &Tag, filter: &Compound) -> bool {
+    match x {
+        Tag::Compound(m) => filter.iter().all(|(key, y)| m.get(key).is_some_and(|x| matches(x, y))),
+        _ => false,
+    }
+}
+
+// whether `x` has everything of `pattern`: the entries of compounds, some
+// element for each element of lists, and equal values otherwise
+fn matches(x: &Tag, pattern: &Tag) -> bool {
+    match (x, pattern) {
+        (Tag::Compound(_), Tag::Compound(m)) => matches_compound(x, m),
+        (Tag::List(_, xs), Tag::List(_, ys)) if ys.is_empty() => xs.is_empty(),
+        (Tag::List(_, xs), Tag::List(_, ys)) => ys.iter().all(|y| xs.iter().any(|x| matches(x, y))),
+        // the equality of tags, which compares floats by their bits
+        _ => x == pattern,
+    }
+}
+
+// matches `s` against a pattern where `*` is any characters and `?` any one
+fn glob(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+
+    // the last `*` seen, and where its match in `s` ends for now
+    let mut star = None;
+    let (mut p, mut i) = (0, 0);
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, i));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            },
+            _ => match star {
+                // let the last `*` take one more character
+                Some((q, j)) => {
+                    star = Some((q, j + 1));
+                    p = q + 1;
+                    i = j + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nbt;
+    use crate::serialize::nbt::Tag;
+    use crate::serialize::nbt::path::{Path, ErrorKind};
+
+    fn path(s: &str) -> Path {
+        s.parse().unwrap()
+    }
+
+    fn level() -> Tag {
+        nbt!({
+            Data: {
+                SpawnX: 10,
+                SpawnY: 64,
+                SpawnZ: -3,
+                Player: {
+                    Inventory: [
+                        { Slot: 0B, id: 1s, Count: 64b },
+                        { Slot: 1b, id: 4s, Count: 12b },
+                        { Slot: 2b, id: 1s, Count: 3b },
+                    ],
+                },
+                "Level Name": "world",
+            },
+        })
+    }
+
+    #[test]
+    fn test_path_get() {
+        let level = level();
+        let get = |s: &str| path(s).get(&level).unwrap().cloned();
+
+        assert_eq!(get("Data.SpawnX").and_then(|x| x.as_i32()), Some(10));
+        assert_eq!(get("Data.Player.Inventory[0].Count").and_then(|x| x.as_i8()), Some(64));
+        assert_eq!(get("Data.Player.Inventory[-1].Count").and_then(|x| x.as_i8()), Some(3));
+        assert_eq!(get("Data.\"Level Name\"").and_then(|x| x.as_str().map(String::from)), Some("world".into()));
+        assert!(get("Data.Player.Inventory[3]").is_none());
+        assert!(get("Data.Missing.Deeper").is_none());
+        assert!(get("{Data:{SpawnX:11}}Data").is_none());
+        assert!(get("{Data:{SpawnX:10}}Data").is_some());
+
+        // floats are matched by their bits, as tags are compared
+        let tag = nbt!({ x: { nan: (f64::NAN), zero: 0.0f } });
+        assert!(path("x{nan:NaNd}").get(&tag).unwrap().is_some());
+        assert!(path("x{zero:0.0f}").get(&tag).unwrap().is_some());
+        assert!(path("x{zero:-0.0f}").get(&tag).unwrap().is_none());
+
+        let counts = |s: &str| -> Vec<i8> {
+            path(s).get_all(&level).unwrap().iter().filter_map(|x| x.as_i8()).collect()
+        };
+        assert_eq!(counts("Data.Player.Inventory[].Count"), [64, 12, 3]);
+        assert_eq!(counts("Data.Player.Inventory[{id:1s}].Count"), [64, 3]);
+        assert_eq!(path("Data.Spawn?").get_all(&level).unwrap().len(), 3);
+        assert_eq!(path("*.Player{Inventory:[{id:4s}]}.*").get_all(&level).unwrap().len(), 1);
+
+        let e = path("Data.SpawnX.Deeper").get(&level).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Mismatch { expected: 10, found: 3 }));
+        assert_eq!(e.to_string(), "expected tag kind 10, found tag kind 3 at Data.SpawnX");
+
+        let e = path("Data.Player.Inventory[{id:1s}][0]").get(&level).unwrap_err();
+        assert_eq!(e.to_string(), "expected tag kind 9, found tag kind 10 at Data.Player.Inventory[0]");
+    }
+
+    #[test]
+    fn test_path_edit() {
+        let mut level = level();
+
+        assert_eq!(path("Data.SpawnX").set(&mut level, 0.into()).unwrap(), 1);
+        assert_eq!(path("Data.Player.Inventory[{id:1s}].Count").set(&mut level, Tag::Byte(1)).unwrap(), 2);
+        assert_eq!(path("Data.Version.Major").set(&mut level, 1.into()).unwrap(), 1);
+        assert_eq!(path("Data.Version.Major").get(&level).unwrap().and_then(|x| x.as_i32()), Some(1));
+
+        let e = path("Data.Player.Inventory[5]").set(&mut level, Tag::Compound(Default::default())).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::OutOfBounds { index: 5, len: 3 }));
+        let e = path("Data.Player.Inventory[0]").set(&mut level, 1.into()).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Mismatch { expected: 10, found: 3 }));
+
+        assert_eq!(path("Data.Player.Inventory[{id:1s}]").remove(&mut level).unwrap(), 2);
+        assert_eq!(path("Data.Spawn*").remove(&mut level).unwrap(), 3);
+        assert_eq!(path("Data.Missing").remove(&mut level).unwrap(), 0);
+        assert_eq!(
+            level.to_string(),
+            r#"{Data:{Player:{Inventory:[{Slot:1b,id:4s,Count:12b}]},"Level Name":"world",Version:{Major:1}}}"#,
+        );
+    }
+
+    #[test]
+    fn test_path_syntax() {
+        for s in ["Data.Player.Inventory[{id:1s}].Count", "{a:1b}*.x?[]", "\"a b\".c{d:[I;1]}[-1]"] {
+            assert_eq!(path(s).to_string(), s);
+        }
+
+        let errors = [("", 0), ("a..b", 2), ("a[x]", 2), ("a[0", 3), ("a{b:}", 4), ("a b", 1), ("a{b:1é}", 5), ("é..b", 3)];
+        for (s, offset) in errors {
+            let e = s.parse::<Path>().unwrap_err();
+            assert!(matches!(e.kind(), ErrorKind::Syntax { offset: o, .. } if *o == offset), "{}: {}", s, e);
+        }
+    }
+}
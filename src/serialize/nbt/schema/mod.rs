@@ -0,0 +1,427 @@
+//! Check that NBT trees have the layout a reader expects.
+//!
+//! A [`Schema`] names the kind of tag expected at every place of a tree,
+//! the bounds of numbers and lengths, and the entries compounds must have.
+//! Schemas are built in Rust, or loaded from JSON such as:
+//!
+//! ```json
+//! {
+//!     "type": "compound",
+//!     "fields": {
+//!         "Health": { "type": "short", "min": 0, "max": 20 },
+//!         "Pos": { "type": "list", "element": { "type": "double" }, "min_len": 3, "max_len": 3 },
+//!         "Dimension": { "type": "int", "optional": true }
+//!     }
+//! }
+//! ```
+//!
+//! [`Schema::alpha_level`] and [`Schema::alpha_player`] describe the files
+//! that the alpha 1.2.6 client loads.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ::serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+
+use crate::serialize::nbt::{Segment, Tag};
+use crate::serialize::nbt::path::write_segments;
+
+/// The layout expected of a tag.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Schema {
+    #[serde(rename = "type")]
+    kind: Kind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_len: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_len: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    element: Option<Box<Schema>>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    fields: IndexMap<String, Field>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    closed: bool,
+}
+
+/// An entry of a compound.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Field {
+    #[serde(flatten)]
+    schema: Schema,
+    #[serde(default, skip_serializing_if = "is_false")]
+    optional: bool,
+}
+
+/// The kinds of tags a schema may expect.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// Any tag at all.
+    #[default]
+    Any,
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    ByteArray,
+    String,
+    List,
+    Compound,
+    IntArray,
+    LongArray,
+}
+
+fn is_false(x: &bool) -> bool {
+    !x
+}
+
+impl Kind {
+    // the kind of tag, if a single one is expected
+    fn tag_kind(self) -> Option<i8> {
+        match self {
+            Kind::Any => None,
+            Kind::Byte => Some(0x01),
+            Kind::Short => Some(0x02),
+            Kind::Int => Some(0x03),
+            Kind::Long => Some(0x04),
+            Kind::Float => Some(0x05),
+            Kind::Double => Some(0x06),
+            Kind::ByteArray => Some(0x07),
+            Kind::String => Some(0x08),
+            Kind::List => Some(0x09),
+            Kind::Compound => Some(0x0a),
+            Kind::IntArray => Some(0x0b),
+            Kind::LongArray => Some(0x0c),
+        }
+    }
+}
+
+/// What is wrong with a tag, as checked against a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    /// A required entry of a compound is missing.
+    Missing,
+    /// An entry that a closed compound does not expect.
+    Unexpected,
+    /// A tag of another kind was expected.
+    WrongKind { expected: i8, found: i8 },
+    /// A number out of its bounds.
+    OutOfRange { value: f64, min: Option<f64>, max: Option<f64> },
+    /// A list, array or string with too few or too many elements.
+    BadLength { len: usize, min: Option<usize>, max: Option<usize> },
+}
+
+/// A place where a tag does not match a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// The path to the offending value, from the root.
+    pub path: Vec<Segment>,
+    /// What is wrong with it.
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::Missing => f.write_str("missing entry")?,
+            ViolationKind::Unexpected => f.write_str("unexpected entry")?,
+            ViolationKind::WrongKind { expected, found } => {
+                write!(f, "expected tag kind {}, found tag kind {}", expected, found)?
+            },
+            ViolationKind::OutOfRange { value, min, max } => {
+                write!(f, "value {} out of range ", value)?;
+                write_range(f, min, max)?;
+            },
+            ViolationKind::BadLength { len, min, max } => {
+                write!(f, "length {} out of range ", len)?;
+                write_range(f, min, max)?;
+            },
+        }
+
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            write_segments(f, &self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_range<T: fmt::Display>(f: &mut fmt::Formatter<'_>, min: &Option<T>, max: &Option<T>) -> fmt::Result {
+    if let Some(min) = min {
+        write!(f, "{}", min)?;
+    }
+    f.write_str("..")?;
+    if let Some(max) = max {
+        write!(f, "={}", max)?;
+    }
+    Ok(())
+}
+
+impl Schema {
+    /// A schema expecting a tag of the given kind.
+    pub fn new(kind: Kind) -> Self {
+        Self { kind, ..Self::default() }
+    }
+
+    /// A schema expecting a list of tags matching `element`.
+    pub fn list(element: Schema) -> Self {
+        Self { element: Some(Box::new(element)), ..Self::new(Kind::List) }
+    }
+
+    /// A schema expecting a compound, with no entries yet.
+    pub fn compound() -> Self {
+        Self::new(Kind::Compound)
+    }
+
+    /// Bounds the value of a number, inclusively.
+    pub fn range(self, min: f64, max: f64) -> Self {
+        Self { min: Some(min), max: Some(max), ..self }
+    }
+
+    /// Bounds the length of a list, an array or a string, inclusively.
+    pub fn len(self, min: usize, max: usize) -> Self {
+        Self { min_len: Some(min), max_len: Some(max), ..self }
+    }
+
+    /// Requires the entry `key` of a compound to match `schema`.
+    pub fn field(mut self, key: &str, schema: Schema) -> Self {
+        self.fields.insert(key.into(), Field { schema, optional: false });
+        self
+    }
+
+    /// Allows the entry `key` of a compound, which must match `schema` when
+    /// present.
+    pub fn optional(mut self, key: &str, schema: Schema) -> Self {
+        self.fields.insert(key.into(), Field { schema, optional: true });
+        self
+    }
+
+    /// Reports the entries of a compound that are not its fields.
+    pub fn closed(self) -> Self {
+        Self { closed: true, ..self }
+    }
+
+    /// Parses a schema from JSON.
+    pub fn from_json(s: &str) -> serde_json::Result<Schema> {
+        serde_json::from_str(s)
+    }
+
+    /// Loads a schema from the JSON file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Schema> {
+        let s = fs::read_to_string(path)?;
+        Schema::from_json(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Checks `tag` against this schema, returning every violation.
+    pub fn validate(&self, tag: &Tag) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(tag, &mut Vec::new(), &mut violations);
+        violations
+    }
+
+    fn check(&self, tag: &Tag, path: &mut Vec<Segment>, violations: &mut Vec<Violation>) {
+        let mut report = |kind| violations.push(Violation { path: path.clone(), kind });
+
+        if let Some(expected) = self.kind.tag_kind() {
+            if tag.kind() != expected {
+                return report(ViolationKind::WrongKind { expected, found: tag.kind() })
+            }
+        }
+
+        let value = match *tag {
+            Tag::Byte(x) => Some(x as f64),
+            Tag::Short(x) => Some(x as f64),
+            Tag::Int(x) => Some(x as f64),
+            Tag::Long(x) => Some(x as f64),
+            Tag::Float(x) => Some(x as f64),
+            Tag::Double(x) => Some(x),
+            _ => None,
+        };
+        if let Some(value) = value {
+            let below = self.min.is_some_and(|min| value < min || value.is_nan());
+            let above = self.max.is_some_and(|max| value > max || value.is_nan());
+            if below || above {
+                report(ViolationKind::OutOfRange { value, min: self.min, max: self.max });
+            }
+        }
+
+        let len = match tag {
+            Tag::ByteArray(xs) => Some(xs.len()),
+            Tag::String(s) => Some(s.chars().count()),
+            Tag::List(_, xs) => Some(xs.len()),
+            Tag::IntArray(xs) => Some(xs.len()),
+            Tag::LongArray(xs) => Some(xs.len()),
+            _ => None,
+        };
+        if let Some(len) = len {
+            let short = self.min_len.is_some_and(|min| len < min);
+            let long = self.max_len.is_some_and(|max| len > max);
+            if short || long {
+                report(ViolationKind::BadLength { len, min: self.min_len, max: self.max_len });
+            }
+        }
+
+        match tag {
+            Tag::List(_, xs) => if let Some(element) = &self.element {
+                for (i, x) in xs.iter().enumerate() {
+                    path.push(Segment::Index(i));
+                    element.check(x, path, violations);
+                    path.pop();
+                }
+            },
+            Tag::Compound(m) => {
+                for (key, field) in &self.fields {
+                    path.push(Segment::Key(key.clone()));
+                    match m.get(key) {
+                        Some(x) => field.schema.check(x, path, violations),
+                        None if field.optional => (),
+                        None => violations.push(Violation { path: path.clone(), kind: ViolationKind::Missing }),
+                    }
+                    path.pop();
+                }
+                if self.closed {
+                    for key in m.keys().filter(|key| !self.fields.contains_key(*key)) {
+                        path.push(Segment::Key(key.clone()));
+                        violations.push(Violation { path: path.clone(), kind: ViolationKind::Unexpected });
+                        path.pop();
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// The layout of `level.dat` in alpha 1.2.6, with the player of a single
+    /// player world.
+    pub fn alpha_level() -> Self {
+        let data = Schema::compound()
+            .field("Time", Schema::new(Kind::Long).range(0.0, i64::MAX as f64))
+            .field("LastPlayed", Schema::new(Kind::Long))
+            .optional("Player", Schema::alpha_player())
+            .field("SpawnX", Schema::new(Kind::Int))
+            .field("SpawnY", Schema::new(Kind::Int).range(0.0, 127.0))
+            .field("SpawnZ", Schema::new(Kind::Int))
+            .field("SizeOnDisk", Schema::new(Kind::Long).range(0.0, i64::MAX as f64))
+            .field("RandomSeed", Schema::new(Kind::Long));
+
+        Schema::compound().field("Data", data)
+    }
+
+    /// The layout of a player in alpha 1.2.6, as saved in `players/*.dat`
+    /// and in `level.dat`.
+    pub fn alpha_player() -> Self {
+        let item = Schema::compound()
+            .field("id", Schema::new(Kind::Short).range(1.0, 2257.0))
+            .field("Count", Schema::new(Kind::Byte).range(0.0, 64.0))
+            .field("Slot", Schema::new(Kind::Byte).range(0.0, 103.0))
+            .field("Damage", Schema::new(Kind::Short));
+
+        Schema::compound()
+            .field("Pos", Schema::list(Schema::new(Kind::Double)).len(3, 3))
+            .field("Motion", Schema::list(Schema::new(Kind::Double)).len(3, 3))
+            .field("Rotation", Schema::list(Schema::new(Kind::Float)).len(2, 2))
+            .field("FallDistance", Schema::new(Kind::Float))
+            .field("Fire", Schema::new(Kind::Short))
+            .field("Air", Schema::new(Kind::Short).range(i16::MIN as f64, 300.0))
+            .field("OnGround", Schema::new(Kind::Byte).range(0.0, 1.0))
+            .field("AttackTime", Schema::new(Kind::Short))
+            .field("DeathTime", Schema::new(Kind::Short))
+            .field("Health", Schema::new(Kind::Short).range(i16::MIN as f64, 20.0))
+            .field("HurtTime", Schema::new(Kind::Short))
+            .field("Inventory", Schema::list(item))
+            .optional("Dimension", Schema::new(Kind::Int).range(-1.0, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nbt;
+    use crate::serialize::nbt::NBT;
+    use crate::serialize::nbt::schema::{Kind, Schema, ViolationKind};
+
+    #[test]
+    fn test_schema_alpha() {
+        let (player, _) = NBT::open("res/player.dat").unwrap();
+        assert!(Schema::alpha_player().validate(player.tag()).is_empty());
+
+        let level = nbt!({
+            Data: {
+                Time: 1000L,
+                LastPlayed: 0L,
+                SpawnX: 0s,
+                SpawnY: 200,
+                SizeOnDisk: 0L,
+                RandomSeed: 1L,
+                Player: {
+                    Pos: [0.0d, 64.0d],
+                    Inventory: [{ id: 1s, Count: 65b, Slot: 0B, Damage: 0s }],
+                },
+            },
+        });
+
+        let violations: Vec<String> = Schema::alpha_level()
+            .validate(&level)
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(violations, [
+            "length 2 out of range 3..=3 at Data.Player.Pos",
+            "missing entry at Data.Player.Motion",
+            "missing entry at Data.Player.Rotation",
+            "missing entry at Data.Player.FallDistance",
+            "missing entry at Data.Player.Fire",
+            "missing entry at Data.Player.Air",
+            "missing entry at Data.Player.OnGround",
+            "missing entry at Data.Player.AttackTime",
+            "missing entry at Data.Player.DeathTime",
+            "missing entry at Data.Player.Health",
+            "missing entry at Data.Player.HurtTime",
+            "value 65 out of range 0..=64 at Data.Player.Inventory[0].Count",
+            "expected tag kind 3, found tag kind 2 at Data.SpawnX",
+            "value 200 out of range 0..=127 at Data.SpawnY",
+            "missing entry at Data.SpawnZ",
+        ]);
+    }
+
+    #[test]
+    fn test_schema_json() {
+        let schema = Schema::from_json(r#"{
+            "type": "compound",
+            "closed": true,
+            "fields": {
+                "Health": { "type": "short", "min": 0, "max": 20 },
+                "Name": { "type": "string", "max_len": 4, "optional": true },
+                "Tags": { "type": "list", "element": { "type": "string" } }
+            }
+        }"#).unwrap();
+
+        assert!(schema.validate(&nbt!({ Health: 3s, Tags: [] })).is_empty());
+
+        let violations = schema.validate(&nbt!({ Health: 3s, Name: "Steve", Tags: [1], Extra: 1b }));
+        let kinds: Vec<_> = violations.iter().map(|v| v.kind.clone()).collect();
+        assert_eq!(kinds, [
+            ViolationKind::BadLength { len: 5, min: None, max: Some(4) },
+            ViolationKind::WrongKind { expected: 8, found: 3 },
+            ViolationKind::Unexpected,
+        ]);
+        assert_eq!(violations[0].to_string(), "length 5 out of range ..=4 at Name");
+
+        // the bundled schemas go through JSON unchanged
+        let json = serde_json::to_string(&Schema::alpha_level()).unwrap();
+        let schema = Schema::from_json(&json).unwrap();
+        assert_eq!(serde_json::to_string(&schema).unwrap(), json);
+
+        assert!(Schema::from_json(r#"{"type": "bool"}"#).is_err());
+        assert!(Schema::new(Kind::Any).validate(&nbt!(1.5f)).is_empty());
+    }
+}
@@ -0,0 +1,594 @@
+use std::io::Read;
+
+use ::serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use ::serde::forward_to_deserialize_any;
+
+use crate::serialize::DataInput;
+use crate::serialize::nbt::{Encoding, Limits, Tag};
+use crate::serialize::nbt::limits::Budget;
+use crate::serialize::nbt::serde::{Error, Result};
+
+const BYTE: i8 = 0x01;
+const SHORT: i8 = 0x02;
+const INT: i8 = 0x03;
+const LONG: i8 = 0x04;
+const BYTE_ARRAY: i8 = 0x07;
+const STRING: i8 = 0x08;
+const COMPOUND: i8 = 0x0a;
+
+fn error<T>(msg: &str) -> Result<T> {
+    Err(Error::Message(msg.into()))
+}
+
+pub(super) fn from_reader<R: Read, T: DeserializeOwned>(input: &mut DataInput<R>, limits: &Limits) -> Result<T> {
+    if input.read_byte()? != COMPOUND {
+        return error("root tag must be a compound")
+    }
+
+    // the key of the root compound is discarded
+    input.read_utf()?;
+
+    let mut reader = Reader { input, budget: Budget::new(limits, 0) };
+    T::deserialize(Deserializer { reader: &mut reader, kind: COMPOUND })
+}
+
+// decoding straight from a `DataInput`
+
+// the input, and what has been spent decoding it so far
+struct Reader<'a, R> {
+    input: &'a mut DataInput<R>,
+    budget: Budget<'a>,
+}
+
+impl<R: Read> Reader<'_, R> {
+    fn read_len(&mut self) -> Result<usize> {
+        match self.input.read_int()? {
+            len if len < 0 => error("negative length"),
+            len => {
+                self.budget.check_len(len as usize, self.input)?;
+                Ok(len as usize)
+            },
+        }
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<i8>> {
+        let len = self.read_len()?;
+        self.budget.charge(len, self.input)?;
+        Ok(self.input.read_bytes(len)?)
+    }
+
+    fn read_utf(&mut self) -> Result<String> {
+        let s = self.input.read_utf()?;
+        self.budget.charge(s.len(), self.input)?;
+        Ok(s)
+    }
+
+    fn skip(&mut self, kind: i8) -> Result<()> {
+        Ok(Tag::skip_from(self.input, kind, Encoding::BigEndian, &mut self.budget)?)
+    }
+}
+
+struct Deserializer<'a, 'b, R> {
+    reader: &'a mut Reader<'b, R>,
+    kind: i8,
+}
+
+struct List<'a, 'b, R> {
+    reader: &'a mut Reader<'b, R>,
+    kind: i8,
+    remaining: usize,
+}
+
+struct Compound<'a, 'b, R> {
+    reader: &'a mut Reader<'b, R>,
+    kind: i8,
+    done: bool,
+}
+
+struct Variant<'a, 'b, R> {
+    reader: &'a mut Reader<'b, R>,
+    kind: i8,
+    key: String,
+}
+
+impl<R: Read> Deserializer<'_, '_, R> {
+    fn list<'de, V: Visitor<'de>>(self, kind: i8, visitor: V) -> Result<V::Value> {
+        self.reader.budget.enter(self.reader.input)?;
+        let remaining = self.reader.read_len()?;
+        let mut list = List { reader: &mut *self.reader, kind, remaining };
+        let value = visitor.visit_seq(&mut list)?;
+        if list.remaining > 0 {
+            return error("list has more elements than expected")
+        }
+        self.reader.budget.leave();
+        Ok(value)
+    }
+
+    fn compound<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.reader.budget.enter(self.reader.input)?;
+        let mut compound = Compound { reader: &mut *self.reader, kind: 0, done: false };
+        let value = visitor.visit_map(&mut compound)?;
+        // skip any entries the visitor did not ask for
+        if !compound.done {
+            loop {
+                let kind = self.reader.input.read_byte()?;
+                if kind == 0 {
+                    break
+                }
+                self.reader.input.read_utf()?;
+                self.reader.skip(kind)?;
+            }
+        }
+        self.reader.budget.leave();
+        Ok(value)
+    }
+}
+
+macro_rules! deserialize_unsigned {
+    ($($method:ident => $kind:expr, $read:ident, $visit:ident: $ty:ty;)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            if self.kind == $kind {
+                visitor.$visit(self.reader.input.$read()? as $ty)
+            } else {
+                self.deserialize_any(visitor)
+            }
+        }
+    )*}
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for Deserializer<'_, '_, R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let input = &mut *self.reader.input;
+        match self.kind {
+            0x01 => visitor.visit_i8(input.read_byte()?),
+            0x02 => visitor.visit_i16(input.read_short()?),
+            0x03 => visitor.visit_i32(input.read_int()?),
+            0x04 => visitor.visit_i64(input.read_long()?),
+            0x05 => visitor.visit_f32(input.read_float()?),
+            0x06 => visitor.visit_f64(input.read_double()?),
+            0x07 => self.list(BYTE, visitor),
+            0x08 => visitor.visit_string(self.reader.read_utf()?),
+            0x09 => {
+                let kind = input.read_byte()?;
+                self.list(kind, visitor)
+            },
+            0x0a => self.compound(visitor),
+            0x0b => self.list(INT, visitor),
+            0x0c => self.list(LONG, visitor),
+            _ => error("invalid tag kind"),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.kind == BYTE {
+            visitor.visit_bool(self.reader.input.read_byte()? != 0)
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    deserialize_unsigned! {
+        deserialize_u8 => BYTE, read_byte, visit_u8: u8;
+        deserialize_u16 => SHORT, read_short, visit_u16: u16;
+        deserialize_u32 => INT, read_int, visit_u32: u32;
+        deserialize_u64 => LONG, read_long, visit_u64: u64;
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.kind == BYTE_ARRAY {
+            let bytes = self.reader.read_bytes()?;
+            visitor.visit_byte_buf(bytes.into_iter().map(|b| b as u8).collect())
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // missing entries are handled by the compound visitor
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.reader.skip(self.kind)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.kind {
+            STRING => {
+                let variant = self.reader.read_utf()?;
+                visitor.visit_enum(variant.into_deserializer())
+            },
+            COMPOUND => {
+                self.reader.budget.enter(self.reader.input)?;
+                let kind = self.reader.input.read_byte()?;
+                if kind == 0 {
+                    return error("expected an enum variant")
+                }
+                let key = self.reader.read_utf()?;
+                let value = visitor.visit_enum(Variant { reader: &mut *self.reader, kind, key })?;
+                if self.reader.input.read_byte()? != 0 {
+                    return error("enum variants must have a single entry")
+                }
+                self.reader.budget.leave();
+                Ok(value)
+            },
+            _ => error("expected an enum variant"),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for List<'_, '_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None)
+        }
+        self.remaining -= 1;
+        seed.deserialize(Deserializer { reader: &mut *self.reader, kind: self.kind }).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for Compound<'_, '_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.done {
+            return Ok(None)
+        }
+        self.kind = self.reader.input.read_byte()?;
+        if self.kind == 0 {
+            self.done = true;
+            return Ok(None)
+        }
+        let key = self.reader.read_utf()?;
+        seed.deserialize(KeyDeserializer(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(Deserializer { reader: &mut *self.reader, kind: self.kind })
+    }
+}
+
+impl<'de, 'a, 'b, R: Read> de::EnumAccess<'de> for Variant<'a, 'b, R> {
+    type Error = Error;
+    type Variant = Deserializer<'a, 'b, R>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(KeyDeserializer(self.key))?;
+        Ok((variant, Deserializer { reader: self.reader, kind: self.kind }))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for Deserializer<'_, '_, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.reader.skip(self.kind)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+// decoding from an in-memory `Tag`
+
+struct TagList<I> {
+    iter: I,
+}
+
+struct TagCompound {
+    iter: indexmap::map::IntoIter<String, Tag>,
+    value: Option<Tag>,
+}
+
+struct TagVariant {
+    key: String,
+    value: Tag,
+}
+
+fn visit_list<'de, I, V>(iter: I, visitor: V) -> Result<V::Value>
+where
+    I: ExactSizeIterator<Item = Tag>,
+    V: Visitor<'de>,
+{
+    let mut list = TagList { iter };
+    let value = visitor.visit_seq(&mut list)?;
+    if list.iter.len() > 0 {
+        return error("list has more elements than expected")
+    }
+    Ok(value)
+}
+
+macro_rules! deserialize_tag_unsigned {
+    ($($method:ident => $variant:ident, $visit:ident: $ty:ty;)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self {
+                Tag::$variant(x) => visitor.$visit(x as $ty),
+                tag => tag.deserialize_any(visitor),
+            }
+        }
+    )*}
+}
+
+impl<'de> de::Deserializer<'de> for Tag {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Tag::End => visitor.visit_unit(),
+            Tag::Byte(x) => visitor.visit_i8(x),
+            Tag::Short(x) => visitor.visit_i16(x),
+            Tag::Int(x) => visitor.visit_i32(x),
+            Tag::Long(x) => visitor.visit_i64(x),
+            Tag::Float(x) => visitor.visit_f32(x),
+            Tag::Double(x) => visitor.visit_f64(x),
+            Tag::ByteArray(xs) => visit_list(xs.into_iter().map(Tag::Byte), visitor),
+            Tag::String(s) => visitor.visit_string(s),
+            Tag::List(_, xs) => visit_list(xs.into_iter(), visitor),
+            Tag::Compound(m) => {
+                let mut compound = TagCompound { iter: m.into_iter(), value: None };
+                visitor.visit_map(&mut compound)
+            },
+            Tag::IntArray(xs) => visit_list(xs.into_iter().map(Tag::Int), visitor),
+            Tag::LongArray(xs) => visit_list(xs.into_iter().map(Tag::Long), visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Tag::Byte(x) => visitor.visit_bool(x != 0),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_tag_unsigned! {
+        deserialize_u8 => Byte, visit_u8: u8;
+        deserialize_u16 => Short, visit_u16: u16;
+        deserialize_u32 => Int, visit_u32: u32;
+        deserialize_u64 => Long, visit_u64: u64;
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Tag::ByteArray(xs) => visitor.visit_byte_buf(xs.into_iter().map(|b| b as u8).collect()),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Tag::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Tag::Compound(m) => {
+                if m.len() != 1 {
+                    return error("enum variants must have a single entry")
+                }
+                let (key, value) = m.into_iter().next().unwrap();
+                visitor.visit_enum(TagVariant { key, value })
+            },
+            _ => error("expected an enum variant"),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de, I: Iterator<Item = Tag>> de::SeqAccess<'de> for TagList<I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.iter.next().map(|tag| seed.deserialize(tag)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.iter.size_hint().1
+    }
+}
+
+impl<'de> de::MapAccess<'de> for TagCompound {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer(key)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => error("map value requested before its key"),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for TagVariant {
+    type Error = Error;
+    type Variant = Tag;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Tag)> {
+        let variant = seed.deserialize(KeyDeserializer(self.key))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Tag {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+// compound keys are strings, which may stand for integer map keys
+struct KeyDeserializer(String);
+
+macro_rules! deserialize_key_parsed {
+    ($($method:ident => $visit:ident: $ty:ty;)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self.0.parse::<$ty>() {
+                Ok(x) => visitor.$visit(x),
+                Err(_) => visitor.visit_string(self.0),
+            }
+        }
+    )*}
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_key_parsed! {
+        deserialize_i8 => visit_i8: i8;
+        deserialize_i16 => visit_i16: i16;
+        deserialize_i32 => visit_i32: i32;
+        deserialize_i64 => visit_i64: i64;
+        deserialize_u8 => visit_u8: u8;
+        deserialize_u16 => visit_u16: u16;
+        deserialize_u32 => visit_u32: u32;
+        deserialize_u64 => visit_u64: u64;
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
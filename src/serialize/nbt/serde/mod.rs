@@ -0,0 +1,305 @@
+//! Map Rust types to and from NBT with [serde](https://serde.rs).
+//!
+//! Integers and floats map to the tag of the same width (unsigned integers
+//! are stored bit for bit in their signed counterpart), `bool` maps to a
+//! byte, and strings and chars to string tags. Sequences of bytes, such as
+//! `Vec<i8>`, become byte arrays, every other sequence becomes a list, and
+//! structs and maps become compounds. Empty sequences, whose elements cannot
+//! be told apart, become empty byte arrays too, except in a list of lists,
+//! and read back as any sequence. A `None` field is left out of its
+//! compound, unit variants are written as strings, and any other enum variant
+//! as a compound with a single entry keyed by the variant name.
+
+use std::fmt;
+use std::error;
+use std::io::{self, Read, Write};
+
+use ::serde::{de::DeserializeOwned, Serialize};
+
+use crate::serialize::{DataInput, DataOutput};
+use crate::serialize::nbt::{self, Limits, NBT, Tag};
+
+mod de;
+mod ser;
+
+/// An error raised while mapping a Rust value to or from NBT.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying input or output failed.
+    Io(io::Error),
+    /// The input is not valid NBT.
+    Nbt(nbt::Error),
+    /// The value could not be mapped.
+    Message(String),
+}
+
+/// The result of mapping a Rust value to or from NBT.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Nbt(e) => e.fmt(f),
+            Error::Message(s) => f.write_str(s),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Nbt(e) => Some(e),
+            Error::Message(_) => None,
+        }
+    }
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<nbt::Error> for Error {
+    fn from(e: nbt::Error) -> Self {
+        Error::Nbt(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::Nbt(e) => e.into(),
+            Error::Message(s) => io::Error::new(io::ErrorKind::InvalidData, s),
+        }
+    }
+}
+
+/// Encodes `value` as an NBT compound named `key` into a [`DataOutput`].
+pub fn to_writer<W, T>(output: &mut DataOutput<W>, key: &str, value: &T) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    ser::to_writer(output, key, value)
+}
+
+/// Converts `value` into a [`Tag`].
+pub fn to_tag<T: ?Sized + Serialize>(value: &T) -> Result<Tag> {
+    ser::to_tag(value)
+}
+
+/// Converts `value` into an [`NBT`] compound named `key`.
+pub fn to_nbt<T: ?Sized + Serialize>(key: &str, value: &T) -> Result<NBT> {
+    NBT::new(key, to_tag(value)?)
+        .ok_or_else(|| Error::Message("root tag must be a compound".into()))
+}
+
+/// Decodes a value from an NBT compound read from a [`DataInput`], within
+/// the default [`Limits`].
+///
+/// The key of the root compound is discarded.
+pub fn from_reader<R: Read, T: DeserializeOwned>(input: &mut DataInput<R>) -> Result<T> {
+    de::from_reader(input, &Limits::default())
+}
+
+/// Decodes a value from an NBT compound read from a [`DataInput`], failing
+/// as soon as the input goes beyond `limits`.
+pub fn from_reader_with_limits<R: Read, T: DeserializeOwned>(input: &mut DataInput<R>, limits: &Limits) -> Result<T> {
+    de::from_reader(input, limits)
+}
+
+/// Converts a [`Tag`] into a value.
+pub fn from_tag<T: DeserializeOwned>(tag: Tag) -> Result<T> {
+    T::deserialize(tag)
+}
+
+/// Converts the compound of an [`NBT`] into a value.
+pub fn from_nbt<T: DeserializeOwned>(nbt: NBT) -> Result<T> {
+    from_tag(nbt.tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::collections::HashMap;
+
+    use flate2::bufread::GzDecoder;
+    use serde::{Deserialize, Serialize};
+
+    use crate::serialize::{DataInput, DataOutput};
+    use crate::serialize::nbt::{NBT, Tag, ErrorKind, Limits, MAX_DEPTH};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Item {
+        #[serde(rename = "id")]
+        id: i16,
+        count: u8,
+        slot: i8,
+        damage: i16,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Player {
+        pos: Vec<f64>,
+        rotation: (f32, f32),
+        health: i16,
+        on_ground: bool,
+        inventory: Vec<Item>,
+        dimension: Option<i32>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Square(u32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Node {
+        child: Option<Box<Node>>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Everything {
+        bytes: Vec<i8>,
+        empty: Vec<i32>,
+        nested: Vec<Vec<i64>>,
+        blobs: Vec<Vec<u8>>,
+        shapes: Vec<Shape>,
+        map: HashMap<String, String>,
+        name: Option<String>,
+        c: char,
+    }
+
+    fn everything() -> Everything {
+        Everything {
+            bytes: vec![1, -2, 3],
+            empty: vec![],
+            nested: vec![vec![1, 2], vec![], vec![3]],
+            blobs: vec![vec![], vec![1, 2], vec![]],
+            shapes: vec![Shape::Square(3), Shape::Rect { w: 1, h: 2 }],
+            map: vec![("a".into(), "b".into())].into_iter().collect(),
+            name: None,
+            c: 'x',
+        }
+    }
+
+    #[test]
+    fn test_serde_player() {
+        let f = File::open("res/player.dat").unwrap();
+        let mut input = DataInput::new(GzDecoder::new(BufReader::new(f)));
+
+        let player: Player = super::from_reader(&mut input).unwrap();
+        assert_eq!(player.pos.len(), 3);
+        assert_eq!(player.inventory[0].id, 3);
+
+        let nbt = super::to_nbt("", &player).unwrap();
+        assert_eq!(super::from_nbt::<Player>(nbt).unwrap(), player);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let value = everything();
+
+        let mut output = DataOutput::new(Vec::new());
+        super::to_writer(&mut output, "root", &value).unwrap();
+
+        let buf = output.into_inner();
+        let decoded: Everything = super::from_reader(&mut DataInput::new(&buf[..])).unwrap();
+        assert_eq!(decoded, value);
+
+        let nbt = NBT::read_from(&mut DataInput::new(&buf[..])).unwrap();
+        match nbt.tag() {
+            Tag::Compound(m) => {
+                assert!(matches!(m["bytes"], Tag::ByteArray(_)));
+                // empty sequences are byte arrays too, unless among lists
+                assert_eq!(m["empty"], Tag::ByteArray(vec![]));
+                assert_eq!(m["blobs"], Tag::List(0x07, vec![Tag::ByteArray(vec![]), Tag::ByteArray(vec![1, 2]), Tag::ByteArray(vec![])]));
+                assert!(matches!(&m["nested"], Tag::List(0x09, xs) if xs[1] == Tag::List(0, vec![])));
+                assert!(matches!(m["shapes"], Tag::List(0x0a, _)));
+                assert!(!m.contains_key("name"));
+            },
+            _ => panic!("not a compound tag"),
+        }
+        assert_eq!(super::from_nbt::<Everything>(nbt).unwrap(), value);
+
+        let tag = super::to_tag(&value).unwrap();
+        assert_eq!(&tag, NBT::read_from(&mut DataInput::new(&buf[..])).unwrap().tag());
+        assert_eq!(super::from_tag::<Everything>(tag).unwrap(), value);
+    }
+
+    #[test]
+    fn test_serde_errors() {
+        let mut output = DataOutput::new(Vec::new());
+        assert!(super::to_writer(&mut output, "", &5i32).is_err());
+        assert!(super::to_tag(&vec![Some(1), None]).is_err());
+        assert!(super::to_tag(&(1i32, "two")).is_err());
+        assert!(super::to_tag(&vec![vec![], vec![1u8]]).is_ok());
+        assert!(super::to_tag(&vec![vec![Vec::<u8>::new()], vec![vec![1u8]]]).is_ok());
+        assert!(super::to_tag(&(1u8, Vec::<u8>::new())).is_err());
+    }
+
+    #[test]
+    fn test_serde_limits() {
+        fn depth_exceeded(e: super::Error) -> Option<usize> {
+            match e {
+                super::Error::Nbt(e) => match e.kind() {
+                    ErrorKind::DepthExceeded(max) => Some(*max),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+
+        // compounds nested too deep, whether decoded or skipped
+        let mut output = DataOutput::new(Vec::new());
+        output.write_byte(0x0a).unwrap();
+        output.write_utf("").unwrap();
+        for _ in 0..MAX_DEPTH {
+            output.write_byte(0x0a).unwrap();
+            output.write_utf("child").unwrap();
+        }
+
+        let buf = output.into_inner();
+        let e = super::from_reader::<_, Node>(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert_eq!(depth_exceeded(e), Some(MAX_DEPTH));
+        let e = super::from_reader::<_, HashMap<String, ()>>(&mut DataInput::new(&buf[..])).unwrap_err();
+        assert_eq!(depth_exceeded(e), Some(MAX_DEPTH));
+
+        // a shallow tree fits in the default limits, but not in tighter ones
+        let node = Node { child: Some(Box::new(Node { child: None })) };
+        let mut output = DataOutput::new(Vec::new());
+        super::to_writer(&mut output, "", &node).unwrap();
+
+        let buf = output.into_inner();
+        assert_eq!(super::from_reader::<_, Node>(&mut DataInput::new(&buf[..])).unwrap(), node);
+
+        let limits = Limits { max_depth: 1, ..Limits::default() };
+        let e = super::from_reader_with_limits::<_, Node>(&mut DataInput::new(&buf[..]), &limits).unwrap_err();
+        assert_eq!(depth_exceeded(e), Some(1));
+
+        let limits = Limits { max_bytes: 4, ..Limits::default() };
+        let e = super::from_reader_with_limits::<_, Node>(&mut DataInput::new(&buf[..]), &limits).unwrap_err();
+        assert!(matches!(e, super::Error::Nbt(ref e) if matches!(e.kind(), ErrorKind::SizeExceeded(4))));
+    }
+}
@@ -0,0 +1,972 @@
+use std::io::Write;
+
+use ::serde::ser::{self, Serialize};
+
+use crate::serialize::DataOutput;
+use crate::serialize::nbt::{self, Tag};
+use crate::serialize::nbt::serde::{Error, Result};
+
+const BYTE: i8 = 0x01;
+const BYTE_ARRAY: i8 = 0x07;
+const LIST: i8 = 0x09;
+const COMPOUND: i8 = 0x0a;
+
+fn error<T>(msg: &str) -> Result<T> {
+    Err(Error::Message(msg.into()))
+}
+
+fn list_len(len: Option<usize>) -> Result<usize> {
+    match len {
+        Some(len) if len <= i32::MAX as usize => Ok(len),
+        Some(_) => error("sequence is too long"),
+        None => error("sequence length must be known"),
+    }
+}
+
+pub(super) fn to_writer<W, T>(output: &mut DataOutput<W>, key: &str, value: &T) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    value.serialize(Serializer { out: output, header: Header::Root(key) })
+}
+
+pub(super) fn to_tag<T: ?Sized + Serialize>(value: &T) -> Result<Tag> {
+    value
+        .serialize(TagSerializer)?
+        .map_or_else(|| error("cannot serialize a missing value"), Ok)
+}
+
+// The kind of a tag is only known once its value starts being serialized,
+// and the kind of a list once its first element is, so the header of each
+// value is written lazily, by the value itself.
+enum Header<'a, W> {
+    // the root compound
+    Root(&'a str),
+    // an entry of a compound
+    Named(&'a str),
+    // an element of a list
+    Element(&'a mut (dyn Pending<W> + 'a)),
+}
+
+trait Pending<W> {
+    fn resolve(&mut self, out: &mut DataOutput<W>, kind: i8) -> Result<()>;
+    // an empty sequence, which takes the kind of the other elements
+    fn resolve_empty(&mut self, out: &mut DataOutput<W>) -> Result<()>;
+}
+
+impl<W: Write> Header<'_, W> {
+    fn resolve(&mut self, out: &mut DataOutput<W>, kind: i8) -> Result<()> {
+        match self {
+            Header::Root(key) => {
+                if kind != COMPOUND {
+                    return error("root tag must be a compound")
+                }
+                out.write_byte(kind)?;
+                Ok(out.write_utf(key)?)
+            },
+            Header::Named(key) => {
+                out.write_byte(kind)?;
+                Ok(out.write_utf(key)?)
+            },
+            Header::Element(list) => list.resolve(out, kind),
+        }
+    }
+
+    // an empty sequence has no element to tell its kind, so it is written as
+    // an empty byte array, unless it is in a list of other sequences
+    fn resolve_empty(&mut self, out: &mut DataOutput<W>) -> Result<()> {
+        match self {
+            Header::Element(list) => list.resolve_empty(out),
+            _ => {
+                self.resolve(out, BYTE_ARRAY)?;
+                Ok(out.write_int(0)?)
+            },
+        }
+    }
+}
+
+// writes an empty sequence in a list of `kind`
+fn write_empty<W: Write>(out: &mut DataOutput<W>, kind: i8) -> Result<()> {
+    match kind {
+        BYTE_ARRAY => Ok(out.write_int(0)?),
+        LIST => {
+            out.write_byte(0x00)?;
+            Ok(out.write_int(0)?)
+        },
+        _ => error("list elements must be of the same kind"),
+    }
+}
+
+struct ListState<'a, W> {
+    header: Header<'a, W>,
+    len: usize,
+    kind: Option<i8>,
+    // empty sequences before the kind is known
+    empties: usize,
+}
+
+impl<W: Write> Pending<W> for ListState<'_, W> {
+    fn resolve(&mut self, out: &mut DataOutput<W>, kind: i8) -> Result<()> {
+        match self.kind {
+            Some(k) if k == kind => Ok(()),
+            Some(_) => error("list elements must be of the same kind"),
+            None => {
+                // a list of bytes is encoded as a byte array
+                if kind == BYTE {
+                    self.header.resolve(out, BYTE_ARRAY)?;
+                } else {
+                    self.header.resolve(out, LIST)?;
+                    out.write_byte(kind)?;
+                }
+                out.write_int(self.len as i32)?;
+                self.kind = Some(kind);
+
+                for _ in 0..self.empties {
+                    write_empty(out, kind)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn resolve_empty(&mut self, out: &mut DataOutput<W>) -> Result<()> {
+        match self.kind {
+            Some(kind) => write_empty(out, kind),
+            None => {
+                self.empties += 1;
+                Ok(())
+            },
+        }
+    }
+}
+
+struct Serializer<'a, W> {
+    out: &'a mut DataOutput<W>,
+    header: Header<'a, W>,
+}
+
+struct Seq<'a, W> {
+    out: &'a mut DataOutput<W>,
+    state: ListState<'a, W>,
+    count: usize,
+    // compounds to close after the list
+    ends: usize,
+}
+
+struct Compound<'a, W> {
+    out: &'a mut DataOutput<W>,
+    key: Option<String>,
+    // compounds to close, including this one
+    ends: usize,
+}
+
+impl<'a, W: Write> Serializer<'a, W> {
+    fn seq(self, len: Option<usize>) -> Result<Seq<'a, W>> {
+        let len = list_len(len)?;
+        Ok(Seq {
+            out: self.out,
+            state: ListState { header: self.header, len, kind: None, empties: 0 },
+            count: 0,
+            ends: 0,
+        })
+    }
+
+    fn compound(mut self) -> Result<Compound<'a, W>> {
+        self.header.resolve(self.out, COMPOUND)?;
+        Ok(Compound { out: self.out, key: None, ends: 1 })
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for Serializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Seq<'a, W>;
+    type SerializeTuple = Seq<'a, W>;
+    type SerializeTupleStruct = Seq<'a, W>;
+    type SerializeTupleVariant = Seq<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_i8(mut self, v: i8) -> Result<()> {
+        self.header.resolve(self.out, 0x01)?;
+        Ok(self.out.write_byte(v)?)
+    }
+
+    fn serialize_i16(mut self, v: i16) -> Result<()> {
+        self.header.resolve(self.out, 0x02)?;
+        Ok(self.out.write_short(v)?)
+    }
+
+    fn serialize_i32(mut self, v: i32) -> Result<()> {
+        self.header.resolve(self.out, 0x03)?;
+        Ok(self.out.write_int(v)?)
+    }
+
+    fn serialize_i64(mut self, v: i64) -> Result<()> {
+        self.header.resolve(self.out, 0x04)?;
+        Ok(self.out.write_long(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_i16(v as i16)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(mut self, v: f32) -> Result<()> {
+        self.header.resolve(self.out, 0x05)?;
+        Ok(self.out.write_float(v)?)
+    }
+
+    fn serialize_f64(mut self, v: f64) -> Result<()> {
+        self.header.resolve(self.out, 0x06)?;
+        Ok(self.out.write_double(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(mut self, v: &str) -> Result<()> {
+        self.header.resolve(self.out, 0x08)?;
+        Ok(self.out.write_utf(v)?)
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<()> {
+        let len = list_len(Some(v.len()))?;
+        self.header.resolve(self.out, BYTE_ARRAY)?;
+        self.out.write_int(len as i32)?;
+        for &b in v {
+            self.out.write_byte(b as i8)?;
+        }
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        match self.header {
+            // missing entries are left out of their compound
+            Header::Named(_) => Ok(()),
+            _ => error("cannot serialize a missing value outside of a compound"),
+        }
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        ser::SerializeStruct::end(self.compound()?)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let mut compound = self.compound()?;
+        ser::SerializeStruct::serialize_field(&mut compound, variant, value)?;
+        ser::SerializeStruct::end(compound)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Seq<'a, W>> {
+        self.seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'a, W>> {
+        self.seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Seq<'a, W>> {
+        self.seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Seq<'a, W>> {
+        let compound = self.compound()?;
+        let mut seq = Serializer { out: compound.out, header: Header::Named(variant) }
+            .seq(Some(len))?;
+        seq.ends = 1;
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.compound()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a, W>> {
+        self.compound()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        let compound = self.compound()?;
+        let mut inner = Serializer { out: compound.out, header: Header::Named(variant) }
+            .compound()?;
+        inner.ends = 2;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Seq<'_, W> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        if self.count > self.state.len {
+            return error("sequence is longer than its declared length")
+        }
+        value.serialize(Serializer {
+            out: &mut *self.out,
+            header: Header::Element(&mut self.state),
+        })
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if self.count != self.state.len {
+            return error("sequence is shorter than its declared length")
+        }
+        if self.state.kind.is_none() {
+            if self.count == 0 {
+                self.state.header.resolve_empty(self.out)?;
+            } else {
+                // a list of empty sequences, which are byte arrays then
+                self.state.resolve(self.out, BYTE_ARRAY)?;
+            }
+        }
+        for _ in 0..self.ends {
+            self.out.write_byte(0x00)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Seq<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Seq<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Seq<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Seq<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> Compound<'_, W> {
+    fn entry<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        value.serialize(Serializer { out: &mut *self.out, header: Header::Named(key) })
+    }
+
+    fn finish(self) -> Result<()> {
+        for _ in 0..self.ends {
+            self.out.write_byte(0x00)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => self.entry(&key, value),
+            None => error("map value serialized before its key"),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+// building an in-memory `Tag`, where `None` stands for a missing value
+
+struct TagSerializer;
+
+struct TagSeq {
+    list: Vec<Tag>,
+    // the variant wrapping the list, if any
+    variant: Option<&'static str>,
+}
+
+struct TagCompound {
+    m: nbt::Compound,
+    key: Option<String>,
+    // the variant wrapping the compound, if any
+    variant: Option<&'static str>,
+}
+
+fn wrap_variant(variant: Option<&'static str>, tag: Tag) -> Tag {
+    match variant {
+        Some(variant) => {
+            let mut m = nbt::Compound::new();
+            m.insert(variant.into(), tag);
+            Tag::Compound(m)
+        },
+        None => tag,
+    }
+}
+
+impl ser::Serializer for TagSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    type SerializeSeq = TagSeq;
+    type SerializeTuple = TagSeq;
+    type SerializeTupleStruct = TagSeq;
+    type SerializeTupleVariant = TagSeq;
+    type SerializeMap = TagCompound;
+    type SerializeStruct = TagCompound;
+    type SerializeStructVariant = TagCompound;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Byte(v as i8)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Long(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Byte(v as i8)))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Short(v as i16)))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Int(v as i32)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Long(v as i64)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<Tag>> {
+        Ok(Some(Tag::String(v.into())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<Tag>> {
+        Ok(Some(Tag::String(v.into())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Option<Tag>> {
+        list_len(Some(v.len()))?;
+        Ok(Some(Tag::ByteArray(v.iter().map(|&b| b as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Option<Tag>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Option<Tag>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Compound(nbt::Compound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Tag>> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Option<Tag>> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Option<Tag>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Option<Tag>> {
+        let mut m = nbt::Compound::new();
+        if let Some(tag) = value.serialize(self)? {
+            m.insert(variant.into(), tag);
+        }
+        Ok(Some(Tag::Compound(m)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<TagSeq> {
+        let list = Vec::with_capacity(len.unwrap_or(0));
+        Ok(TagSeq { list, variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<TagSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<TagSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<TagSeq> {
+        let list = Vec::with_capacity(len);
+        Ok(TagSeq { list, variant: Some(variant) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<TagCompound> {
+        Ok(TagCompound { m: nbt::Compound::new(), key: None, variant: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<TagCompound> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<TagCompound> {
+        Ok(TagCompound { m: nbt::Compound::new(), key: None, variant: Some(variant) })
+    }
+}
+
+impl TagSeq {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match value.serialize(TagSerializer)? {
+            Some(tag) => self.list.push(tag),
+            None => return error("cannot serialize a missing value outside of a compound"),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Tag>> {
+        list_len(Some(self.list.len()))?;
+
+        // empty sequences are empty byte arrays, as they have no element to
+        // tell their kind, unless the other elements are lists
+        let is_empty = |tag: &Tag| matches!(tag, Tag::ByteArray(xs) if xs.is_empty());
+        let kind = self.list.iter().find(|tag| !is_empty(tag)).map_or(BYTE_ARRAY, Tag::kind);
+
+        let mut list = Vec::with_capacity(self.list.len());
+        for tag in self.list {
+            list.push(match tag {
+                tag if tag.kind() == kind => tag,
+                tag if kind == LIST && is_empty(&tag) => Tag::List(0x00, Vec::new()),
+                _ => return error("list elements must be of the same kind"),
+            });
+        }
+
+        // a list of bytes is encoded as a byte array
+        let tag = match kind {
+            BYTE => Tag::ByteArray(list
+                .into_iter()
+                .map(|tag| match tag {
+                    Tag::Byte(b) => b,
+                    _ => unreachable!(),
+                })
+                .collect()),
+            _ if list.is_empty() => Tag::ByteArray(Vec::new()),
+            _ => Tag::list(list),
+        };
+
+        Ok(Some(wrap_variant(self.variant, tag)))
+    }
+}
+
+impl ser::SerializeSeq for TagSeq {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for TagSeq {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for TagSeq {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for TagSeq {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl TagCompound {
+    fn entry<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<()> {
+        if let Some(tag) = value.serialize(TagSerializer)? {
+            self.m.insert(key, tag);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Tag>> {
+        Ok(Some(wrap_variant(self.variant, Tag::Compound(self.m))))
+    }
+}
+
+impl ser::SerializeMap for TagCompound {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => self.entry(key, value),
+            None => error("map value serialized before its key"),
+        }
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for TagCompound {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.entry(key.into(), value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for TagCompound {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.entry(key.into(), value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        self.finish()
+    }
+}
+
+// compound keys are strings, but integer map keys are accepted as well
+struct KeySerializer;
+
+macro_rules! key_to_string {
+    ($($method:ident: $ty:ty),*) => {$(
+        fn $method(self, v: $ty) -> Result<String> {
+            Ok(v.to_string())
+        }
+    )*}
+}
+
+macro_rules! key_unsupported {
+    ($($method:ident: $ty:ty),*) => {$(
+        fn $method(self, _v: $ty) -> Result<String> {
+            error("compound keys must be strings")
+        }
+    )*}
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    key_to_string! {
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_char: char, serialize_str: &str
+    }
+
+    key_unsupported! {
+        serialize_bool: bool, serialize_f32: f32, serialize_f64: f64, serialize_bytes: &[u8]
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        error("compound keys must be strings")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        error("compound keys must be strings")
+    }
+}
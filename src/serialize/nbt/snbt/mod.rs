@@ -0,0 +1,733 @@
+//! Read and write [stringified NBT](https://minecraft.fandom.com/wiki/NBT_format#SNBT_format).
+//!
+//! Every numeric tag carries a suffix naming its width (`1b`, `2s`, `3`,
+//! `4L`, `1.5f`, `2.5d`), arrays are written as `[B; 1b, 2b]`, `[I; 1, 2]`
+//! and `[L; 1L, 2L]`, empty lists of a kind as `[10;]`, and strings are
+//! always quoted, so a [`Tag`] printed with [`fmt::Display`] parses back
+//! into the very same tag. Use the alternate flag (`{:#}`) to pretty print
+//! a tag over multiple lines.
+//!
+//! Lists and compounds may be nested as deep as the default
+//! [`Limits::max_depth`](crate::serialize::nbt::Limits::max_depth).
+
+use std::fmt;
+use std::error;
+use std::str::FromStr;
+
+use crate::serialize::nbt::{Compound, Limits, NBT, Tag};
+
+/// An error raised while parsing SNBT, along with its position in the input.
+#[derive(Clone, Debug)]
+pub struct Error {
+    offset: usize,
+    line: usize,
+    column: usize,
+    message: &'static str,
+}
+
+impl Error {
+    fn new(src: &str, offset: usize, message: &'static str) -> Self {
+        let before = &src[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .map(|s| s.chars().count())
+            .unwrap_or(0) + 1;
+        Self { offset, line, column, message }
+    }
+
+    /// The byte offset in the input where the error occurred.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The line where the error occurred, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column where the error occurred, in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// A description of the error.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl error::Error for Error {}
+
+impl FromStr for Tag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Tag, Error> {
+        let mut parser = Parser::new(s);
+        let tag = parser.parse_tag()?;
+        parser.finish()?;
+        Ok(tag)
+    }
+}
+
+impl FromStr for NBT {
+    type Err = Error;
+
+    /// Parses a compound, optionally preceded by its key, as in `Level: {...}`.
+    fn from_str(s: &str) -> Result<NBT, Error> {
+        let mut parser = Parser::new(s);
+        parser.skip_whitespace();
+
+        let key = if parser.peek() == Some('{') {
+            String::new()
+        } else {
+            let key = parser.parse_key()?;
+            parser.skip_whitespace();
+            parser.expect(':')?;
+            parser.skip_whitespace();
+            key
+        };
+
+        if parser.peek() != Some('{') {
+            return Err(parser.error(parser.pos, "expected compound"))
+        }
+
+        let tag = parser.nested(parser.pos, Parser::parse_compound)?;
+        parser.finish()?;
+
+        Ok(NBT { key, tag })
+    }
+}
+
+// parses the tag at the start of `src`, returning it with the number of
+// bytes it took, or the offset and message of an error
+pub(crate) fn parse_prefix(src: &str) -> Result<(Tag, usize), (usize, &'static str)> {
+    let mut parser = Parser::new(src);
+    match parser.parse_tag() {
+        Ok(tag) => Ok((tag, parser.pos)),
+        Err(e) => Err((e.offset, e.message)),
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    // how many lists and compounds enclose the position
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0, depth: 0, max_depth: Limits::default().max_depth }
+    }
+
+    fn error(&self, offset: usize, message: &'static str) -> Error {
+        Error::new(self.src, offset, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            },
+            Some(_) => Err(self.error(self.pos, expected_message(expected))),
+            None => Err(self.error(self.pos, "unexpected end of input")),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error(self.pos, "trailing characters after tag")),
+        }
+    }
+
+    // parses a list or compound, starting at `start`, one level deeper
+    fn nested(&mut self, start: usize, parse: fn(&mut Self) -> Result<Tag, Error>) -> Result<Tag, Error> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(start, "tags nested too deep"))
+        }
+        self.depth += 1;
+        let tag = parse(self);
+        self.depth -= 1;
+        tag
+    }
+
+    fn parse_tag(&mut self) -> Result<Tag, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(self.pos, Self::parse_compound),
+            Some('[') => self.parse_list(),
+            Some('"') | Some('\'') => Ok(Tag::String(self.parse_quoted()?)),
+            Some(_) => {
+                let start = self.pos;
+                let token = self.parse_unquoted();
+                if token.is_empty() {
+                    return Err(self.error(start, "unexpected character"))
+                }
+                parse_scalar(token).map_err(|message| self.error(start, message))
+            },
+            None => Err(self.error(self.pos, "unexpected end of input")),
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<Tag, Error> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut m = Compound::new();
+
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Tag::Compound(m))
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+            let key = self.parse_key()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+
+            let tag = self.parse_tag()?;
+
+            if m.contains_key(&key) {
+                return Err(self.error(start, "duplicate key in compound"))
+            }
+            m.insert(key, tag);
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => break Ok(Tag::Compound(m)),
+                Some(c) => break Err(self.error(self.pos - c.len_utf8(), "expected ',' or '}'")),
+                None => break Err(self.error(self.pos, "unexpected end of input")),
+            }
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Tag, Error> {
+        let start = self.pos;
+        self.expect('[')?;
+
+        if let Some(prefix) = self.array_prefix() {
+            return self.parse_array(prefix)
+        }
+
+        self.nested(start, Self::parse_elements)
+    }
+
+    // the rest of a list, past its opening bracket
+    fn parse_elements(&mut self) -> Result<Tag, Error> {
+        self.skip_whitespace();
+
+        let mut list = Vec::new();
+
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Tag::List(0, list))
+        }
+
+        if let Some(kind) = self.list_kind()? {
+            self.skip_whitespace();
+            self.expect(']')?;
+            return Ok(Tag::List(kind, list))
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+            let tag = self.parse_tag()?;
+
+            if list.first().map(Tag::kind).unwrap_or(tag.kind()) != tag.kind() {
+                return Err(self.error(start, "list elements must be of the same kind"))
+            }
+            list.push(tag);
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => break Ok(Tag::list(list)),
+                Some(c) => break Err(self.error(self.pos - c.len_utf8(), "expected ',' or ']'")),
+                None => break Err(self.error(self.pos, "unexpected end of input")),
+            }
+        }
+    }
+
+    // checks for the kind of an empty list, as in `[10;]`, consuming it
+    fn list_kind(&mut self) -> Result<Option<i8>, Error> {
+        let rest = &self.src[self.pos..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 || !rest[digits..].trim_start().starts_with(';') {
+            return Ok(None)
+        }
+
+        match rest[..digits].parse() {
+            Ok(kind @ 0x01..=0x0c) => {
+                self.pos += rest[digits..].find(';').unwrap() + digits + 1;
+                Ok(Some(kind))
+            },
+            _ => Err(self.error(self.pos, "invalid list kind")),
+        }
+    }
+
+    // checks for the `B;`, `I;` or `L;` prefix of an array, consuming it
+    fn array_prefix(&mut self) -> Option<char> {
+        let rest = &self.src[self.pos..];
+        let prefix = rest.chars().next().filter(|c| matches!(c, 'B' | 'I' | 'L'))?;
+        let after = rest[1..].trim_start();
+        if !after.starts_with(';') {
+            return None
+        }
+        self.pos = self.src.len() - after.len() + 1;
+        Some(prefix)
+    }
+
+    fn parse_array(&mut self, prefix: char) -> Result<Tag, Error> {
+        self.skip_whitespace();
+
+        let mut array = match prefix {
+            'B' => Tag::ByteArray(Vec::new()),
+            'I' => Tag::IntArray(Vec::new()),
+            _ => Tag::LongArray(Vec::new()),
+        };
+
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(array)
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+            match (&mut array, self.parse_tag()?) {
+                (Tag::ByteArray(xs), Tag::Byte(x)) => xs.push(x),
+                (Tag::IntArray(xs), Tag::Int(x)) => xs.push(x),
+                (Tag::LongArray(xs), Tag::Long(x)) => xs.push(x),
+                (Tag::ByteArray(_), _) => return Err(self.error(start, "expected byte in byte array")),
+                (Tag::IntArray(_), _) => return Err(self.error(start, "expected int in int array")),
+                _ => return Err(self.error(start, "expected long in long array")),
+            }
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => break Ok(array),
+                Some(c) => break Err(self.error(self.pos - c.len_utf8(), "expected ',' or ']'")),
+                None => break Err(self.error(self.pos, "unexpected end of input")),
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some('"') | Some('\'') => self.parse_quoted(),
+            Some(_) => {
+                let start = self.pos;
+                let key = self.parse_unquoted();
+                if key.is_empty() {
+                    return Err(self.error(start, "expected key"))
+                }
+                Ok(key.into())
+            },
+            None => Err(self.error(self.pos, "unexpected end of input")),
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_unquoted_char(c) {
+                break
+            }
+            self.pos += 1;
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        let quote = self.bump().unwrap();
+
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some(c) if c == '\\' || c == '"' || c == '\'' => s.push(c),
+                    Some(c) => return Err(self.error(self.pos - c.len_utf8(), "invalid escape sequence")),
+                    None => return Err(self.error(start, "unterminated string")),
+                },
+                Some(c) if c == quote => break Ok(s),
+                Some(c) => s.push(c),
+                None => break Err(self.error(start, "unterminated string")),
+            }
+        }
+    }
+}
+
+fn expected_message(c: char) -> &'static str {
+    match c {
+        ':' => "expected ':'",
+        '{' => "expected '{'",
+        '[' => "expected '['",
+        _ => "unexpected character",
+    }
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '+'
+}
+
+// an optional sign followed by digits
+fn is_integer(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+// an optional sign, a decimal number and an optional exponent
+fn is_decimal(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let mut parts = mantissa.splitn(2, '.');
+    let int = parts.next().unwrap_or("");
+    let frac = parts.next().unwrap_or("");
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.len() + frac.len() == 0 || !digits(int) || !digits(frac) {
+        return false
+    }
+    exponent.map(is_integer).unwrap_or(true)
+}
+
+// a decimal number, or one of the special values printed by `{:?}`
+fn is_float(s: &str) -> bool {
+    is_decimal(s) || matches!(s, "NaN" | "inf" | "-inf" | "+inf")
+}
+
+fn parse_scalar(token: &str) -> Result<Tag, &'static str> {
+    match token {
+        "true" => return Ok(Tag::Byte(1)),
+        "false" => return Ok(Tag::Byte(0)),
+        _ => (),
+    }
+
+    let (body, suffix) = token.split_at(token.len() - 1);
+
+    match suffix {
+        "b" | "B" if is_integer(body) => body
+            .parse()
+            .map(Tag::Byte)
+            .map_err(|_| "byte out of range"),
+        "s" | "S" if is_integer(body) => body
+            .parse()
+            .map(Tag::Short)
+            .map_err(|_| "short out of range"),
+        "l" | "L" if is_integer(body) => body
+            .parse()
+            .map(Tag::Long)
+            .map_err(|_| "long out of range"),
+        "f" | "F" if is_float(body) => body
+            .parse()
+            .map(Tag::Float)
+            .map_err(|_| "invalid float"),
+        "d" | "D" if is_float(body) => body
+            .parse()
+            .map(Tag::Double)
+            .map_err(|_| "invalid double"),
+        _ if is_integer(token) => token
+            .parse()
+            .map(Tag::Int)
+            .map_err(|_| "int out of range"),
+        _ if is_decimal(token) => token
+            .parse()
+            .map(Tag::Double)
+            .map_err(|_| "invalid double"),
+        _ => Ok(Tag::String(token.into())),
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty = f.alternate();
+        Printer { f, pretty, indent: 0 }.tag(self)
+    }
+}
+
+impl fmt::Display for NBT {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty = f.alternate();
+        let mut printer = Printer { f, pretty, indent: 0 };
+
+        if !self.key.is_empty() {
+            printer.key(&self.key)?;
+            printer.f.write_str(if pretty { ": " } else { ":" })?;
+        }
+
+        printer.tag(&self.tag)
+    }
+}
+
+struct Printer<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    pretty: bool,
+    indent: usize,
+}
+
+impl Printer<'_, '_> {
+    fn tag(&mut self, tag: &Tag) -> fmt::Result {
+        match tag {
+            Tag::End => Ok(()),
+            Tag::Byte(x) => write!(self.f, "{}b", x),
+            Tag::Short(x) => write!(self.f, "{}s", x),
+            Tag::Int(x) => write!(self.f, "{}", x),
+            Tag::Long(x) => write!(self.f, "{}L", x),
+            Tag::Float(x) => write!(self.f, "{:?}f", x),
+            Tag::Double(x) => write!(self.f, "{:?}d", x),
+            Tag::ByteArray(xs) => {
+                self.f.write_str("[B;")?;
+                for (i, x) in xs.iter().enumerate() {
+                    self.separator(i)?;
+                    write!(self.f, "{}b", x)?;
+                }
+                self.f.write_str("]")
+            },
+            Tag::IntArray(xs) => {
+                self.f.write_str("[I;")?;
+                for (i, x) in xs.iter().enumerate() {
+                    self.separator(i)?;
+                    write!(self.f, "{}", x)?;
+                }
+                self.f.write_str("]")
+            },
+            Tag::LongArray(xs) => {
+                self.f.write_str("[L;")?;
+                for (i, x) in xs.iter().enumerate() {
+                    self.separator(i)?;
+                    write!(self.f, "{}L", x)?;
+                }
+                self.f.write_str("]")
+            },
+            Tag::String(s) => self.quoted(s),
+            // an empty list keeps its kind, unless it has none
+            Tag::List(kind, xs) if xs.is_empty() && *kind != 0 => write!(self.f, "[{};]", kind),
+            Tag::List(kind, xs) => {
+                // only nest lists of compounds or other lists
+                let nested = !xs.is_empty() && (*kind == 0x09 || *kind == 0x0a);
+
+                self.f.write_str("[")?;
+                if nested && self.pretty {
+                    self.indent += 1;
+                    for (i, x) in xs.iter().enumerate() {
+                        if i > 0 {
+                            self.f.write_str(",")?;
+                        }
+                        self.newline()?;
+                        self.tag(x)?;
+                    }
+                    self.indent -= 1;
+                    self.newline()?;
+                } else {
+                    for (i, x) in xs.iter().enumerate() {
+                        if i > 0 {
+                            self.f.write_str(",")?;
+                            if self.pretty {
+                                self.f.write_str(" ")?;
+                            }
+                        }
+                        self.tag(x)?;
+                    }
+                }
+                self.f.write_str("]")
+            },
+            Tag::Compound(m) => {
+                self.f.write_str("{")?;
+                if m.is_empty() {
+                    return self.f.write_str("}")
+                }
+                self.indent += 1;
+                for (i, (key, tag)) in m.iter().enumerate() {
+                    if i > 0 {
+                        self.f.write_str(",")?;
+                    }
+                    self.newline()?;
+                    self.key(key)?;
+                    self.f.write_str(if self.pretty { ": " } else { ":" })?;
+                    self.tag(tag)?;
+                }
+                self.indent -= 1;
+                self.newline()?;
+                self.f.write_str("}")
+            },
+        }
+    }
+
+    // separates array elements, after the `B;`, `I;` or `L;` prefix
+    fn separator(&mut self, i: usize) -> fmt::Result {
+        if i > 0 {
+            self.f.write_str(",")?;
+        }
+        if self.pretty {
+            self.f.write_str(" ")?;
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self) -> fmt::Result {
+        if !self.pretty {
+            return Ok(())
+        }
+        self.f.write_str("\n")?;
+        for _ in 0..self.indent {
+            self.f.write_str("    ")?;
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, key: &str) -> fmt::Result {
+        if !key.is_empty() && key.chars().all(is_unquoted_char) {
+            self.f.write_str(key)
+        } else {
+            self.quoted(key)
+        }
+    }
+
+    fn quoted(&mut self, s: &str) -> fmt::Result {
+        // prefer the quote that needs no escaping
+        let quote = if s.contains('"') && !s.contains('\'') { '\'' } else { '"' };
+
+        write!(self.f, "{}", quote)?;
+        for c in s.chars() {
+            if c == quote || c == '\\' {
+                write!(self.f, "\\")?;
+            }
+            write!(self.f, "{}", c)?;
+        }
+        write!(self.f, "{}", quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use flate2::bufread::GzDecoder;
+
+    use crate::serialize::DataInput;
+    use crate::serialize::nbt::{NBT, Tag, MAX_DEPTH};
+
+    fn read_nbt_file(path: &str) -> NBT {
+        let f = File::open(path).unwrap();
+        let gz = GzDecoder::new(BufReader::new(f));
+        NBT::read_from(&mut DataInput::new(gz)).unwrap()
+    }
+
+    #[test]
+    fn test_snbt_round_trip() {
+        for path in &["res/player.dat", "res/bigtest.dat"] {
+            let nbt = read_nbt_file(path);
+
+            let compact: NBT = nbt.to_string().parse().unwrap();
+            let pretty: NBT = format!("{:#}", nbt).parse().unwrap();
+
+            assert_eq!(compact.key(), nbt.key());
+            assert_eq!(compact.tag(), nbt.tag());
+            assert_eq!(pretty.tag(), nbt.tag());
+        }
+    }
+
+    #[test]
+    fn test_snbt_scalars() {
+        let tag: Tag = "{a: 1b, b: 2s, c: 3, d: 4L, e: 1.5f, f: 2.5, g: 'x\"y', \"h i\": [B; 1b, -2b], j: NaNf}"
+            .parse()
+            .unwrap();
+        let expected: Tag = "{j:NaNf,\"h i\":[B;1b,-2b],g:\"x\\\"y\",f:2.5d,e:1.5f,d:4L,c:3,b:2s,a:true}"
+            .parse()
+            .unwrap();
+
+        assert_eq!(tag, expected);
+
+        let tag: Tag = "{ints: [I; 1, -2], longs: [L;3L], empty: []}".parse().unwrap();
+        assert_eq!(tag.to_string(), "{ints:[I;1,-2],longs:[L;3L],empty:[]}");
+
+        // empty lists keep their kind
+        let tag = Tag::Compound(vec![("xs".to_string(), Tag::List(0x0a, Vec::new()))].into_iter().collect());
+        assert_eq!(tag.to_string(), "{xs:[10;]}");
+        assert_eq!(format!("{:#}", tag).parse::<Tag>().unwrap(), tag);
+        assert_eq!("[ 3 ; ]".parse::<Tag>().unwrap(), Tag::List(0x03, Vec::new()));
+        assert!("[13;]".parse::<Tag>().is_err());
+        assert!("[3; 1]".parse::<Tag>().is_err());
+    }
+
+    #[test]
+    fn test_snbt_errors() {
+        let err = "{a: 1b,\n  b: [1, 2s]}".parse::<Tag>().unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 10));
+
+        let err = "{a: 300b}".parse::<Tag>().unwrap_err();
+        assert_eq!(err.message(), "byte out of range");
+        assert_eq!(err.offset(), 4);
+
+        let err = "[B; 1b, 2]".parse::<Tag>().unwrap_err();
+        assert_eq!(err.offset(), 8);
+
+        let err = "[I; 1, 2L]".parse::<Tag>().unwrap_err();
+        assert_eq!(err.message(), "expected int in int array");
+
+        assert!("{a: 'unterminated}".parse::<Tag>().is_err());
+        assert!("{a: 1} b".parse::<Tag>().is_err());
+
+        // errors at characters of more than one byte
+        for (src, offset) in [("{a:1é}", 4), ("[1é]", 2), ("[B;1bé]", 5), ("{a:'\\é'}", 5), ("{a:1,é:2}", 5)] {
+            let err = src.parse::<Tag>().unwrap_err();
+            assert_eq!((src, err.offset()), (src, offset));
+            assert_eq!(err.column(), src[..offset].chars().count() + 1);
+        }
+
+        // lists nested as deep as binary NBT allows, and no deeper
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(nested(MAX_DEPTH).parse::<Tag>().is_ok());
+        let err = nested(MAX_DEPTH + 1).parse::<Tag>().unwrap_err();
+        assert_eq!((err.message(), err.offset()), ("tags nested too deep", MAX_DEPTH));
+        assert!("[".repeat(1 << 20).parse::<Tag>().is_err());
+
+        let src = "{a:".repeat(MAX_DEPTH - 1) + "{}" + &"}".repeat(MAX_DEPTH - 1);
+        assert!(src.parse::<NBT>().is_ok());
+        assert!(format!("{{a:{}}}", src).parse::<NBT>().is_err());
+    }
+}
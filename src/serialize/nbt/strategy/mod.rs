@@ -0,0 +1,112 @@
+//! Generators of arbitrary NBT trees, for property tests.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::serialize::nbt::{Compound, NBT, Tag};
+
+// keys and strings, with the characters that modified UTF-8 encodes
+// specially
+fn string() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z0-9_ ]{0,12}",
+        any::<String>().prop_map(|s| s.chars().take(12).collect()),
+        Just("\0\u{e9}\u{20ac}\u{1f600}".to_string()),
+    ]
+}
+
+// every tag but lists and compounds
+fn leaf() -> impl Strategy<Value = Tag> {
+    prop_oneof![
+        any::<i8>().prop_map(Tag::Byte),
+        any::<i16>().prop_map(Tag::Short),
+        any::<i32>().prop_map(Tag::Int),
+        any::<i64>().prop_map(Tag::Long),
+        any::<f32>().prop_map(Tag::Float),
+        any::<f64>().prop_map(Tag::Double),
+        vec(any::<i8>(), 0..16).prop_map(Tag::ByteArray),
+        string().prop_map(Tag::String),
+        vec(any::<i32>(), 0..16).prop_map(Tag::IntArray),
+        vec(any::<i64>(), 0..16).prop_map(Tag::LongArray),
+    ]
+}
+
+/// Arbitrary tags, with lists of a single kind and nested up to 4 deep.
+pub(crate) fn tag() -> impl Strategy<Value = Tag> {
+    leaf().prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(|mut xs| {
+                let kind = xs.first().map_or(0, Tag::kind);
+                xs.retain(|x| x.kind() == kind);
+                Tag::List(kind, xs)
+            }),
+            // an empty list keeps its kind
+            (1..=12i8).prop_map(|kind| Tag::List(kind, Vec::new())),
+            compound(inner).prop_map(Tag::Compound),
+        ]
+    })
+}
+
+fn compound(inner: impl Strategy<Value = Tag>) -> impl Strategy<Value = Compound> {
+    vec((string(), inner), 0..8).prop_map(|entries| entries.into_iter().collect())
+}
+
+/// Arbitrary NBT values, whose root is a compound.
+pub(crate) fn nbt() -> impl Strategy<Value = NBT> {
+    (string(), compound(tag())).prop_map(|(key, m)| NBT::new(key, Tag::Compound(m)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::serialize::{DataInput, DataOutput};
+    use crate::serialize::nbt::{Encoding, Limits, NBT, WriteOptions};
+
+    fn encode(nbt: &NBT, encoding: Encoding) -> Vec<u8> {
+        let mut output = DataOutput::new(Vec::new());
+        nbt.write_with_options(&mut output, &WriteOptions::encoded(encoding)).unwrap();
+        output.into_inner()
+    }
+
+    proptest! {
+        #[test]
+        fn test_strategy_round_trip(nbt in super::nbt()) {
+            for encoding in [Encoding::BigEndian, Encoding::LittleEndian, Encoding::Network] {
+                let buf = encode(&nbt, encoding);
+                let mut input = DataInput::new(&buf[..]);
+                let read = NBT::read_encoded(&mut input, encoding, &Limits::default()).unwrap();
+
+                prop_assert_eq!(input.position(), buf.len() as u64);
+                prop_assert_eq!(read.fingerprint(), nbt.fingerprint());
+                prop_assert_eq!(read, nbt.clone());
+            }
+
+            // and so does SNBT
+            prop_assert_eq!(nbt.to_string().parse::<NBT>().unwrap(), nbt.clone());
+            prop_assert_eq!(format!("{:#}", nbt).parse::<NBT>().unwrap(), nbt);
+        }
+
+        #[test]
+        fn test_strategy_garbage(
+            nbt in super::nbt(),
+            flips in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+            junk in proptest::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let limits = Limits { max_bytes: 1 << 16, ..Limits::default() };
+            for encoding in [Encoding::BigEndian, Encoding::LittleEndian, Encoding::Network] {
+                // a valid value with a few bytes changed, then cut short
+                let mut buf = encode(&nbt, encoding);
+                for (i, b) in &flips {
+                    let i = i.index(buf.len());
+                    buf[i] = *b;
+                }
+                buf.truncate(buf.len() - flips.len().min(buf.len()));
+
+                // decoding may fail, but not panic
+                let _ = NBT::read_encoded(&mut DataInput::new(&buf[..]), encoding, &limits);
+                let _ = NBT::read_encoded(&mut DataInput::new(&junk[..]), encoding, &limits);
+            }
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! Read NBT values as a stream of events, without building a tree.
+//!
+//! A [`Reader`] emits an [`Event`] for every key, value, and start and end of
+//! a list or compound it finds, reusing its buffers for strings and
+//! arrays. Subtrees that are of no interest can be skipped over without
+//! being decoded, which makes looking up a few values in a big file cheap:
+//!
+//! ```no_run
+//! # use std::io;
+//! # use neonmc::serialize::DataInput;
+//! # use neonmc::serialize::nbt::Error;
+//! # use neonmc::serialize::nbt::stream::Reader;
+//! # fn main() -> Result<(), Error> {
+//! # let input = DataInput::new(io::empty());
+//! let mut reader = Reader::new(input);
+//!
+//! if reader.seek(&["Data", "Player", "Pos"])? {
+//!     println!("{}", reader.read_tag()?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Read};
+
+use crate::serialize::DataInput;
+use crate::serialize::nbt::{self, Encoding, Error, ErrorKind, Limits, Tag};
+use crate::serialize::nbt::error::At;
+use crate::serialize::nbt::limits::Budget;
+
+/// An event emitted by a [`Reader`].
+#[derive(Copy, Clone, Debug)]
+pub enum Event<'a> {
+    /// The key of the value that follows, inside a compound.
+    Key(&'a str),
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [u8]),
+    String(&'a str),
+    /// The start of a list of `len` tags of the given `kind`.
+    ListStart { kind: i8, len: usize },
+    /// The start of a compound.
+    CompoundStart,
+    IntArray(&'a [i32]),
+    LongArray(&'a [i64]),
+    /// The end of the innermost list or compound.
+    End,
+}
+
+enum Frame {
+    Compound,
+    List { kind: i8, remaining: usize },
+}
+
+/// Reads NBT values as a stream of events.
+pub struct Reader<R> {
+    input: DataInput<R>,
+    stack: Vec<Frame>,
+    limits: Limits,
+    // the kind of the value to be read next, after a key
+    pending: Option<i8>,
+    started: bool,
+    key: String,
+    string: String,
+    bytes: Vec<u8>,
+    ints: Vec<i32>,
+    longs: Vec<i64>,
+}
+
+impl<R> Reader<R> {
+    /// Creates a new event reader, for the NBT value at the start of `input`.
+    pub fn new(input: DataInput<R>) -> Self {
+        Self::with_limits(input, Limits::default())
+    }
+
+    /// Creates a new event reader that fails as soon as the input goes beyond
+    /// `limits`.
+    pub fn with_limits(input: DataInput<R>, limits: Limits) -> Self {
+        Self {
+            input,
+            stack: Vec::new(),
+            limits,
+            pending: None,
+            started: false,
+            key: String::new(),
+            string: String::new(),
+            bytes: Vec::new(),
+            ints: Vec::new(),
+            longs: Vec::new(),
+        }
+    }
+
+    /// Returns the number of lists and compounds the reader is in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the [`DataInput`] associated with this [`Reader`].
+    pub fn into_inner(self) -> DataInput<R> {
+        self.input
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Reads the next event, or `None` once the root compound has ended.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>, Error> {
+        if let Some(kind) = self.pending.take() {
+            return self.value(kind).map(Some)
+        }
+
+        match self.stack.last_mut() {
+            None if self.started => Ok(None),
+            None => {
+                self.started = true;
+
+                let offset = self.input.position();
+                let kind = self.input.read_byte().at(&self.input)?;
+
+                if kind != 0x0a {
+                    let kind = ErrorKind::UnexpectedTag { expected: 0x0a, found: kind };
+                    return Err(Error::new(kind, Some(offset)))
+                }
+
+                self.input.read_utf_into(&mut self.key).at(&self.input)?;
+                self.pending = Some(0x0a);
+
+                Ok(Some(Event::Key(&self.key)))
+            },
+            Some(Frame::Compound) => {
+                let kind = nbt::read_kind(&mut self.input)?;
+
+                if kind == 0 {
+                    self.stack.pop();
+                    return Ok(Some(Event::End))
+                }
+
+                self.input.read_utf_into(&mut self.key).at(&self.input)?;
+                self.pending = Some(kind);
+
+                Ok(Some(Event::Key(&self.key)))
+            },
+            Some(Frame::List { kind, remaining }) => {
+                if *remaining == 0 {
+                    self.stack.pop();
+                    return Ok(Some(Event::End))
+                }
+
+                *remaining -= 1;
+                let kind = *kind;
+
+                self.value(kind).map(Some)
+            },
+        }
+    }
+
+    fn value(&mut self, kind: i8) -> Result<Event<'_>, Error> {
+        let input = &mut self.input;
+        let mut budget = Budget::new(&self.limits, self.stack.len());
+        Ok(match kind {
+            0x01 => Event::Byte(input.read_byte().at(input)?),
+            0x02 => Event::Short(input.read_short().at(input)?),
+            0x03 => Event::Int(input.read_int().at(input)?),
+            0x04 => Event::Long(input.read_long().at(input)?),
+            0x05 => Event::Float(input.read_float().at(input)?),
+            0x06 => Event::Double(input.read_double().at(input)?),
+            0x07 => {
+                let size = nbt::read_len(input, Encoding::BigEndian)?;
+                budget.check_len(size, input)?;
+                budget.charge(size, input)?;
+                self.bytes.clear();
+                self.bytes.resize(size, 0);
+                input.read_raw(&mut self.bytes).at(input)?;
+                Event::ByteArray(&self.bytes)
+            },
+            0x08 => {
+                input.read_utf_into(&mut self.string).at(input)?;
+                Event::String(&self.string)
+            },
+            0x09 => {
+                budget.enter(input)?;
+                let (kind, len) = nbt::read_list_head(input, Encoding::BigEndian, &budget)?;
+                self.stack.push(Frame::List { kind, remaining: len });
+                Event::ListStart { kind, len }
+            },
+            0x0a => {
+                budget.enter(input)?;
+                self.stack.push(Frame::Compound);
+                Event::CompoundStart
+            },
+            0x0b => {
+                let size = nbt::read_len(input, Encoding::BigEndian)?;
+                budget.check_len(size, input)?;
+                budget.charge(size.saturating_mul(4), input)?;
+                input.read_ints_into(size, &mut self.ints).at(input)?;
+                Event::IntArray(&self.ints)
+            },
+            0x0c => {
+                let size = nbt::read_len(input, Encoding::BigEndian)?;
+                budget.check_len(size, input)?;
+                budget.charge(size.saturating_mul(8), input)?;
+                input.read_longs_into(size, &mut self.longs).at(input)?;
+                Event::LongArray(&self.longs)
+            },
+            _ => return Err(Error::new(ErrorKind::UnknownTag(kind), None)),
+        })
+    }
+
+    // the kind of the value the next event would start
+    fn next_value(&mut self) -> Option<i8> {
+        if let Some(kind) = self.pending.take() {
+            return Some(kind)
+        }
+        match self.stack.last_mut() {
+            Some(Frame::List { kind, remaining }) if *remaining > 0 => {
+                *remaining -= 1;
+                Some(*kind)
+            },
+            _ => None,
+        }
+    }
+
+    /// Skips over the value the next event would start, which must follow a
+    /// [`Event::Key`] or be an element of a list.
+    pub fn skip(&mut self) -> Result<(), Error> {
+        match self.next_value() {
+            Some(kind) => Tag::skip_from(&mut self.input, kind, Encoding::BigEndian, &mut Budget::new(&self.limits, self.stack.len())),
+            None => Err(misuse("no value to skip")),
+        }
+    }
+
+    /// Decodes the value the next event would start into a [`Tag`], which
+    /// must follow a [`Event::Key`] or be an element of a list.
+    pub fn read_tag(&mut self) -> Result<Tag, Error> {
+        match self.next_value() {
+            Some(kind) => Tag::read_from(&mut self.input, kind, Encoding::BigEndian, &mut Budget::new(&self.limits, self.stack.len())),
+            None => Err(misuse("no value to read")),
+        }
+    }
+
+    /// Skips over the rest of the innermost list or compound, including
+    /// its end.
+    pub fn skip_to_end(&mut self) -> Result<(), Error> {
+        let mut budget = Budget::new(&self.limits, self.stack.len());
+        if let Some(kind) = self.pending.take() {
+            Tag::skip_from(&mut self.input, kind, Encoding::BigEndian, &mut budget)?;
+        }
+        match self.stack.pop() {
+            Some(Frame::Compound) => {
+                // the rest of the compound reads like a whole one
+                budget.leave();
+                Tag::skip_from(&mut self.input, 0x0a, Encoding::BigEndian, &mut budget)
+            },
+            Some(Frame::List { kind, remaining }) => {
+                for _ in 0..remaining {
+                    Tag::skip_from(&mut self.input, kind, Encoding::BigEndian, &mut budget)?;
+                }
+                Ok(())
+            },
+            None => Err(misuse("not inside a list or compound")),
+        }
+    }
+
+    /// Looks up the entry at `path` from the current compound, or from the
+    /// root when nothing has been read yet, skipping over any other entries.
+    ///
+    /// Returns `true` if the entry was found, in which case the next event
+    /// starts its value. Otherwise, the reader is left past the entry or the
+    /// compound where the lookup failed.
+    pub fn seek(&mut self, path: &[&str]) -> Result<bool, Error> {
+        if !self.started {
+            self.next_event()?;
+        }
+
+        for key in path {
+            // step into the compound announced by the previous key
+            match self.pending {
+                Some(0x0a) => {
+                    self.next_event()?;
+                },
+                Some(_) => {
+                    self.skip()?;
+                    return Ok(false)
+                },
+                None => (),
+            }
+
+            if !matches!(self.stack.last(), Some(Frame::Compound)) {
+                return Ok(false)
+            }
+
+            loop {
+                let found = match self.next_event()? {
+                    Some(Event::Key(k)) => k == *key,
+                    _ => return Ok(false),
+                };
+                if found {
+                    break
+                }
+                self.skip()?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+// an error for calling a method at the wrong time
+fn misuse(message: &str) -> Error {
+    Error::from_io(io::Error::other(message), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use flate2::bufread::GzDecoder;
+
+    use crate::nbt;
+    use crate::serialize::{DataInput, DataOutput};
+    use crate::serialize::nbt::{NBT, Tag, ErrorKind, Limits};
+    use crate::serialize::nbt::stream::{Event, Reader};
+
+    fn open_nbt_file(path: &str) -> Reader<GzDecoder<BufReader<File>>> {
+        let f = File::open(path).unwrap();
+        Reader::new(DataInput::new(GzDecoder::new(BufReader::new(f))))
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let mut reader = open_nbt_file("res/bigtest.dat");
+
+        match reader.next_event().unwrap() {
+            Some(Event::Key(key)) => assert_eq!(key, "Level"),
+            _ => panic!("expected the root key"),
+        }
+
+        let (mut keys, mut ends) = (0, 0);
+        while let Some(event) = reader.next_event().unwrap() {
+            match event {
+                Event::Key(_) => keys += 1,
+                Event::End => ends += 1,
+                _ => (),
+            }
+        }
+
+        assert_eq!(keys, 21);
+        assert_eq!(ends, 8);
+        assert_eq!(reader.depth(), 0);
+    }
+
+    #[test]
+    fn test_stream_seek() {
+        let mut reader = open_nbt_file("res/bigtest.dat");
+
+        assert!(reader.seek(&["nested compound test", "ham", "value"]).unwrap());
+        match reader.read_tag().unwrap() {
+            Tag::Float(x) => assert_eq!(x, 0.75),
+            _ => panic!("not a float tag"),
+        }
+
+        // back out of the ham compound, and look into its sibling
+        reader.skip_to_end().unwrap();
+        assert!(reader.seek(&["egg", "name"]).unwrap());
+        match reader.next_event().unwrap() {
+            Some(Event::String(name)) => assert_eq!(name, "Eggbert"),
+            _ => panic!("not a string tag"),
+        }
+
+        let mut reader = open_nbt_file("res/player.dat");
+
+        assert!(reader.seek(&["Pos"]).unwrap());
+        match reader.read_tag().unwrap() {
+            Tag::List(_, xs) => assert_eq!(xs.len(), 3),
+            _ => panic!("not a list tag"),
+        }
+
+        let mut reader = open_nbt_file("res/player.dat");
+        assert!(!reader.seek(&["Pos", "x"]).unwrap());
+    }
+
+    #[test]
+    fn test_stream_arrays() {
+        let nbt = NBT::new("", nbt!({ a: [I; 1, -2, 3], b: [I; 4], c: [L; 5L, -6L] })).unwrap();
+        let mut output = DataOutput::new(Vec::new());
+        nbt.write_to(&mut output).unwrap();
+        let buf = output.into_inner();
+
+        let mut reader = Reader::new(DataInput::new(&buf[..]));
+        assert!(reader.seek(&["a"]).unwrap());
+        let first = match reader.next_event().unwrap() {
+            Some(Event::IntArray(xs)) => {
+                assert_eq!(xs, [1, -2, 3]);
+                xs.as_ptr()
+            },
+            _ => panic!("not an int array"),
+        };
+
+        // the next array is decoded into the same buffer
+        assert!(matches!(reader.next_event().unwrap(), Some(Event::Key("b"))));
+        match reader.next_event().unwrap() {
+            Some(Event::IntArray(xs)) => {
+                assert_eq!(xs, [4]);
+                assert_eq!(xs.as_ptr(), first);
+            },
+            _ => panic!("not an int array"),
+        }
+
+        assert!(matches!(reader.next_event().unwrap(), Some(Event::Key("c"))));
+        assert!(matches!(reader.next_event().unwrap(), Some(Event::LongArray(&[5, -6]))));
+    }
+
+    #[test]
+    fn test_stream_limits() {
+        // a list of end tags, which take no bytes
+        let buf = b"\x0a\0\0\x09\0\x01l\0\x7f\xff\xff\xff\0";
+        let mut reader = Reader::with_limits(DataInput::new(&buf[..]), Limits::unlimited());
+        assert!(reader.seek(&["l"]).unwrap());
+        let e = reader.skip().unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UnknownTag(0)));
+        assert_eq!(e.offset(), Some(8));
+
+        // arrays are charged before their buffers grow to the claimed size
+        let buf = b"\x0a\0\0\x0c\0\x01a\x01\0\0\0\0";
+        let limits = Limits { max_bytes: 1024, ..Limits::default() };
+        let mut reader = Reader::with_limits(DataInput::new(&buf[..]), limits);
+        assert!(reader.seek(&["a"]).unwrap());
+        let e = reader.next_event().unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::SizeExceeded(1024)));
+        assert_eq!(reader.longs.capacity(), 0);
+    }
+}